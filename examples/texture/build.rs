@@ -2,50 +2,19 @@ use std::collections::hash_map::DefaultHasher;
 use std::env;
 use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
-use std::process::Command;
 
 fn main() {
     generate_rust_types_from_shader_types();
     compile_shaders();
 }
 
-// xcrun -sdk macosx metal -c shaders.metal -o shaders.air
-// xcrun -sdk macosx metallib shaders.air -o shaders.metallib
+// Compiles shaders.metal and the headers it includes into $OUT_DIR/shaders.metallib.
 fn compile_shaders() {
-    println!("cargo:rerun-if-changed=shaders.metal");
-    println!("cargo:rerun-if-changed=shader_types.h");
-
-    let output = Command::new("xcrun")
-        .arg("-sdk")
-        .arg("macosx")
-        .arg("metal")
-        .args(&["-c", "shaders.metal"])
-        .args(&["-o", "shaders.air"])
-        .spawn()
-        .unwrap()
-        .wait_with_output()
-        .unwrap();
-    if !output.status.success() {
-        panic!(
-            r#"
-stdout: {}
-stderr: {}
-"#,
-            String::from_utf8(output.stdout).unwrap(),
-            String::from_utf8(output.stderr).unwrap()
-        );
+    let mut build = metal::build::ShaderBuild::new("shaders");
+    build.add_source("shaders.metal");
+    if let Err(error) = build.compile() {
+        panic!("{}", error);
     }
-
-    Command::new("xcrun")
-        .arg("-sdk")
-        .arg("macosx")
-        .arg("metallib")
-        .arg("shaders.air")
-        .args(&["-o", "shaders.metallib"])
-        .spawn()
-        .unwrap()
-        .wait()
-        .unwrap();
 }
 
 fn generate_rust_types_from_shader_types() {
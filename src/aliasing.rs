@@ -0,0 +1,459 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use super::*;
+
+use std::{error::Error, fmt, ops::Range};
+
+/// A resource added to an `AliasingPlanner`, in the order resources were added.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct TransientId(pub usize);
+
+/// A resource that only lives from `first_pass` to `last_pass`, both inclusive.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TransientResource {
+    /// From `DeviceRef::heap_buffer_size_and_align` or `heap_texture_size_and_align`.
+    pub size_and_align: MTLSizeAndAlign,
+    pub first_pass: usize,
+    pub last_pass: usize,
+}
+
+impl TransientResource {
+    fn overlaps_in_time(&self, other: &TransientResource) -> bool {
+        self.first_pass <= other.last_pass && other.first_pass <= self.last_pass
+    }
+}
+
+/// Assigns placement heap offsets to transient resources so that resources whose lifetimes
+/// do not overlap share memory.
+///
+/// Resources are placed largest first, each at the lowest offset that satisfies its alignment
+/// and does not overlap any already placed resource that is alive at the same time.
+///
+/// ```
+/// use metal::{AliasingPlanner, MTLSizeAndAlign};
+///
+/// let size = |size| MTLSizeAndAlign { size, align: 256 };
+/// let mut planner = AliasingPlanner::new();
+/// let gbuffer = planner.add(size(4096), 0, 1);
+/// let bloom = planner.add(size(1024), 1, 2);
+/// let tonemap = planner.add(size(2048), 2, 3);
+/// let plan = planner.plan();
+///
+/// // `tonemap` reuses the memory of `gbuffer`, which is dead after pass 1.
+/// assert_eq!(plan.offset(gbuffer), 0);
+/// assert_eq!(plan.offset(bloom), 4096);
+/// assert_eq!(plan.offset(tonemap), 0);
+/// assert_eq!(plan.heap_size(), 5120);
+/// assert_eq!(plan.aliases(tonemap), vec![gbuffer]);
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AliasingPlanner {
+    resources: Vec<TransientResource>,
+}
+
+impl AliasingPlanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `last_pass` is before `first_pass` or the alignment is not a power of two.
+    pub fn add(
+        &mut self,
+        size_and_align: MTLSizeAndAlign,
+        first_pass: usize,
+        last_pass: usize,
+    ) -> TransientId {
+        assert!(
+            first_pass <= last_pass,
+            "resource is dead before it is born"
+        );
+        assert!(
+            size_and_align.align.is_power_of_two(),
+            "alignment must be a power of two"
+        );
+        self.resources.push(TransientResource {
+            size_and_align,
+            first_pass,
+            last_pass,
+        });
+        TransientId(self.resources.len() - 1)
+    }
+
+    pub fn resources(&self) -> &[TransientResource] {
+        &self.resources
+    }
+
+    pub fn plan(&self) -> AliasingPlan {
+        let mut order: Vec<usize> = (0..self.resources.len()).collect();
+        order.sort_by_key(|&index| {
+            let resource = &self.resources[index];
+            (
+                std::cmp::Reverse(resource.size_and_align.size),
+                resource.first_pass,
+                index,
+            )
+        });
+
+        let mut offsets = vec![0; self.resources.len()];
+        let mut placed: Vec<usize> = Vec::with_capacity(order.len());
+        for index in order {
+            let resource = &self.resources[index];
+            let mut occupied: Vec<Range<NSUInteger>> = placed
+                .iter()
+                .filter(|&&other| resource.overlaps_in_time(&self.resources[other]))
+                .map(|&other| {
+                    offsets[other]..offsets[other] + self.resources[other].size_and_align.size
+                })
+                .collect();
+            occupied.sort_by_key(|range| range.start);
+
+            let MTLSizeAndAlign { size, align } = resource.size_and_align;
+            let mut offset = 0;
+            for range in occupied {
+                if offset + size <= range.start {
+                    break;
+                }
+                offset = offset.max(range.end.next_multiple_of(align));
+            }
+            offsets[index] = offset;
+            placed.push(index);
+        }
+
+        let heap_size = self
+            .resources
+            .iter()
+            .zip(&offsets)
+            .map(|(resource, offset)| offset + resource.size_and_align.size)
+            .max()
+            .unwrap_or(0);
+        let heap_alignment = self
+            .resources
+            .iter()
+            .map(|resource| resource.size_and_align.align)
+            .max()
+            .unwrap_or(1);
+        AliasingPlan {
+            resources: self.resources.clone(),
+            offsets,
+            heap_size,
+            heap_alignment,
+        }
+    }
+}
+
+/// Heap offsets chosen by `AliasingPlanner::plan`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AliasingPlan {
+    resources: Vec<TransientResource>,
+    offsets: Vec<NSUInteger>,
+    heap_size: NSUInteger,
+    heap_alignment: NSUInteger,
+}
+
+impl AliasingPlan {
+    /// The size of a placement heap that holds every resource at its offset.
+    pub fn heap_size(&self) -> NSUInteger {
+        self.heap_size
+    }
+
+    /// The largest resource alignment, which the heap's base address must satisfy.
+    pub fn heap_alignment(&self) -> NSUInteger {
+        self.heap_alignment
+    }
+
+    /// The offset to pass to `new_buffer_with_offset` or `new_texture_with_offset`.
+    pub fn offset(&self, id: TransientId) -> NSUInteger {
+        self.offsets[id.0]
+    }
+
+    pub fn resource(&self, id: TransientId) -> &TransientResource {
+        &self.resources[id.0]
+    }
+
+    /// The total size of all resources, i.e. the heap size without aliasing.
+    pub fn unaliased_size(&self) -> NSUInteger {
+        self.resources
+            .iter()
+            .map(|resource| resource.size_and_align.size)
+            .sum()
+    }
+
+    /// Resources that share memory with `id`, in the order they were added.
+    pub fn aliases(&self, id: TransientId) -> Vec<TransientId> {
+        (0..self.resources.len())
+            .filter(|&other| other != id.0 && self.memory_overlaps(id.0, other))
+            .map(TransientId)
+            .collect()
+    }
+
+    /// A descriptor for a placement heap of `heap_size` bytes.
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn heap_descriptor(&self, storage_mode: MTLStorageMode) -> HeapDescriptor {
+        let descriptor = HeapDescriptor::new();
+        descriptor.set_heap_type(MTLHeapType::Placement);
+        descriptor.set_storage_mode(storage_mode);
+        descriptor.set_size(self.heap_size);
+        descriptor
+    }
+
+    fn memory_overlaps(&self, a: usize, b: usize) -> bool {
+        let end = |index: usize| self.offsets[index] + self.resources[index].size_and_align.size;
+        self.offsets[a] < end(b) && self.offsets[b] < end(a)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum AliasingError {
+    /// The resource is used outside the passes it was planned for.
+    OutsideLifetime { resource: TransientId, pass: usize },
+    /// The resource is used after `make_aliasable` was called on it.
+    UseAfterMakeAliasable { resource: TransientId, pass: usize },
+    /// The resource is used after another resource sharing its memory was used.
+    UseAfterAlias {
+        resource: TransientId,
+        aliased_by: TransientId,
+        pass: usize,
+    },
+}
+
+impl fmt::Display for AliasingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AliasingError::OutsideLifetime { resource, pass } => write!(
+                f,
+                "transient resource {} used in pass {} outside its lifetime",
+                resource.0, pass
+            ),
+            AliasingError::UseAfterMakeAliasable { resource, pass } => write!(
+                f,
+                "transient resource {} used in pass {} after it was made aliasable",
+                resource.0, pass
+            ),
+            AliasingError::UseAfterAlias {
+                resource,
+                aliased_by,
+                pass,
+            } => write!(
+                f,
+                "transient resource {} used in pass {} after its memory was reused by {}",
+                resource.0, pass, aliased_by.0
+            ),
+        }
+    }
+}
+
+impl Error for AliasingError {}
+
+/// Checks the uses of resources placed by an `AliasingPlan` while a frame is encoded.
+///
+/// The first use of a resource takes over its memory from any alias used before. Using a
+/// resource again after another one took over its memory, or after it was made aliasable, is
+/// an error, as is using it outside the passes it was planned for. The checks are meant for
+/// debug builds; `debug_use` skips them in release builds.
+#[derive(Clone, Debug)]
+pub struct AliasTracker<'a> {
+    plan: &'a AliasingPlan,
+    aliasable: Vec<bool>,
+    used: Vec<bool>,
+    clobbered_by: Vec<Option<TransientId>>,
+}
+
+impl<'a> AliasTracker<'a> {
+    pub fn new(plan: &'a AliasingPlan) -> Self {
+        AliasTracker {
+            plan,
+            aliasable: vec![false; plan.resources.len()],
+            used: vec![false; plan.resources.len()],
+            clobbered_by: vec![None; plan.resources.len()],
+        }
+    }
+
+    /// Records that `resource` was used in `pass`, which makes it the owner of its memory.
+    pub fn record_use(&mut self, resource: TransientId, pass: usize) -> Result<(), AliasingError> {
+        let planned = self.plan.resource(resource);
+        if pass < planned.first_pass || pass > planned.last_pass {
+            return Err(AliasingError::OutsideLifetime { resource, pass });
+        }
+        if self.aliasable[resource.0] {
+            return Err(AliasingError::UseAfterMakeAliasable { resource, pass });
+        }
+        if let Some(aliased_by) = self.clobbered_by[resource.0] {
+            return Err(AliasingError::UseAfterAlias {
+                resource,
+                aliased_by,
+                pass,
+            });
+        }
+        self.used[resource.0] = true;
+        for other in self.plan.aliases(resource) {
+            if self.used[other.0] {
+                self.clobbered_by[other.0] = Some(resource);
+            }
+        }
+        Ok(())
+    }
+
+    /// Records `ResourceRef::make_aliasable` for `resource`.
+    pub fn make_aliasable(&mut self, resource: TransientId) {
+        self.aliasable[resource.0] = true;
+    }
+
+    /// Calls `record_use` in debug builds and panics on error.
+    #[track_caller]
+    pub fn debug_use(&mut self, resource: TransientId, pass: usize) {
+        if cfg!(debug_assertions) {
+            if let Err(error) = self.record_use(resource, pass) {
+                panic!("{}", error);
+            }
+        }
+    }
+
+    /// Calls `make_aliasable` on the resource and records it in debug builds.
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn make_resource_aliasable(&mut self, id: TransientId, resource: &ResourceRef) {
+        resource.make_aliasable();
+        if cfg!(debug_assertions) {
+            self.make_aliasable(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Rng;
+
+    fn size(size: NSUInteger, align: NSUInteger) -> MTLSizeAndAlign {
+        MTLSizeAndAlign { size, align }
+    }
+
+    /// `a` (passes 0-1) and `b` (passes 2-3) share memory; `c` lives throughout.
+    fn plan() -> (AliasingPlan, [TransientId; 3]) {
+        let mut planner = AliasingPlanner::new();
+        let a = planner.add(size(1024, 256), 0, 1);
+        let b = planner.add(size(1024, 256), 2, 3);
+        let c = planner.add(size(512, 256), 0, 3);
+        let plan = planner.plan();
+        assert_eq!(plan.aliases(a), [b]);
+        (plan, [a, b, c])
+    }
+
+    #[test]
+    fn use_after_alias() {
+        let (plan, [a, b, c]) = plan();
+        let mut tracker = AliasTracker::new(&plan);
+        tracker.record_use(a, 0).unwrap();
+        tracker.record_use(c, 0).unwrap();
+        tracker.record_use(a, 1).unwrap();
+        tracker.record_use(b, 2).unwrap();
+        tracker.record_use(c, 2).unwrap();
+        assert_eq!(
+            tracker.record_use(a, 1),
+            Err(AliasingError::UseAfterAlias {
+                resource: a,
+                aliased_by: b,
+                pass: 1,
+            })
+        );
+        // `b` owns the memory now and can keep using it.
+        tracker.record_use(b, 3).unwrap();
+    }
+
+    #[test]
+    fn first_use_of_unused_alias_is_allowed() {
+        let (plan, [a, b, _]) = plan();
+        let mut tracker = AliasTracker::new(&plan);
+        // `a` was never used, so `b` taking the memory does not clobber it.
+        tracker.record_use(b, 2).unwrap();
+        tracker.record_use(b, 3).unwrap();
+        assert_eq!(
+            tracker.record_use(a, 3),
+            Err(AliasingError::OutsideLifetime {
+                resource: a,
+                pass: 3
+            })
+        );
+    }
+
+    #[test]
+    fn use_after_make_aliasable() {
+        let (plan, [a, _, c]) = plan();
+        let mut tracker = AliasTracker::new(&plan);
+        tracker.record_use(a, 0).unwrap();
+        tracker.make_aliasable(a);
+        assert_eq!(
+            tracker.record_use(a, 1),
+            Err(AliasingError::UseAfterMakeAliasable {
+                resource: a,
+                pass: 1
+            })
+        );
+        tracker.record_use(c, 1).unwrap();
+    }
+
+    #[test]
+    fn outside_lifetime() {
+        let (plan, [a, b, c]) = plan();
+        let mut tracker = AliasTracker::new(&plan);
+        assert_eq!(
+            tracker.record_use(a, 2),
+            Err(AliasingError::OutsideLifetime {
+                resource: a,
+                pass: 2
+            })
+        );
+        assert_eq!(
+            tracker.record_use(b, 1),
+            Err(AliasingError::OutsideLifetime {
+                resource: b,
+                pass: 1
+            })
+        );
+        tracker.record_use(c, 0).unwrap();
+        tracker.record_use(c, 3).unwrap();
+    }
+
+    #[test]
+    fn placements_never_overlap_in_memory_and_time() {
+        let mut rng = Rng::new(0x9e37_79b9);
+        for _ in 0..200 {
+            let mut planner = AliasingPlanner::new();
+            for _ in 0..1 + rng.below(16) {
+                let first_pass = rng.below(8) as usize;
+                let last_pass = first_pass + rng.below(4) as usize;
+                let align = 1 << rng.below(10);
+                planner.add(size(1 + rng.below(4096), align), first_pass, last_pass);
+            }
+            let plan = planner.plan();
+            let resources = planner.resources();
+            assert!(plan.heap_size() <= plan.unaliased_size() + 512 * resources.len() as u64);
+            for (index, resource) in resources.iter().enumerate() {
+                let id = TransientId(index);
+                let offset = plan.offset(id);
+                let end = offset + resource.size_and_align.size;
+                assert_eq!(offset % resource.size_and_align.align, 0);
+                assert!(end <= plan.heap_size());
+                assert_eq!(plan.heap_alignment() % resource.size_and_align.align, 0);
+                for (other, other_resource) in resources[..index].iter().enumerate() {
+                    let other_offset = plan.offset(TransientId(other));
+                    let other_end = other_offset + other_resource.size_and_align.size;
+                    let memory = offset < other_end && other_offset < end;
+                    assert_eq!(plan.aliases(id).contains(&TransientId(other)), memory);
+                    assert!(
+                        !(memory && resource.overlaps_in_time(other_resource)),
+                        "resources {} and {} overlap",
+                        other,
+                        index
+                    );
+                }
+            }
+        }
+    }
+}
@@ -5,12 +5,16 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use super::{MTLTextureType, NSUInteger};
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use super::MTLTextureType;
+use super::NSUInteger;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use objc::runtime::{NO, YES};
 
 #[repr(u64)]
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MTLDataType {
     None = 0,
 
@@ -107,9 +111,186 @@ pub enum MTLDataType {
     RGB9E5Float = 77,
 }
 
+/// The scalar type the components of a numeric `MTLDataType` are made of.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum DataScalarType {
+    Float,
+    Half,
+    Int,
+    UInt,
+    Short,
+    UShort,
+    Char,
+    UChar,
+    Bool,
+}
+
+impl DataScalarType {
+    pub const fn size(&self) -> NSUInteger {
+        match self {
+            DataScalarType::Float | DataScalarType::Int | DataScalarType::UInt => 4,
+            DataScalarType::Half | DataScalarType::Short | DataScalarType::UShort => 2,
+            DataScalarType::Char | DataScalarType::UChar | DataScalarType::Bool => 1,
+        }
+    }
+}
+
+/// Size, alignment and shape of data types as laid out in MSL buffers.
+///
+/// Three-component vectors occupy four components, and a matrix is stored as an array of
+/// column vectors. The pixel data types (`R8Unorm` ... `RGB9E5Float`) are packed, with an
+/// alignment equal to their size. `Pointer` is a 64-bit device address. Aggregates and
+/// resources have no fixed layout and return `None`.
+impl MTLDataType {
+    /// Scalar type, rows per column and column count of scalars, vectors and matrices.
+    fn numeric_shape(&self) -> Option<(DataScalarType, NSUInteger, NSUInteger)> {
+        use self::DataScalarType as S;
+        use self::MTLDataType as T;
+
+        Some(match *self {
+            T::Float => (S::Float, 1, 1),
+            T::Float2 => (S::Float, 2, 1),
+            T::Float3 => (S::Float, 3, 1),
+            T::Float4 => (S::Float, 4, 1),
+            T::Float2x2 => (S::Float, 2, 2),
+            T::Float2x3 => (S::Float, 3, 2),
+            T::Float2x4 => (S::Float, 4, 2),
+            T::Float3x2 => (S::Float, 2, 3),
+            T::Float3x3 => (S::Float, 3, 3),
+            T::Float3x4 => (S::Float, 4, 3),
+            T::Float4x2 => (S::Float, 2, 4),
+            T::Float4x3 => (S::Float, 3, 4),
+            T::Float4x4 => (S::Float, 4, 4),
+            T::Half => (S::Half, 1, 1),
+            T::Half2 => (S::Half, 2, 1),
+            T::Half3 => (S::Half, 3, 1),
+            T::Half4 => (S::Half, 4, 1),
+            T::Half2x2 => (S::Half, 2, 2),
+            T::Half2x3 => (S::Half, 3, 2),
+            T::Half2x4 => (S::Half, 4, 2),
+            T::Half3x2 => (S::Half, 2, 3),
+            T::Half3x3 => (S::Half, 3, 3),
+            T::Half3x4 => (S::Half, 4, 3),
+            T::Half4x2 => (S::Half, 2, 4),
+            T::Half4x3 => (S::Half, 3, 4),
+            T::Half4x4 => (S::Half, 4, 4),
+            T::Int => (S::Int, 1, 1),
+            T::Int2 => (S::Int, 2, 1),
+            T::Int3 => (S::Int, 3, 1),
+            T::Int4 => (S::Int, 4, 1),
+            T::UInt => (S::UInt, 1, 1),
+            T::UInt2 => (S::UInt, 2, 1),
+            T::UInt3 => (S::UInt, 3, 1),
+            T::UInt4 => (S::UInt, 4, 1),
+            T::Short => (S::Short, 1, 1),
+            T::Short2 => (S::Short, 2, 1),
+            T::Short3 => (S::Short, 3, 1),
+            T::Short4 => (S::Short, 4, 1),
+            T::UShort => (S::UShort, 1, 1),
+            T::UShort2 => (S::UShort, 2, 1),
+            T::UShort3 => (S::UShort, 3, 1),
+            T::UShort4 => (S::UShort, 4, 1),
+            T::Char => (S::Char, 1, 1),
+            T::Char2 => (S::Char, 2, 1),
+            T::Char3 => (S::Char, 3, 1),
+            T::Char4 => (S::Char, 4, 1),
+            T::UChar => (S::UChar, 1, 1),
+            T::UChar2 => (S::UChar, 2, 1),
+            T::UChar3 => (S::UChar, 3, 1),
+            T::UChar4 => (S::UChar, 4, 1),
+            T::Bool => (S::Bool, 1, 1),
+            T::Bool2 => (S::Bool, 2, 1),
+            T::Bool3 => (S::Bool, 3, 1),
+            T::Bool4 => (S::Bool, 4, 1),
+            _ => return None,
+        })
+    }
+
+    /// Size and component count of the packed pixel data types.
+    fn pixel_shape(&self) -> Option<(NSUInteger, NSUInteger)> {
+        use self::MTLDataType as T;
+
+        Some(match *self {
+            T::R8Unorm | T::R8Snorm => (1, 1),
+            T::R16Unorm | T::R16Snorm => (2, 1),
+            T::RG8Unorm | T::RG8Snorm => (2, 2),
+            T::RG16Unorm | T::RG16Snorm => (4, 2),
+            T::RGBA8Unorm | T::RGBA8Unorm_sRGB | T::RGBA8Snorm => (4, 4),
+            T::RGBA16Unorm | T::RGBA16Snorm => (8, 4),
+            T::RGB10A2Unorm => (4, 4),
+            T::RG11B10Float | T::RGB9E5Float => (4, 3),
+            _ => return None,
+        })
+    }
+
+    pub fn size(&self) -> Option<NSUInteger> {
+        if let Some((scalar, rows, columns)) = self.numeric_shape() {
+            return Some(columns * column_size(scalar, rows));
+        }
+        match *self {
+            MTLDataType::Pointer => Some(8),
+            _ => self.pixel_shape().map(|(size, _)| size),
+        }
+    }
+
+    pub fn alignment(&self) -> Option<NSUInteger> {
+        match self.numeric_shape() {
+            Some((scalar, rows, _)) => Some(column_size(scalar, rows)),
+            None => self.size(),
+        }
+    }
+
+    /// `None` for the pixel data types, which are not made of a single scalar type.
+    pub fn scalar_type(&self) -> Option<DataScalarType> {
+        self.numeric_shape().map(|(scalar, _, _)| scalar)
+    }
+
+    /// Components of a scalar or vector, rows of a matrix, or channels of a pixel type.
+    pub fn component_count(&self) -> Option<NSUInteger> {
+        match self.numeric_shape() {
+            Some((_, rows, _)) => Some(rows),
+            None => self.pixel_shape().map(|(_, components)| components),
+        }
+    }
+
+    /// Columns of a matrix; 1 for scalars, vectors and pixel types.
+    pub fn column_count(&self) -> Option<NSUInteger> {
+        match self.numeric_shape() {
+            Some((_, _, columns)) => Some(columns),
+            None => self.pixel_shape().map(|_| 1),
+        }
+    }
+
+    pub fn is_vector(&self) -> bool {
+        matches!(self.numeric_shape(), Some((_, rows, 1)) if rows > 1)
+    }
+
+    pub fn is_matrix(&self) -> bool {
+        matches!(self.numeric_shape(), Some((_, _, columns)) if columns > 1)
+    }
+
+    pub fn is_pixel_type(&self) -> bool {
+        self.pixel_shape().is_some()
+    }
+
+    pub fn is_resource(&self) -> bool {
+        matches!(self, MTLDataType::Texture | MTLDataType::Sampler)
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        *self == MTLDataType::Pointer
+    }
+}
+
+/// Size of one vector of `rows` scalars, with three-component vectors padded to four.
+fn column_size(scalar: DataScalarType, rows: NSUInteger) -> NSUInteger {
+    scalar.size() * if rows == 3 { 4 } else { rows }
+}
+
 #[repr(u64)]
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MTLArgumentType {
     Buffer = 0,
     ThreadgroupMemory = 1,
@@ -122,20 +303,24 @@ pub enum MTLArgumentType {
 #[repr(u64)]
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MTLArgumentAccess {
     ReadOnly = 0,
     ReadWrite = 1,
     WriteOnly = 2,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLStructMember {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLStructMember;
     pub struct StructMember;
     pub struct StructMemberRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl StructMemberRef {
     pub fn name(&self) -> &str {
         unsafe {
@@ -152,23 +337,26 @@ impl StructMemberRef {
         unsafe { msg_send![self, dataType] }
     }
 
-    pub fn struct_type(&self) -> MTLStructType {
+    pub fn struct_type(&self) -> Option<&StructTypeRef> {
         unsafe { msg_send![self, structType] }
     }
 
-    pub fn array_type(&self) -> MTLArrayType {
+    pub fn array_type(&self) -> Option<&ArrayTypeRef> {
         unsafe { msg_send![self, arrayType] }
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLStructMemberArray {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLStructMemberArray;
     pub struct StructMemberArray;
     pub struct StructMemberArrayRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl StructMemberArrayRef {
     pub fn object_at(&self, index: NSUInteger) -> Option<&StructMemberRef> {
         unsafe { msg_send![self, objectAtIndexedSubscript: index] }
@@ -179,14 +367,17 @@ impl StructMemberArrayRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLStructType {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLStructType;
     pub struct StructType;
     pub struct StructTypeRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl StructTypeRef {
     pub fn members(&self) -> &StructMemberArrayRef {
         unsafe { msg_send![self, members] }
@@ -199,14 +390,17 @@ impl StructTypeRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLArrayType {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLArrayType;
     pub struct ArrayType;
     pub struct ArrayTypeRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ArrayTypeRef {
     pub fn array_length(&self) -> NSUInteger {
         unsafe { msg_send![self, arrayLength] }
@@ -220,23 +414,26 @@ impl ArrayTypeRef {
         unsafe { msg_send![self, elementType] }
     }
 
-    pub fn element_struct_type(&self) -> MTLStructType {
+    pub fn element_struct_type(&self) -> Option<&StructTypeRef> {
         unsafe { msg_send![self, elementStructType] }
     }
 
-    pub fn element_array_type(&self) -> MTLArrayType {
+    pub fn element_array_type(&self) -> Option<&ArrayTypeRef> {
         unsafe { msg_send![self, elementArrayType] }
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLArgument {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLArgument;
     pub struct Argument;
     pub struct ArgumentRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ArgumentRef {
     pub fn name(&self) -> &str {
         unsafe {
@@ -300,14 +497,17 @@ impl ArgumentRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLArgumentDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLArgumentDescriptor;
     pub struct ArgumentDescriptor;
     pub struct ArgumentDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ArgumentDescriptor {
     pub fn new<'a>() -> &'a ArgumentDescriptorRef {
         unsafe {
@@ -317,6 +517,7 @@ impl ArgumentDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ArgumentDescriptorRef {
     pub fn set_data_type(&self, ty: MTLDataType) {
         unsafe { msg_send![self, setDataType: ty] }
@@ -338,3 +539,195 @@ impl ArgumentDescriptorRef {
         unsafe { msg_send![self, setTextureType: ty] }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Data type, size, alignment, scalar type, component count and column count.
+    type LayoutRow = (
+        MTLDataType,
+        Option<NSUInteger>,
+        Option<NSUInteger>,
+        Option<DataScalarType>,
+        Option<NSUInteger>,
+        Option<NSUInteger>,
+    );
+
+    const LAYOUTS: &[LayoutRow] = {
+        use self::DataScalarType as S;
+        use self::MTLDataType as T;
+
+        &[
+            (T::Float, Some(4), Some(4), Some(S::Float), Some(1), Some(1)),
+            (
+                T::Float2,
+                Some(8),
+                Some(8),
+                Some(S::Float),
+                Some(2),
+                Some(1),
+            ),
+            (
+                T::Float3,
+                Some(16),
+                Some(16),
+                Some(S::Float),
+                Some(3),
+                Some(1),
+            ),
+            (
+                T::Float4,
+                Some(16),
+                Some(16),
+                Some(S::Float),
+                Some(4),
+                Some(1),
+            ),
+            (
+                T::Float2x2,
+                Some(16),
+                Some(8),
+                Some(S::Float),
+                Some(2),
+                Some(2),
+            ),
+            (
+                T::Float2x3,
+                Some(32),
+                Some(16),
+                Some(S::Float),
+                Some(3),
+                Some(2),
+            ),
+            (
+                T::Float3x3,
+                Some(48),
+                Some(16),
+                Some(S::Float),
+                Some(3),
+                Some(3),
+            ),
+            (
+                T::Float4x2,
+                Some(32),
+                Some(8),
+                Some(S::Float),
+                Some(2),
+                Some(4),
+            ),
+            (
+                T::Float4x3,
+                Some(64),
+                Some(16),
+                Some(S::Float),
+                Some(3),
+                Some(4),
+            ),
+            (
+                T::Float4x4,
+                Some(64),
+                Some(16),
+                Some(S::Float),
+                Some(4),
+                Some(4),
+            ),
+            (T::Half, Some(2), Some(2), Some(S::Half), Some(1), Some(1)),
+            (T::Half3, Some(8), Some(8), Some(S::Half), Some(3), Some(1)),
+            (
+                T::Half2x2,
+                Some(8),
+                Some(4),
+                Some(S::Half),
+                Some(2),
+                Some(2),
+            ),
+            (
+                T::Half3x3,
+                Some(24),
+                Some(8),
+                Some(S::Half),
+                Some(3),
+                Some(3),
+            ),
+            (
+                T::Half4x4,
+                Some(32),
+                Some(8),
+                Some(S::Half),
+                Some(4),
+                Some(4),
+            ),
+            (T::Int3, Some(16), Some(16), Some(S::Int), Some(3), Some(1)),
+            (T::UInt2, Some(8), Some(8), Some(S::UInt), Some(2), Some(1)),
+            (
+                T::Short3,
+                Some(8),
+                Some(8),
+                Some(S::Short),
+                Some(3),
+                Some(1),
+            ),
+            (
+                T::UShort,
+                Some(2),
+                Some(2),
+                Some(S::UShort),
+                Some(1),
+                Some(1),
+            ),
+            (T::Char3, Some(4), Some(4), Some(S::Char), Some(3), Some(1)),
+            (
+                T::UChar4,
+                Some(4),
+                Some(4),
+                Some(S::UChar),
+                Some(4),
+                Some(1),
+            ),
+            (T::Bool, Some(1), Some(1), Some(S::Bool), Some(1), Some(1)),
+            (T::Bool3, Some(4), Some(4), Some(S::Bool), Some(3), Some(1)),
+            (T::R8Unorm, Some(1), Some(1), None, Some(1), Some(1)),
+            (T::R16Snorm, Some(2), Some(2), None, Some(1), Some(1)),
+            (T::RG8Snorm, Some(2), Some(2), None, Some(2), Some(1)),
+            (T::RG16Unorm, Some(4), Some(4), None, Some(2), Some(1)),
+            (T::RGBA8Unorm_sRGB, Some(4), Some(4), None, Some(4), Some(1)),
+            (T::RGBA16Snorm, Some(8), Some(8), None, Some(4), Some(1)),
+            (T::RGB10A2Unorm, Some(4), Some(4), None, Some(4), Some(1)),
+            (T::RG11B10Float, Some(4), Some(4), None, Some(3), Some(1)),
+            (T::RGB9E5Float, Some(4), Some(4), None, Some(3), Some(1)),
+            (T::Pointer, Some(8), Some(8), None, None, None),
+            (T::None, None, None, None, None, None),
+            (T::Struct, None, None, None, None, None),
+            (T::Array, None, None, None, None, None),
+            (T::Texture, None, None, None, None, None),
+            (T::Sampler, None, None, None, None, None),
+        ]
+    };
+
+    #[test]
+    fn data_type_layouts() {
+        for &(data_type, size, alignment, scalar_type, components, columns) in LAYOUTS {
+            assert_eq!(data_type.size(), size, "{:?}", data_type);
+            assert_eq!(data_type.alignment(), alignment, "{:?}", data_type);
+            assert_eq!(data_type.scalar_type(), scalar_type, "{:?}", data_type);
+            assert_eq!(data_type.component_count(), components, "{:?}", data_type);
+            assert_eq!(data_type.column_count(), columns, "{:?}", data_type);
+        }
+    }
+
+    #[test]
+    fn data_type_classification() {
+        assert!(MTLDataType::Float3.is_vector());
+        assert!(!MTLDataType::Float.is_vector());
+        assert!(!MTLDataType::Float3x3.is_vector());
+        assert!(MTLDataType::Half2x4.is_matrix());
+        assert!(!MTLDataType::Half4.is_matrix());
+        assert!(MTLDataType::RGB10A2Unorm.is_pixel_type());
+        assert!(!MTLDataType::UChar4.is_pixel_type());
+        assert!(MTLDataType::Texture.is_resource());
+        assert!(MTLDataType::Sampler.is_resource());
+        assert!(!MTLDataType::Pointer.is_resource());
+        assert!(MTLDataType::Pointer.is_pointer());
+    }
+}
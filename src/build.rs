@@ -0,0 +1,558 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Offline compilation of MSL sources into a `.metallib`, for use from build scripts.
+//!
+//! ```no_run
+//! // build.rs
+//! let mut build = metal::build::ShaderBuild::new("shaders");
+//! build.add_source("shaders.metal");
+//! build.define("USE_TEXTURES", None);
+//! let output = build.compile().unwrap_or_else(|error| panic!("{}", error));
+//! println!("cargo:rustc-env=SHADERS_METALLIB={}", output.library.display());
+//! ```
+
+use crate::{
+    preprocessor::{include_directives, normalize},
+    CompilerDiagnostic, MTLLanguageVersion,
+};
+
+use std::{
+    env,
+    error::Error,
+    ffi::OsString,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+/// The result of a successful `ShaderBuild::compile`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ShaderBuildOutput {
+    pub library: PathBuf,
+    /// The sources and every file they include.
+    pub dependencies: Vec<PathBuf>,
+    /// Whether the library from a previous build was reused because nothing changed.
+    pub cached: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BuildError {
+    NoSources,
+    /// No output directory was set and `OUT_DIR` is not defined.
+    MissingOutDir,
+    Io {
+        path: PathBuf,
+        kind: io::ErrorKind,
+    },
+    /// The compiler command could not be started.
+    Spawn {
+        program: PathBuf,
+        kind: io::ErrorKind,
+    },
+    /// `metal` rejected a source file; `diagnostics` is parsed from `log`.
+    Compile {
+        source: PathBuf,
+        diagnostics: Vec<CompilerDiagnostic>,
+        log: String,
+    },
+    /// `metallib` failed to link the compiled sources.
+    Link {
+        log: String,
+    },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::NoSources => write!(f, "no shader sources were added"),
+            BuildError::MissingOutDir => write!(f, "no output directory and OUT_DIR is not set"),
+            BuildError::Io { path, kind } => {
+                write!(f, "failed to access {}: {:?}", path.display(), kind)
+            }
+            BuildError::Spawn { program, kind } => {
+                write!(f, "failed to run {}: {:?}", program.display(), kind)
+            }
+            BuildError::Compile {
+                source,
+                diagnostics,
+                log,
+            } => {
+                write!(f, "failed to compile {}", source.display())?;
+                if diagnostics.is_empty() {
+                    return write!(f, "\n{}", log.trim_end());
+                }
+                for diagnostic in diagnostics {
+                    write!(f, "\n{}", diagnostic)?;
+                }
+                Ok(())
+            }
+            BuildError::Link { log } => write!(f, "failed to link metallib\n{}", log.trim_end()),
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+/// Compiles a set of `.metal` files into one `.metallib` with `xcrun metal` and
+/// `xcrun metallib`.
+///
+/// The library is rebuilt only when a source, an included file or a setting changed since
+/// the last build in the same output directory. Unless disabled with `set_cargo_metadata`,
+/// `compile` prints `cargo:rerun-if-changed` for every source and include it found.
+///
+/// Includes are found without evaluating conditionals. Quoted includes are looked up next to
+/// the including file and then in the include directories, angle-bracket includes only in the
+/// include directories. An include that resolves to no file, such as `<metal_stdlib>` or one
+/// inside `#if 0`, is left to the compiler and not tracked.
+#[derive(Clone, Debug)]
+pub struct ShaderBuild {
+    name: String,
+    sources: Vec<PathBuf>,
+    include_dirs: Vec<PathBuf>,
+    defines: Vec<(String, Option<String>)>,
+    sdk: String,
+    language_version: Option<MTLLanguageVersion>,
+    flags: Vec<String>,
+    compiler: PathBuf,
+    out_dir: Option<PathBuf>,
+    cargo_metadata: bool,
+}
+
+impl ShaderBuild {
+    /// Builds `<name>.metallib` for the `macosx` SDK with `xcrun`.
+    pub fn new(name: &str) -> Self {
+        ShaderBuild {
+            name: name.to_owned(),
+            sources: Vec::new(),
+            include_dirs: Vec::new(),
+            defines: Vec::new(),
+            sdk: "macosx".to_owned(),
+            language_version: None,
+            flags: Vec::new(),
+            compiler: PathBuf::from("xcrun"),
+            out_dir: None,
+            cargo_metadata: true,
+        }
+    }
+
+    pub fn add_source<P: AsRef<Path>>(&mut self, path: P) {
+        self.sources.push(path.as_ref().to_owned());
+    }
+
+    /// Adds an `-I` directory, also used to find the includes to watch.
+    pub fn add_include_dir<P: AsRef<Path>>(&mut self, path: P) {
+        self.include_dirs.push(path.as_ref().to_owned());
+    }
+
+    /// Adds `-D name` or `-D name=value`.
+    pub fn define(&mut self, name: &str, value: Option<&str>) {
+        self.defines
+            .push((name.to_owned(), value.map(ToOwned::to_owned)));
+    }
+
+    /// Selects the SDK passed to `xcrun -sdk`, e.g. `macosx` or `iphoneos`.
+    pub fn set_sdk(&mut self, sdk: &str) {
+        self.sdk = sdk.to_owned();
+    }
+
+    pub fn set_language_version(&mut self, version: MTLLanguageVersion) {
+        self.language_version = Some(version);
+    }
+
+    /// Passes an extra argument to `metal`, e.g. `-gline-tables-only`.
+    pub fn add_flag(&mut self, flag: &str) {
+        self.flags.push(flag.to_owned());
+    }
+
+    /// Replaces `xcrun`. The program is invoked as `<program> -sdk <sdk> metal ...` and
+    /// `<program> -sdk <sdk> metallib ...`, so a script standing in for `xcrun` lets the build
+    /// logic run where the Metal toolchain is not installed.
+    pub fn set_compiler<P: AsRef<Path>>(&mut self, program: P) {
+        self.compiler = program.as_ref().to_owned();
+    }
+
+    /// Sets where intermediate and final files go. Defaults to `OUT_DIR`.
+    pub fn set_out_dir<P: AsRef<Path>>(&mut self, path: P) {
+        self.out_dir = Some(path.as_ref().to_owned());
+    }
+
+    pub fn set_cargo_metadata(&mut self, enabled: bool) {
+        self.cargo_metadata = enabled;
+    }
+
+    pub fn compile(&self) -> Result<ShaderBuildOutput, BuildError> {
+        if self.sources.is_empty() {
+            return Err(BuildError::NoSources);
+        }
+        let out_dir = match self.out_dir {
+            Some(ref out_dir) => out_dir.clone(),
+            None => env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or(BuildError::MissingOutDir)?,
+        };
+
+        let dependencies = self.dependencies()?;
+        if self.cargo_metadata {
+            for dependency in &dependencies {
+                println!("cargo:rerun-if-changed={}", dependency.display());
+            }
+        }
+
+        let library = out_dir.join(format!("{}.metallib", self.name));
+        let hash_file = out_dir.join(format!("{}.metallib.hash", self.name));
+        let hash = format!("{:016x}", self.hash(&dependencies)?);
+        let cached = library.is_file()
+            && fs::read_to_string(&hash_file).ok().as_deref() == Some(hash.as_str());
+        if cached {
+            return Ok(ShaderBuildOutput {
+                library,
+                dependencies,
+                cached,
+            });
+        }
+
+        fs::create_dir_all(&out_dir).map_err(|error| io_error(&out_dir, error))?;
+        let mut air_files = Vec::with_capacity(self.sources.len());
+        for (index, source) in self.sources.iter().enumerate() {
+            let stem = source
+                .file_stem()
+                .map_or_else(|| "shader".into(), |stem| stem.to_string_lossy());
+            let air = out_dir.join(format!("{}-{}-{}.air", self.name, index, stem));
+            let output = self.run(self.compile_args(source, &air))?;
+            if !output.status.success() {
+                let log = command_log(&output);
+                return Err(BuildError::Compile {
+                    source: source.clone(),
+                    diagnostics: CompilerDiagnostic::parse_log(&log),
+                    log,
+                });
+            }
+            air_files.push(air);
+        }
+
+        let mut link_args = vec![OsString::from("metallib")];
+        link_args.extend(air_files.iter().map(|air| air.as_os_str().to_owned()));
+        link_args.push("-o".into());
+        link_args.push(library.as_os_str().to_owned());
+        let output = self.run(link_args)?;
+        if !output.status.success() {
+            return Err(BuildError::Link {
+                log: command_log(&output),
+            });
+        }
+
+        fs::write(&hash_file, hash).map_err(|error| io_error(&hash_file, error))?;
+        Ok(ShaderBuildOutput {
+            library,
+            dependencies,
+            cached: false,
+        })
+    }
+
+    fn dependencies(&self) -> Result<Vec<PathBuf>, BuildError> {
+        let mut dependencies: Vec<PathBuf> = Vec::new();
+        for source in &self.sources {
+            let source = normalize(source);
+            if !dependencies.contains(&source) {
+                dependencies.push(source);
+            }
+        }
+
+        // Scans each file once, appending newly found includes to the end of the list.
+        let mut index = 0;
+        while index < dependencies.len() {
+            let path = dependencies[index].clone();
+            index += 1;
+            let contents = fs::read_to_string(&path).map_err(|error| io_error(&path, error))?;
+            for (name, angled) in include_directives(&contents) {
+                let local = path.parent().filter(|_| !angled).map(|dir| dir.join(&name));
+                let searched = self.include_dirs.iter().map(|dir| dir.join(&name));
+                let resolved = local
+                    .into_iter()
+                    .chain(searched)
+                    .map(|candidate| normalize(&candidate))
+                    .find(|candidate| candidate.is_file());
+                if let Some(resolved) = resolved {
+                    if !dependencies.contains(&resolved) {
+                        dependencies.push(resolved);
+                    }
+                }
+            }
+        }
+        Ok(dependencies)
+    }
+
+    /// FNV-1a over the settings and the contents of every dependency. Unlike `DefaultHasher`
+    /// it is stable across Rust releases, so caches survive toolchain updates.
+    fn hash(&self, dependencies: &[PathBuf]) -> Result<u64, BuildError> {
+        let mut hash = 0xcbf2_9ce4_8422_2325u64;
+        let mut feed = |bytes: &[u8]| {
+            for &byte in bytes.iter().chain(&[0xff]) {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+
+        feed(self.compiler.to_string_lossy().as_bytes());
+        feed(self.sdk.as_bytes());
+        for source in &self.sources {
+            feed(source.to_string_lossy().as_bytes());
+            for arg in self.compile_args(source, Path::new("")) {
+                feed(arg.to_string_lossy().as_bytes());
+            }
+        }
+        for dependency in dependencies {
+            let contents = fs::read(dependency).map_err(|error| io_error(dependency, error))?;
+            feed(dependency.to_string_lossy().as_bytes());
+            feed(&contents);
+        }
+        Ok(hash)
+    }
+
+    fn compile_args(&self, source: &Path, air: &Path) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["metal".into(), "-c".into()];
+        if let Some(version) = self.language_version {
+            let platform = if self.sdk.starts_with("macosx") {
+                "macos"
+            } else {
+                "ios"
+            };
+            let (major, minor) = (version as u64 >> 16, version as u64 & 0xffff);
+            args.push(format!("-std={}-metal{}.{}", platform, major, minor).into());
+        }
+        for dir in &self.include_dirs {
+            args.push("-I".into());
+            args.push(dir.as_os_str().to_owned());
+        }
+        for (name, value) in &self.defines {
+            args.push(match value {
+                Some(value) => format!("-D{}={}", name, value).into(),
+                None => format!("-D{}", name).into(),
+            });
+        }
+        args.extend(self.flags.iter().map(OsString::from));
+        args.push(source.as_os_str().to_owned());
+        args.push("-o".into());
+        args.push(air.as_os_str().to_owned());
+        args
+    }
+
+    fn run(&self, args: Vec<OsString>) -> Result<Output, BuildError> {
+        Command::new(&self.compiler)
+            .arg("-sdk")
+            .arg(&self.sdk)
+            .args(args)
+            .output()
+            .map_err(|error| BuildError::Spawn {
+                program: self.compiler.clone(),
+                kind: error.kind(),
+            })
+    }
+}
+
+fn command_log(output: &Output) -> String {
+    let mut log = String::from_utf8_lossy(&output.stderr).into_owned();
+    log.push_str(&String::from_utf8_lossy(&output.stdout));
+    log
+}
+
+fn io_error(path: &Path, error: io::Error) -> BuildError {
+    BuildError::Io {
+        path: path.to_owned(),
+        kind: error.kind(),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::DiagnosticSeverity;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Stands in for `xcrun`: logs its arguments, fails `metal` on sources containing `#error`
+    /// with a clang-style diagnostic, and otherwise writes the file named after `-o`.
+    const STUB_COMPILER: &str = r#"#!/bin/sh
+echo "$@" >> "$(dirname "$0")/calls.log"
+eval "output=\${$#}"
+if [ "$3" = metal ]; then
+    for arg; do
+        case "$arg" in *.metal) source=$arg ;; esac
+    done
+    if grep -q '#error' "$source"; then
+        echo "$source:2:5: error: stub failure" >&2
+        echo "1 error generated." >&2
+        exit 1
+    fi
+fi
+echo built > "$output"
+"#;
+
+    /// A scratch directory holding the stub compiler.
+    struct Fixture {
+        dir: TempDir,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let dir = TempDir::new(&format!("build-{}", name));
+            fs::create_dir_all(dir.path().join("include")).unwrap();
+            let compiler = dir.write("xcrun", STUB_COMPILER);
+            fs::set_permissions(&compiler, fs::Permissions::from_mode(0o755)).unwrap();
+            Fixture { dir }
+        }
+
+        fn build(&self) -> ShaderBuild {
+            let mut build = ShaderBuild::new("shaders");
+            build.set_compiler(self.dir.path().join("xcrun"));
+            build.set_out_dir(self.dir.path().join("out"));
+            build.set_cargo_metadata(false);
+            build
+        }
+
+        fn calls(&self) -> Vec<String> {
+            fs::read_to_string(self.dir.path().join("calls.log"))
+                .unwrap_or_default()
+                .lines()
+                .map(ToOwned::to_owned)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn passes_settings_to_compiler_and_linker() {
+        let fixture = Fixture::new("args");
+        let source = fixture.dir.write("a.metal", "kernel void a() {}");
+        let mut build = fixture.build();
+        build.add_source(&source);
+        build.add_include_dir(fixture.dir.path().join("include"));
+        build.define("USE_TEXTURES", None);
+        build.define("COUNT", Some("4"));
+        build.set_sdk("iphoneos");
+        build.set_language_version(MTLLanguageVersion::V2_3);
+        build.add_flag("-gline-tables-only");
+
+        let output = build.compile().unwrap();
+        let out = fixture.dir.path().join("out");
+        assert_eq!(output.library, out.join("shaders.metallib"));
+        assert!(!output.cached);
+        assert!(output.library.is_file());
+        let air = out.join("shaders-0-a.air");
+        assert_eq!(
+            fixture.calls(),
+            [
+                format!(
+                    "-sdk iphoneos metal -c -std=ios-metal2.3 -I {} -DUSE_TEXTURES -DCOUNT=4 \
+                     -gline-tables-only {} -o {}",
+                    fixture.dir.path().join("include").display(),
+                    source.display(),
+                    air.display()
+                ),
+                format!(
+                    "-sdk iphoneos metallib {} -o {}",
+                    air.display(),
+                    output.library.display()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reuses_library_until_an_input_changes() {
+        let fixture = Fixture::new("cache");
+        let header = fixture.dir.write("include/common.h", "#define VALUE 1");
+        let source = fixture.dir.write(
+            "a.metal",
+            "#include <metal_stdlib>\n#include <common.h>\nkernel void a() {}",
+        );
+        let mut build = fixture.build();
+        build.add_source(&source);
+        build.add_include_dir(fixture.dir.path().join("include"));
+
+        let first = build.compile().unwrap();
+        assert!(!first.cached);
+        assert_eq!(first.dependencies, [source.clone(), header.clone()]);
+        assert_eq!(fixture.calls().len(), 2);
+
+        let second = build.compile().unwrap();
+        assert!(second.cached);
+        assert_eq!(fixture.calls().len(), 2);
+
+        fs::write(&header, "#define VALUE 2").unwrap();
+        assert!(!build.compile().unwrap().cached);
+        assert_eq!(fixture.calls().len(), 4);
+
+        build.define("EXTRA", None);
+        assert!(!build.compile().unwrap().cached);
+        assert!(build.compile().unwrap().cached);
+        assert_eq!(fixture.calls().len(), 6);
+    }
+
+    #[test]
+    fn unresolved_includes_are_not_tracked() {
+        let fixture = Fixture::new("unresolved");
+        let source = fixture.dir.write(
+            "a.metal",
+            "#if 0\n#include \"generated.h\"\n#endif\nkernel void a() {}",
+        );
+        let mut build = fixture.build();
+        build.add_source(&source);
+
+        let output = build.compile().unwrap();
+        assert_eq!(output.dependencies, [source]);
+
+        // The header is picked up once it exists.
+        let header = fixture.dir.write("generated.h", "");
+        assert_eq!(build.compile().unwrap().dependencies[1], header);
+    }
+
+    #[test]
+    fn compile_failure_reports_diagnostics() {
+        let fixture = Fixture::new("failure");
+        let source = fixture
+            .dir
+            .write("bad.metal", "kernel void a() {\n    #error\n}");
+        let mut build = fixture.build();
+        build.add_source(&source);
+
+        match build.compile() {
+            Err(BuildError::Compile {
+                source: failed,
+                diagnostics,
+                log,
+            }) => {
+                assert_eq!(failed, source);
+                assert!(log.contains("stub failure"));
+                assert_eq!(diagnostics.len(), 1);
+                assert_eq!(diagnostics[0].file, source.display().to_string());
+                assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, Some(5)));
+                assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+                assert_eq!(diagnostics[0].message, "stub failure");
+            }
+            other => panic!("expected a compile error, got {:?}", other),
+        }
+        assert_eq!(fixture.calls().len(), 1);
+        assert!(!fixture.dir.path().join("out/shaders.metallib").exists());
+    }
+
+    #[test]
+    fn rejects_empty_and_missing_sources() {
+        let fixture = Fixture::new("sources");
+        let mut build = fixture.build();
+        assert_eq!(build.compile(), Err(BuildError::NoSources));
+
+        let missing = fixture.dir.path().join("missing.metal");
+        build.add_source(&missing);
+        assert_eq!(
+            build.compile(),
+            Err(BuildError::Io {
+                path: missing,
+                kind: io::ErrorKind::NotFound,
+            })
+        );
+    }
+}
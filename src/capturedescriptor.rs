@@ -5,8 +5,10 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use super::*;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use std::path::Path;
 
 /// https://developer.apple.com/documentation/metal/mtlcapturedestination?language=objc
@@ -19,14 +21,17 @@ pub enum MTLCaptureDestination {
 }
 
 /// https://developer.apple.com/documentation/metal/mtlcapturedescriptor
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLCaptureDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLCaptureDescriptor;
     pub struct CaptureDescriptor;
     pub struct CaptureDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CaptureDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -36,6 +41,7 @@ impl CaptureDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CaptureDescriptorRef {
     /// https://developer.apple.com/documentation/metal/mtlcapturedescriptor/3237248-captureobject
     pub fn set_capture_device(&self, device: &DeviceRef) {
@@ -6,7 +6,6 @@
 // copied, modified, or distributed except according to those terms.
 
 use super::*;
-use std::ffi::CStr;
 
 pub enum MTLCaptureScope {}
 
@@ -71,7 +70,7 @@ impl CaptureManagerRef {
     }
 
     /// https://developer.apple.com/documentation/metal/mtlcapturemanager/3237259-startcapture
-    pub fn start_capture(&self, descriptor: &CaptureDescriptorRef) -> Result<(), String> {
+    pub fn start_capture(&self, descriptor: &CaptureDescriptorRef) -> Result<(), MetalError> {
         unsafe {
             try_objc! { err =>
                 msg_send![self, startCaptureWithDescriptor: descriptor
@@ -5,8 +5,10 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use super::*;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use block::Block;
 
 #[repr(u32)]
@@ -45,16 +47,20 @@ pub enum MTLDispatchType {
     Concurrent = 1,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 type CommandBufferHandler<'a> = Block<(&'a CommandBufferRef,), ()>;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLCommandBuffer {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLCommandBuffer;
     pub struct CommandBuffer;
     pub struct CommandBufferRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CommandBufferRef {
     pub fn label(&self) -> &str {
         unsafe {
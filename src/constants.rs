@@ -149,3 +149,687 @@ pub enum MTLPixelFormat {
     BGR10_XR = 554,
     BGR10_XR_SRGB = 555,
 }
+
+/// The numeric interpretation of the components of a pixel format.
+///
+/// Combined depth/stencil formats report the type of their depth component.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum PixelFormatComponentType {
+    /// Unsigned normalized, including the sRGB and extended range (`*_XR`) formats.
+    Unorm,
+    /// Signed normalized.
+    Snorm,
+    /// Unsigned integer.
+    Uint,
+    /// Signed integer.
+    Sint,
+    /// Floating point, including the shared-exponent and unsigned float formats.
+    Float,
+}
+
+/// How the components of a pixel format are laid out in memory.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum PixelFormatLayout {
+    /// Every component occupies its own byte-aligned storage.
+    Ordinary,
+    /// All components are packed together into a single 16, 32 or 64-bit value.
+    Packed,
+    /// Texels are encoded together in fixed-size compressed blocks.
+    Compressed,
+    /// Horizontally adjacent texels share their chroma samples (4:2:2 subsampling).
+    Subsampled,
+}
+
+bitflags! {
+    /// The aspects of a texture that a pixel format stores.
+    pub struct PixelFormatAspects: u32 {
+        const Color = 1 << 0;
+        const Depth = 1 << 1;
+        const Stencil = 1 << 2;
+    }
+}
+
+/// Static properties of a pixel format.
+///
+/// Uncompressed formats are described as blocks of 1x1 texels, except for the
+/// subsampled 4:2:2 formats which use 2x1 blocks.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct PixelFormatInfo {
+    /// The number of bytes used by one block of texels.
+    pub bytes_per_block: u32,
+    /// The width of one block, in texels.
+    pub block_width: u32,
+    /// The height of one block, in texels.
+    pub block_height: u32,
+    /// The number of components (channels) stored by the format.
+    pub components: u32,
+    pub component_type: PixelFormatComponentType,
+    /// Whether color values are stored with the sRGB transfer function applied.
+    pub srgb: bool,
+    pub aspects: PixelFormatAspects,
+    pub layout: PixelFormatLayout,
+}
+
+impl PixelFormatInfo {
+    const fn new(
+        bytes_per_block: u32,
+        components: u32,
+        component_type: PixelFormatComponentType,
+        layout: PixelFormatLayout,
+    ) -> Self {
+        PixelFormatInfo {
+            bytes_per_block,
+            block_width: 1,
+            block_height: 1,
+            components,
+            component_type,
+            srgb: false,
+            aspects: PixelFormatAspects::Color,
+            layout,
+        }
+    }
+
+    const fn ordinary(
+        bytes_per_block: u32,
+        components: u32,
+        component_type: PixelFormatComponentType,
+    ) -> Self {
+        Self::new(
+            bytes_per_block,
+            components,
+            component_type,
+            PixelFormatLayout::Ordinary,
+        )
+    }
+
+    const fn packed(
+        bytes_per_block: u32,
+        components: u32,
+        component_type: PixelFormatComponentType,
+    ) -> Self {
+        Self::new(
+            bytes_per_block,
+            components,
+            component_type,
+            PixelFormatLayout::Packed,
+        )
+    }
+
+    const fn compressed(
+        block_width: u32,
+        block_height: u32,
+        bytes_per_block: u32,
+        components: u32,
+        component_type: PixelFormatComponentType,
+    ) -> Self {
+        PixelFormatInfo {
+            block_width,
+            block_height,
+            ..Self::new(
+                bytes_per_block,
+                components,
+                component_type,
+                PixelFormatLayout::Compressed,
+            )
+        }
+    }
+
+    const fn srgb(self) -> Self {
+        PixelFormatInfo { srgb: true, ..self }
+    }
+
+    const fn aspects(self, aspects: PixelFormatAspects) -> Self {
+        PixelFormatInfo { aspects, ..self }
+    }
+}
+
+impl MTLPixelFormat {
+    /// Returns the static properties of this format, or `None` for `Invalid`.
+    pub fn info(&self) -> Option<PixelFormatInfo> {
+        use MTLPixelFormat::*;
+        use PixelFormatComponentType::{Float, Sint, Snorm, Uint, Unorm};
+
+        const DEPTH: PixelFormatAspects = PixelFormatAspects::Depth;
+        const STENCIL: PixelFormatAspects = PixelFormatAspects::Stencil;
+        const DEPTH_STENCIL: PixelFormatAspects = PixelFormatAspects::from_bits_truncate(
+            PixelFormatAspects::Depth.bits() | PixelFormatAspects::Stencil.bits(),
+        );
+
+        let info = match self {
+            Invalid => return None,
+
+            A8Unorm => PixelFormatInfo::ordinary(1, 1, Unorm),
+            R8Unorm => PixelFormatInfo::ordinary(1, 1, Unorm),
+            R8Unorm_sRGB => PixelFormatInfo::ordinary(1, 1, Unorm).srgb(),
+            R8Snorm => PixelFormatInfo::ordinary(1, 1, Snorm),
+            R8Uint => PixelFormatInfo::ordinary(1, 1, Uint),
+            R8Sint => PixelFormatInfo::ordinary(1, 1, Sint),
+
+            R16Unorm => PixelFormatInfo::ordinary(2, 1, Unorm),
+            R16Snorm => PixelFormatInfo::ordinary(2, 1, Snorm),
+            R16Uint => PixelFormatInfo::ordinary(2, 1, Uint),
+            R16Sint => PixelFormatInfo::ordinary(2, 1, Sint),
+            R16Float => PixelFormatInfo::ordinary(2, 1, Float),
+            RG8Unorm => PixelFormatInfo::ordinary(2, 2, Unorm),
+            RG8Unorm_sRGB => PixelFormatInfo::ordinary(2, 2, Unorm).srgb(),
+            RG8Snorm => PixelFormatInfo::ordinary(2, 2, Snorm),
+            RG8Uint => PixelFormatInfo::ordinary(2, 2, Uint),
+            RG8Sint => PixelFormatInfo::ordinary(2, 2, Sint),
+            B5G6R5Unorm => PixelFormatInfo::packed(2, 3, Unorm),
+            A1BGR5Unorm => PixelFormatInfo::packed(2, 4, Unorm),
+            ABGR4Unorm => PixelFormatInfo::packed(2, 4, Unorm),
+            BGR5A1Unorm => PixelFormatInfo::packed(2, 4, Unorm),
+
+            R32Uint => PixelFormatInfo::ordinary(4, 1, Uint),
+            R32Sint => PixelFormatInfo::ordinary(4, 1, Sint),
+            R32Float => PixelFormatInfo::ordinary(4, 1, Float),
+            RG16Unorm => PixelFormatInfo::ordinary(4, 2, Unorm),
+            RG16Snorm => PixelFormatInfo::ordinary(4, 2, Snorm),
+            RG16Uint => PixelFormatInfo::ordinary(4, 2, Uint),
+            RG16Sint => PixelFormatInfo::ordinary(4, 2, Sint),
+            RG16Float => PixelFormatInfo::ordinary(4, 2, Float),
+            RGBA8Unorm => PixelFormatInfo::ordinary(4, 4, Unorm),
+            RGBA8Unorm_sRGB => PixelFormatInfo::ordinary(4, 4, Unorm).srgb(),
+            RGBA8Snorm => PixelFormatInfo::ordinary(4, 4, Snorm),
+            RGBA8Uint => PixelFormatInfo::ordinary(4, 4, Uint),
+            RGBA8Sint => PixelFormatInfo::ordinary(4, 4, Sint),
+            BGRA8Unorm => PixelFormatInfo::ordinary(4, 4, Unorm),
+            BGRA8Unorm_sRGB => PixelFormatInfo::ordinary(4, 4, Unorm).srgb(),
+            RGB10A2Unorm => PixelFormatInfo::packed(4, 4, Unorm),
+            RGB10A2Uint => PixelFormatInfo::packed(4, 4, Uint),
+            RG11B10Float => PixelFormatInfo::packed(4, 3, Float),
+            RGB9E5Float => PixelFormatInfo::packed(4, 3, Float),
+            BGR10A2Unorm => PixelFormatInfo::packed(4, 4, Unorm),
+
+            RG32Uint => PixelFormatInfo::ordinary(8, 2, Uint),
+            RG32Sint => PixelFormatInfo::ordinary(8, 2, Sint),
+            RG32Float => PixelFormatInfo::ordinary(8, 2, Float),
+            RGBA16Unorm => PixelFormatInfo::ordinary(8, 4, Unorm),
+            RGBA16Snorm => PixelFormatInfo::ordinary(8, 4, Snorm),
+            RGBA16Uint => PixelFormatInfo::ordinary(8, 4, Uint),
+            RGBA16Sint => PixelFormatInfo::ordinary(8, 4, Sint),
+            RGBA16Float => PixelFormatInfo::ordinary(8, 4, Float),
+
+            RGBA32Uint => PixelFormatInfo::ordinary(16, 4, Uint),
+            RGBA32Sint => PixelFormatInfo::ordinary(16, 4, Sint),
+            RGBA32Float => PixelFormatInfo::ordinary(16, 4, Float),
+
+            BC1_RGBA => PixelFormatInfo::compressed(4, 4, 8, 4, Unorm),
+            BC1_RGBA_sRGB => PixelFormatInfo::compressed(4, 4, 8, 4, Unorm).srgb(),
+            BC2_RGBA => PixelFormatInfo::compressed(4, 4, 16, 4, Unorm),
+            BC2_RGBA_sRGB => PixelFormatInfo::compressed(4, 4, 16, 4, Unorm).srgb(),
+            BC3_RGBA => PixelFormatInfo::compressed(4, 4, 16, 4, Unorm),
+            BC3_RGBA_sRGB => PixelFormatInfo::compressed(4, 4, 16, 4, Unorm).srgb(),
+            BC4_RUnorm => PixelFormatInfo::compressed(4, 4, 8, 1, Unorm),
+            BC4_RSnorm => PixelFormatInfo::compressed(4, 4, 8, 1, Snorm),
+            BC5_RGUnorm => PixelFormatInfo::compressed(4, 4, 16, 2, Unorm),
+            BC5_RGSnorm => PixelFormatInfo::compressed(4, 4, 16, 2, Snorm),
+            BC6H_RGBFloat => PixelFormatInfo::compressed(4, 4, 16, 3, Float),
+            BC6H_RGBUfloat => PixelFormatInfo::compressed(4, 4, 16, 3, Float),
+            BC7_RGBAUnorm => PixelFormatInfo::compressed(4, 4, 16, 4, Unorm),
+            BC7_RGBAUnorm_sRGB => PixelFormatInfo::compressed(4, 4, 16, 4, Unorm).srgb(),
+
+            PVRTC_RGB_2BPP => PixelFormatInfo::compressed(8, 4, 8, 3, Unorm),
+            PVRTC_RGB_2BPP_sRGB => PixelFormatInfo::compressed(8, 4, 8, 3, Unorm).srgb(),
+            PVRTC_RGB_4BPP => PixelFormatInfo::compressed(4, 4, 8, 3, Unorm),
+            PVRTC_RGB_4BPP_sRGB => PixelFormatInfo::compressed(4, 4, 8, 3, Unorm).srgb(),
+            PVRTC_RGBA_2BPP => PixelFormatInfo::compressed(8, 4, 8, 4, Unorm),
+            PVRTC_RGBA_2BPP_sRGB => PixelFormatInfo::compressed(8, 4, 8, 4, Unorm).srgb(),
+            PVRTC_RGBA_4BPP => PixelFormatInfo::compressed(4, 4, 8, 4, Unorm),
+            PVRTC_RGBA_4BPP_sRGB => PixelFormatInfo::compressed(4, 4, 8, 4, Unorm).srgb(),
+
+            EAC_R11Unorm => PixelFormatInfo::compressed(4, 4, 8, 1, Unorm),
+            EAC_R11Snorm => PixelFormatInfo::compressed(4, 4, 8, 1, Snorm),
+            EAC_RG11Unorm => PixelFormatInfo::compressed(4, 4, 16, 2, Unorm),
+            EAC_RG11Snorm => PixelFormatInfo::compressed(4, 4, 16, 2, Snorm),
+            EAC_RGBA8 => PixelFormatInfo::compressed(4, 4, 16, 4, Unorm),
+            EAC_RGBA8_sRGB => PixelFormatInfo::compressed(4, 4, 16, 4, Unorm).srgb(),
+            ETC2_RGB8 => PixelFormatInfo::compressed(4, 4, 8, 3, Unorm),
+            ETC2_RGB8_sRGB => PixelFormatInfo::compressed(4, 4, 8, 3, Unorm).srgb(),
+            ETC2_RGB8A1 => PixelFormatInfo::compressed(4, 4, 8, 4, Unorm),
+            ETC2_RGB8A1_sRGB => PixelFormatInfo::compressed(4, 4, 8, 4, Unorm).srgb(),
+
+            ASTC_4x4_sRGB => PixelFormatInfo::compressed(4, 4, 16, 4, Unorm).srgb(),
+            ASTC_5x4_sRGB => PixelFormatInfo::compressed(5, 4, 16, 4, Unorm).srgb(),
+            ASTC_5x5_sRGB => PixelFormatInfo::compressed(5, 5, 16, 4, Unorm).srgb(),
+            ASTC_6x5_sRGB => PixelFormatInfo::compressed(6, 5, 16, 4, Unorm).srgb(),
+            ASTC_6x6_sRGB => PixelFormatInfo::compressed(6, 6, 16, 4, Unorm).srgb(),
+            ASTC_8x5_sRGB => PixelFormatInfo::compressed(8, 5, 16, 4, Unorm).srgb(),
+            ASTC_8x6_sRGB => PixelFormatInfo::compressed(8, 6, 16, 4, Unorm).srgb(),
+            ASTC_8x8_sRGB => PixelFormatInfo::compressed(8, 8, 16, 4, Unorm).srgb(),
+            ASTC_10x5_sRGB => PixelFormatInfo::compressed(10, 5, 16, 4, Unorm).srgb(),
+            ASTC_10x6_sRGB => PixelFormatInfo::compressed(10, 6, 16, 4, Unorm).srgb(),
+            ASTC_10x8_sRGB => PixelFormatInfo::compressed(10, 8, 16, 4, Unorm).srgb(),
+            ASTC_10x10_sRGB => PixelFormatInfo::compressed(10, 10, 16, 4, Unorm).srgb(),
+            ASTC_12x10_sRGB => PixelFormatInfo::compressed(12, 10, 16, 4, Unorm).srgb(),
+            ASTC_12x12_sRGB => PixelFormatInfo::compressed(12, 12, 16, 4, Unorm).srgb(),
+            ASTC_4x4_LDR => PixelFormatInfo::compressed(4, 4, 16, 4, Unorm),
+            ASTC_5x4_LDR => PixelFormatInfo::compressed(5, 4, 16, 4, Unorm),
+            ASTC_5x5_LDR => PixelFormatInfo::compressed(5, 5, 16, 4, Unorm),
+            ASTC_6x5_LDR => PixelFormatInfo::compressed(6, 5, 16, 4, Unorm),
+            ASTC_6x6_LDR => PixelFormatInfo::compressed(6, 6, 16, 4, Unorm),
+            ASTC_8x5_LDR => PixelFormatInfo::compressed(8, 5, 16, 4, Unorm),
+            ASTC_8x6_LDR => PixelFormatInfo::compressed(8, 6, 16, 4, Unorm),
+            ASTC_8x8_LDR => PixelFormatInfo::compressed(8, 8, 16, 4, Unorm),
+            ASTC_10x5_LDR => PixelFormatInfo::compressed(10, 5, 16, 4, Unorm),
+            ASTC_10x6_LDR => PixelFormatInfo::compressed(10, 6, 16, 4, Unorm),
+            ASTC_10x8_LDR => PixelFormatInfo::compressed(10, 8, 16, 4, Unorm),
+            ASTC_10x10_LDR => PixelFormatInfo::compressed(10, 10, 16, 4, Unorm),
+            ASTC_12x10_LDR => PixelFormatInfo::compressed(12, 10, 16, 4, Unorm),
+            ASTC_12x12_LDR => PixelFormatInfo::compressed(12, 12, 16, 4, Unorm),
+            ASTC_4x4_HDR => PixelFormatInfo::compressed(4, 4, 16, 4, Float),
+            ASTC_5x4_HDR => PixelFormatInfo::compressed(5, 4, 16, 4, Float),
+            ASTC_5x5_HDR => PixelFormatInfo::compressed(5, 5, 16, 4, Float),
+            ASTC_6x5_HDR => PixelFormatInfo::compressed(6, 5, 16, 4, Float),
+            ASTC_6x6_HDR => PixelFormatInfo::compressed(6, 6, 16, 4, Float),
+            ASTC_8x5_HDR => PixelFormatInfo::compressed(8, 5, 16, 4, Float),
+            ASTC_8x6_HDR => PixelFormatInfo::compressed(8, 6, 16, 4, Float),
+            ASTC_8x8_HDR => PixelFormatInfo::compressed(8, 8, 16, 4, Float),
+            ASTC_10x5_HDR => PixelFormatInfo::compressed(10, 5, 16, 4, Float),
+            ASTC_10x6_HDR => PixelFormatInfo::compressed(10, 6, 16, 4, Float),
+            ASTC_10x8_HDR => PixelFormatInfo::compressed(10, 8, 16, 4, Float),
+            ASTC_10x10_HDR => PixelFormatInfo::compressed(10, 10, 16, 4, Float),
+            ASTC_12x10_HDR => PixelFormatInfo::compressed(12, 10, 16, 4, Float),
+            ASTC_12x12_HDR => PixelFormatInfo::compressed(12, 12, 16, 4, Float),
+
+            GBGR422 | BGRG422 => PixelFormatInfo {
+                block_width: 2,
+                ..PixelFormatInfo::new(4, 3, Unorm, PixelFormatLayout::Subsampled)
+            },
+
+            Depth16Unorm => PixelFormatInfo::ordinary(2, 1, Unorm).aspects(DEPTH),
+            Depth32Float => PixelFormatInfo::ordinary(4, 1, Float).aspects(DEPTH),
+            Stencil8 => PixelFormatInfo::ordinary(1, 1, Uint).aspects(STENCIL),
+            Depth24Unorm_Stencil8 => PixelFormatInfo::packed(4, 2, Unorm).aspects(DEPTH_STENCIL),
+            Depth32Float_Stencil8 => PixelFormatInfo::ordinary(8, 2, Float).aspects(DEPTH_STENCIL),
+            X32_Stencil8 => PixelFormatInfo::ordinary(8, 1, Uint).aspects(STENCIL),
+            X24_Stencil8 => PixelFormatInfo::packed(4, 1, Uint).aspects(STENCIL),
+
+            BGRA10_XR => PixelFormatInfo::packed(8, 4, Unorm),
+            BGRA10_XR_SRGB => PixelFormatInfo::packed(8, 4, Unorm).srgb(),
+            BGR10_XR => PixelFormatInfo::packed(4, 3, Unorm),
+            BGR10_XR_SRGB => PixelFormatInfo::packed(4, 3, Unorm).srgb(),
+        };
+        Some(info)
+    }
+
+    /// The number of bytes used by one block of texels, or one texel for uncompressed formats.
+    pub fn bytes_per_block(&self) -> u32 {
+        self.info().map_or(0, |info| info.bytes_per_block)
+    }
+
+    /// The `(width, height)` of one block of texels.
+    pub fn block_size(&self) -> (u32, u32) {
+        self.info()
+            .map_or((1, 1), |info| (info.block_width, info.block_height))
+    }
+
+    pub fn component_count(&self) -> u32 {
+        self.info().map_or(0, |info| info.components)
+    }
+
+    pub fn component_type(&self) -> Option<PixelFormatComponentType> {
+        self.info().map(|info| info.component_type)
+    }
+
+    pub fn aspects(&self) -> PixelFormatAspects {
+        self.info()
+            .map_or(PixelFormatAspects::empty(), |info| info.aspects)
+    }
+
+    pub fn layout(&self) -> Option<PixelFormatLayout> {
+        self.info().map(|info| info.layout)
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.info().is_some_and(|info| info.srgb)
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.layout() == Some(PixelFormatLayout::Compressed)
+    }
+
+    pub fn is_packed(&self) -> bool {
+        self.layout() == Some(PixelFormatLayout::Packed)
+    }
+
+    pub fn has_depth(&self) -> bool {
+        self.aspects().contains(PixelFormatAspects::Depth)
+    }
+
+    pub fn has_stencil(&self) -> bool {
+        self.aspects().contains(PixelFormatAspects::Stencil)
+    }
+
+    pub fn is_depth_stencil(&self) -> bool {
+        self.aspects()
+            .intersects(PixelFormatAspects::Depth | PixelFormatAspects::Stencil)
+    }
+
+    /// Returns the `(linear, sRGB)` pair this format belongs to, if it has an sRGB twin.
+    fn srgb_pair(&self) -> Option<(MTLPixelFormat, MTLPixelFormat)> {
+        use MTLPixelFormat::*;
+        let pair = match self {
+            R8Unorm | R8Unorm_sRGB => (R8Unorm, R8Unorm_sRGB),
+            RG8Unorm | RG8Unorm_sRGB => (RG8Unorm, RG8Unorm_sRGB),
+            RGBA8Unorm | RGBA8Unorm_sRGB => (RGBA8Unorm, RGBA8Unorm_sRGB),
+            BGRA8Unorm | BGRA8Unorm_sRGB => (BGRA8Unorm, BGRA8Unorm_sRGB),
+            BC1_RGBA | BC1_RGBA_sRGB => (BC1_RGBA, BC1_RGBA_sRGB),
+            BC2_RGBA | BC2_RGBA_sRGB => (BC2_RGBA, BC2_RGBA_sRGB),
+            BC3_RGBA | BC3_RGBA_sRGB => (BC3_RGBA, BC3_RGBA_sRGB),
+            BC7_RGBAUnorm | BC7_RGBAUnorm_sRGB => (BC7_RGBAUnorm, BC7_RGBAUnorm_sRGB),
+            PVRTC_RGB_2BPP | PVRTC_RGB_2BPP_sRGB => (PVRTC_RGB_2BPP, PVRTC_RGB_2BPP_sRGB),
+            PVRTC_RGB_4BPP | PVRTC_RGB_4BPP_sRGB => (PVRTC_RGB_4BPP, PVRTC_RGB_4BPP_sRGB),
+            PVRTC_RGBA_2BPP | PVRTC_RGBA_2BPP_sRGB => (PVRTC_RGBA_2BPP, PVRTC_RGBA_2BPP_sRGB),
+            PVRTC_RGBA_4BPP | PVRTC_RGBA_4BPP_sRGB => (PVRTC_RGBA_4BPP, PVRTC_RGBA_4BPP_sRGB),
+            EAC_RGBA8 | EAC_RGBA8_sRGB => (EAC_RGBA8, EAC_RGBA8_sRGB),
+            ETC2_RGB8 | ETC2_RGB8_sRGB => (ETC2_RGB8, ETC2_RGB8_sRGB),
+            ETC2_RGB8A1 | ETC2_RGB8A1_sRGB => (ETC2_RGB8A1, ETC2_RGB8A1_sRGB),
+            ASTC_4x4_LDR | ASTC_4x4_sRGB => (ASTC_4x4_LDR, ASTC_4x4_sRGB),
+            ASTC_5x4_LDR | ASTC_5x4_sRGB => (ASTC_5x4_LDR, ASTC_5x4_sRGB),
+            ASTC_5x5_LDR | ASTC_5x5_sRGB => (ASTC_5x5_LDR, ASTC_5x5_sRGB),
+            ASTC_6x5_LDR | ASTC_6x5_sRGB => (ASTC_6x5_LDR, ASTC_6x5_sRGB),
+            ASTC_6x6_LDR | ASTC_6x6_sRGB => (ASTC_6x6_LDR, ASTC_6x6_sRGB),
+            ASTC_8x5_LDR | ASTC_8x5_sRGB => (ASTC_8x5_LDR, ASTC_8x5_sRGB),
+            ASTC_8x6_LDR | ASTC_8x6_sRGB => (ASTC_8x6_LDR, ASTC_8x6_sRGB),
+            ASTC_8x8_LDR | ASTC_8x8_sRGB => (ASTC_8x8_LDR, ASTC_8x8_sRGB),
+            ASTC_10x5_LDR | ASTC_10x5_sRGB => (ASTC_10x5_LDR, ASTC_10x5_sRGB),
+            ASTC_10x6_LDR | ASTC_10x6_sRGB => (ASTC_10x6_LDR, ASTC_10x6_sRGB),
+            ASTC_10x8_LDR | ASTC_10x8_sRGB => (ASTC_10x8_LDR, ASTC_10x8_sRGB),
+            ASTC_10x10_LDR | ASTC_10x10_sRGB => (ASTC_10x10_LDR, ASTC_10x10_sRGB),
+            ASTC_12x10_LDR | ASTC_12x10_sRGB => (ASTC_12x10_LDR, ASTC_12x10_sRGB),
+            ASTC_12x12_LDR | ASTC_12x12_sRGB => (ASTC_12x12_LDR, ASTC_12x12_sRGB),
+            BGRA10_XR | BGRA10_XR_SRGB => (BGRA10_XR, BGRA10_XR_SRGB),
+            BGR10_XR | BGR10_XR_SRGB => (BGR10_XR, BGR10_XR_SRGB),
+            _ => return None,
+        };
+        Some(pair)
+    }
+
+    /// Returns the sRGB variant of this format, such as `RGBA8Unorm_sRGB` for `RGBA8Unorm`.
+    ///
+    /// sRGB formats return themselves; `None` is returned when there is no sRGB variant.
+    pub fn srgb_variant(&self) -> Option<MTLPixelFormat> {
+        self.srgb_pair().map(|(_, srgb)| srgb)
+    }
+
+    /// Returns the linear variant of this format, such as `RGBA8Unorm` for `RGBA8Unorm_sRGB`.
+    ///
+    /// Linear formats return themselves; `None` is returned when there is no sRGB variant.
+    pub fn linear_variant(&self) -> Option<MTLPixelFormat> {
+        self.srgb_pair().map(|(linear, _)| linear)
+    }
+
+    /// Whether this format and `other` only differ by their sRGB transfer function.
+    pub fn is_srgb_pair_of(&self, other: MTLPixelFormat) -> bool {
+        *self != other && self.srgb_pair().is_some() && self.srgb_pair() == other.srgb_pair()
+    }
+
+    /// Whether a texture with this format may be reinterpreted as `view_format` through
+    /// `TextureRef::new_texture_view`.
+    ///
+    /// The rules follow Metal's texture view compatibility:
+    /// - a valid format is always compatible with itself and with its sRGB/linear twin;
+    /// - uncompressed color formats of the same size class (8, 16, 32, 64 or 128 bits per pixel)
+    ///   may alias each other, except the subsampled and extended range formats;
+    /// - the stencil of a combined depth/stencil format can be read through
+    ///   `X24_Stencil8` or `X32_Stencil8`;
+    /// - compressed formats, depth formats and the remaining formats only alias themselves.
+    ///
+    /// Apart from the first rule the texture also has to be created with
+    /// `MTLTextureUsage::PixelFormatView`.
+    pub fn is_view_compatible(&self, view_format: MTLPixelFormat) -> bool {
+        use MTLPixelFormat::*;
+
+        if *self == Invalid || view_format == Invalid {
+            return false;
+        }
+        if *self == view_format || self.is_srgb_pair_of(view_format) {
+            return true;
+        }
+        match (self, view_format) {
+            (Depth24Unorm_Stencil8, X24_Stencil8) | (Depth32Float_Stencil8, X32_Stencil8) => {
+                return true
+            }
+            (BGRA10_XR, _)
+            | (BGRA10_XR_SRGB, _)
+            | (BGR10_XR, _)
+            | (BGR10_XR_SRGB, _)
+            | (_, BGRA10_XR)
+            | (_, BGRA10_XR_SRGB)
+            | (_, BGR10_XR)
+            | (_, BGR10_XR_SRGB) => return false,
+            _ => {}
+        }
+
+        match (self.info(), view_format.info()) {
+            (Some(source), Some(view)) => {
+                let aliasable = |info: &PixelFormatInfo| {
+                    info.aspects == PixelFormatAspects::Color
+                        && (info.layout == PixelFormatLayout::Ordinary
+                            || info.layout == PixelFormatLayout::Packed)
+                };
+                aliasable(&source)
+                    && aliasable(&view)
+                    && source.bytes_per_block == view.bytes_per_block
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MTLPixelFormat::*;
+
+    #[test]
+    fn invalid_has_no_info() {
+        assert_eq!(Invalid.info(), None);
+        assert_eq!(Invalid.bytes_per_block(), 0);
+        assert_eq!(Invalid.block_size(), (1, 1));
+        assert_eq!(Invalid.aspects(), PixelFormatAspects::empty());
+        assert_eq!(Invalid.component_type(), None);
+    }
+
+    #[test]
+    fn block_sizes() {
+        for &(format, block_size, bytes_per_block) in &[
+            (RGBA8Unorm, (1, 1), 4),
+            (BC1_RGBA, (4, 4), 8),
+            (BC4_RSnorm, (4, 4), 8),
+            (BC7_RGBAUnorm_sRGB, (4, 4), 16),
+            (ASTC_4x4_LDR, (4, 4), 16),
+            (ASTC_10x5_sRGB, (10, 5), 16),
+            (ASTC_12x12_HDR, (12, 12), 16),
+            (PVRTC_RGB_2BPP, (8, 4), 8),
+            (PVRTC_RGBA_4BPP_sRGB, (4, 4), 8),
+            (EAC_RG11Unorm, (4, 4), 16),
+            (ETC2_RGB8, (4, 4), 8),
+            (GBGR422, (2, 1), 4),
+            (BGRG422, (2, 1), 4),
+        ] {
+            assert_eq!(format.block_size(), block_size, "{:?}", format);
+            assert_eq!(format.bytes_per_block(), bytes_per_block, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn bytes_per_block() {
+        for &(format, bytes_per_block) in &[
+            (A8Unorm, 1),
+            (B5G6R5Unorm, 2),
+            (RGB9E5Float, 4),
+            (RG11B10Float, 4),
+            (RGBA32Float, 16),
+            (BGRA10_XR, 8),
+            (BGRA10_XR_SRGB, 8),
+            (BGR10_XR, 4),
+            (Depth16Unorm, 2),
+            (Depth32Float, 4),
+            (Stencil8, 1),
+            (Depth24Unorm_Stencil8, 4),
+            (Depth32Float_Stencil8, 8),
+            (X24_Stencil8, 4),
+            (X32_Stencil8, 8),
+        ] {
+            assert_eq!(format.bytes_per_block(), bytes_per_block, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn aspects() {
+        let depth_stencil = PixelFormatAspects::Depth | PixelFormatAspects::Stencil;
+        for &(format, aspects) in &[
+            (RGBA8Unorm, PixelFormatAspects::Color),
+            (BC1_RGBA, PixelFormatAspects::Color),
+            (Depth16Unorm, PixelFormatAspects::Depth),
+            (Depth32Float, PixelFormatAspects::Depth),
+            (Stencil8, PixelFormatAspects::Stencil),
+            (X24_Stencil8, PixelFormatAspects::Stencil),
+            (X32_Stencil8, PixelFormatAspects::Stencil),
+            (Depth24Unorm_Stencil8, depth_stencil),
+            (Depth32Float_Stencil8, depth_stencil),
+        ] {
+            assert_eq!(format.aspects(), aspects, "{:?}", format);
+        }
+        assert!(Depth32Float_Stencil8.has_depth() && Depth32Float_Stencil8.has_stencil());
+        assert!(Stencil8.is_depth_stencil() && !Stencil8.has_depth());
+        assert!(!RGBA8Unorm.is_depth_stencil());
+    }
+
+    #[test]
+    fn layouts_and_component_types() {
+        assert_eq!(RGBA8Unorm.layout(), Some(PixelFormatLayout::Ordinary));
+        assert!(RGB10A2Unorm.is_packed());
+        assert!(BGRA10_XR.is_packed());
+        assert!(ASTC_6x6_HDR.is_compressed());
+        assert_eq!(GBGR422.layout(), Some(PixelFormatLayout::Subsampled));
+        assert_eq!(
+            RGB9E5Float.component_type(),
+            Some(PixelFormatComponentType::Float)
+        );
+        assert_eq!(
+            Depth32Float_Stencil8.component_type(),
+            Some(PixelFormatComponentType::Float)
+        );
+        assert_eq!(RG11B10Float.component_count(), 3);
+        assert!(BGRA8Unorm_sRGB.is_srgb() && !BGRA8Unorm.is_srgb());
+    }
+
+    const SRGB_PAIRS: [(MTLPixelFormat, MTLPixelFormat); 31] = [
+        (R8Unorm, R8Unorm_sRGB),
+        (RG8Unorm, RG8Unorm_sRGB),
+        (RGBA8Unorm, RGBA8Unorm_sRGB),
+        (BGRA8Unorm, BGRA8Unorm_sRGB),
+        (BC1_RGBA, BC1_RGBA_sRGB),
+        (BC2_RGBA, BC2_RGBA_sRGB),
+        (BC3_RGBA, BC3_RGBA_sRGB),
+        (BC7_RGBAUnorm, BC7_RGBAUnorm_sRGB),
+        (PVRTC_RGB_2BPP, PVRTC_RGB_2BPP_sRGB),
+        (PVRTC_RGB_4BPP, PVRTC_RGB_4BPP_sRGB),
+        (PVRTC_RGBA_2BPP, PVRTC_RGBA_2BPP_sRGB),
+        (PVRTC_RGBA_4BPP, PVRTC_RGBA_4BPP_sRGB),
+        (EAC_RGBA8, EAC_RGBA8_sRGB),
+        (ETC2_RGB8, ETC2_RGB8_sRGB),
+        (ETC2_RGB8A1, ETC2_RGB8A1_sRGB),
+        (ASTC_4x4_LDR, ASTC_4x4_sRGB),
+        (ASTC_5x4_LDR, ASTC_5x4_sRGB),
+        (ASTC_5x5_LDR, ASTC_5x5_sRGB),
+        (ASTC_6x5_LDR, ASTC_6x5_sRGB),
+        (ASTC_6x6_LDR, ASTC_6x6_sRGB),
+        (ASTC_8x5_LDR, ASTC_8x5_sRGB),
+        (ASTC_8x6_LDR, ASTC_8x6_sRGB),
+        (ASTC_8x8_LDR, ASTC_8x8_sRGB),
+        (ASTC_10x5_LDR, ASTC_10x5_sRGB),
+        (ASTC_10x6_LDR, ASTC_10x6_sRGB),
+        (ASTC_10x8_LDR, ASTC_10x8_sRGB),
+        (ASTC_10x10_LDR, ASTC_10x10_sRGB),
+        (ASTC_12x10_LDR, ASTC_12x10_sRGB),
+        (ASTC_12x12_LDR, ASTC_12x12_sRGB),
+        (BGRA10_XR, BGRA10_XR_SRGB),
+        (BGR10_XR, BGR10_XR_SRGB),
+    ];
+
+    #[test]
+    fn srgb_pairs() {
+        for &(linear, srgb) in &SRGB_PAIRS {
+            assert!(!linear.is_srgb() && srgb.is_srgb(), "{:?}", srgb);
+            for &format in &[linear, srgb] {
+                assert_eq!(format.srgb_variant(), Some(srgb));
+                assert_eq!(format.linear_variant(), Some(linear));
+            }
+            assert!(linear.is_srgb_pair_of(srgb) && srgb.is_srgb_pair_of(linear));
+            assert!(!linear.is_srgb_pair_of(linear));
+            assert!(linear.is_view_compatible(srgb) && srgb.is_view_compatible(linear));
+        }
+        assert!(!RGBA8Unorm.is_srgb_pair_of(BGRA8Unorm_sRGB));
+    }
+
+    #[test]
+    fn formats_without_srgb_twin() {
+        for &format in &[Invalid, RGBA16Float, BC4_RUnorm, ASTC_4x4_HDR, Depth32Float] {
+            assert_eq!(format.srgb_variant(), None, "{:?}", format);
+            assert_eq!(format.linear_variant(), None, "{:?}", format);
+            assert!(!format.is_srgb_pair_of(RGBA8Unorm));
+        }
+        assert!(!Invalid.is_srgb_pair_of(Invalid));
+    }
+
+    #[test]
+    fn view_compatible() {
+        for &(format, view) in &[
+            (RGBA8Unorm, RGBA8Unorm),
+            (RGBA8Unorm, BGRA8Unorm_sRGB),
+            (RGBA8Unorm, R32Float),
+            (RGBA8Unorm, RG16Sint),
+            (RGB10A2Unorm, RGBA8Uint),
+            (RG11B10Float, R32Uint),
+            (R16Float, RG8Unorm),
+            (A8Unorm, R8Sint),
+            (RGBA16Float, RG32Uint),
+            (RGBA32Float, RGBA32Sint),
+            (Depth24Unorm_Stencil8, X24_Stencil8),
+            (Depth32Float_Stencil8, X32_Stencil8),
+            (BC1_RGBA, BC1_RGBA),
+            (Depth32Float, Depth32Float),
+            (BGRA10_XR, BGRA10_XR_SRGB),
+        ] {
+            assert!(
+                format.is_view_compatible(view),
+                "{:?} as {:?}",
+                format,
+                view
+            );
+        }
+    }
+
+    #[test]
+    fn view_incompatible() {
+        for &(format, view) in &[
+            (Invalid, Invalid),
+            (Invalid, RGBA8Unorm),
+            (RGBA8Unorm, Invalid),
+            (RGBA8Unorm, RG8Unorm),
+            (RGBA8Unorm, RGBA16Float),
+            (R32Float, Depth32Float),
+            (Depth32Float, R32Float),
+            (X24_Stencil8, Depth24Unorm_Stencil8),
+            (Depth32Float_Stencil8, X24_Stencil8),
+            (BC1_RGBA, BC4_RUnorm),
+            (BC1_RGBA, RG32Uint),
+            (ASTC_4x4_LDR, ASTC_4x4_HDR),
+            (GBGR422, RGBA8Unorm),
+            (BGRA10_XR, RG32Uint),
+            (RGBA16Float, BGRA10_XR),
+            (BGR10_XR, RGBA8Unorm),
+        ] {
+            assert!(
+                !format.is_view_compatible(view),
+                "{:?} as {:?}",
+                format,
+                view
+            );
+        }
+    }
+}
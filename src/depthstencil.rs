@@ -5,7 +5,9 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use crate::DeviceRef;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use objc::runtime::{NO, YES};
 
 #[repr(u64)]
@@ -34,14 +36,17 @@ pub enum MTLStencilOperation {
     DecrementWrap = 7,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLStencilDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLStencilDescriptor;
     pub struct StencilDescriptor;
     pub struct StencilDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl StencilDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -51,6 +56,7 @@ impl StencilDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl StencilDescriptorRef {
     pub fn stencil_compare_function(&self) -> MTLCompareFunction {
         unsafe { msg_send![self, stencilCompareFunction] }
@@ -101,14 +107,17 @@ impl StencilDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLDepthStencilDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLDepthStencilDescriptor;
     pub struct DepthStencilDescriptor;
     pub struct DepthStencilDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl DepthStencilDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -118,6 +127,7 @@ impl DepthStencilDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl DepthStencilDescriptorRef {
     pub fn depth_compare_function(&self) -> MTLCompareFunction {
         unsafe { msg_send![self, depthCompareFunction] }
@@ -172,14 +182,17 @@ impl DepthStencilDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLDepthStencilState {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLDepthStencilState;
     pub struct DepthStencilState;
     pub struct DepthStencilStateRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl DepthStencilStateRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
@@ -7,11 +7,15 @@
 
 use super::*;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use block::{Block, ConcreteBlock};
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use foreign_types::ForeignType;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use objc::runtime::{Object, NO, YES};
 
-use std::{ffi::CStr, os::raw::c_char, path::Path, ptr};
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use std::{path::Path, ptr};
 
 // Available on macOS 10.11+, iOS 8.0+, tvOS 9.0+
 #[allow(non_camel_case_types)]
@@ -55,6 +59,7 @@ pub enum MTLFeatureSet {
 // Available on macOS 10.15+, iOS 13.0+
 #[repr(i64)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum MTLGPUFamily {
     Common1 = 3001,
@@ -95,7 +100,7 @@ bitflags! {
     }
 }
 
-#[allow(non_camel_case_types)]
+#[allow(non_camel_case_types, clippy::enum_variant_names)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 enum OS {
     iOS,
@@ -702,7 +707,7 @@ impl MTLFeatureSet {
 
     pub fn max_buffer_length(&self) -> u32 {
         if self.os() == OS::macOS && self.os_version() >= 12 {
-            1 * GB
+            GB
         } else {
             256 * MB
         }
@@ -1378,10 +1383,811 @@ impl MTLFeatureSet {
             PixelFormatCapabilities::empty()
         }
     }
+
+    /// Returns the capabilities of `format` on this feature set.
+    ///
+    /// Every pixel format is covered; formats that are unavailable on this feature set
+    /// (including `Invalid` and the ASTC HDR formats, which no feature set exposes) report
+    /// empty capabilities.
+    pub fn pixel_format_capabilities(&self, format: MTLPixelFormat) -> PixelFormatCapabilities {
+        use MTLPixelFormat::*;
+
+        match format {
+            Invalid => PixelFormatCapabilities::empty(),
+            A8Unorm => self.a8_unorm_capabilities(),
+            R8Unorm => self.r8_unorm_capabilities(),
+            R8Unorm_sRGB => self.r8_unorm_srgb_capabilities(),
+            R8Snorm => self.r8_snorm_capabilities(),
+            R8Uint => self.r8_uint_capabilities(),
+            R8Sint => self.r8_sint_capabilities(),
+            R16Unorm => self.r16_unorm_capabilities(),
+            R16Snorm => self.r16_snorm_capabilities(),
+            R16Uint => self.r16_uint_capabilities(),
+            R16Sint => self.r16_sint_capabilities(),
+            R16Float => self.r16_float_capabilities(),
+            RG8Unorm => self.rg8_unorm_capabilities(),
+            RG8Unorm_sRGB => self.rg8_unorm_srgb_capabilities(),
+            RG8Snorm => self.rg8_snorm_capabilities(),
+            RG8Uint => self.rg8_uint_capabilities(),
+            RG8Sint => self.rg8_sint_capabilities(),
+            B5G6R5Unorm => self.b5_g6_r5_unorm_capabilities(),
+            A1BGR5Unorm => self.a1_bgr5_unorm_capabilities(),
+            ABGR4Unorm => self.abgr4_unorm_capabilities(),
+            BGR5A1Unorm => self.bgr5_a1_unorm_capabilities(),
+            R32Uint => self.r32_uint_capabilities(),
+            R32Sint => self.r32_sint_capabilities(),
+            R32Float => self.r32_float_capabilities(),
+            RG16Unorm => self.rg16_unorm_capabilities(),
+            RG16Snorm => self.rg16_snorm_capabilities(),
+            RG16Uint => self.rg16_uint_capabilities(),
+            RG16Sint => self.rg16_sint_capabilities(),
+            RG16Float => self.rg16_float_capabilities(),
+            RGBA8Unorm => self.rgba8_unorm_capabilities(),
+            RGBA8Unorm_sRGB => self.rgba8_unorm_srgb_capabilities(),
+            RGBA8Snorm => self.rgba8_snorm_capabilities(),
+            RGBA8Uint => self.rgba8_uint_capabilities(),
+            RGBA8Sint => self.rgba8_sint_capabilities(),
+            BGRA8Unorm => self.bgra8_unorm_capabilities(),
+            BGRA8Unorm_sRGB => self.bgra8_unorm_srgb_capabilities(),
+            RGB10A2Unorm => self.rgb10_a2_unorm_capabilities(),
+            RGB10A2Uint => self.rgb10_a2_uint_capabilities(),
+            RG11B10Float => self.rg11_b10_float_capabilities(),
+            RGB9E5Float => self.rgb9_e5_float_capabilities(),
+            BGR10A2Unorm => self.bgr10_a2_unorm_capabilities(),
+            RG32Uint => self.rg32_uint_capabilities(),
+            RG32Sint => self.rg32_sint_capabilities(),
+            RG32Float => self.rg32_float_capabilities(),
+            RGBA16Unorm => self.rgba16_unorm_capabilities(),
+            RGBA16Snorm => self.rgba16_snorm_capabilities(),
+            RGBA16Uint => self.rgba16_uint_capabilities(),
+            RGBA16Sint => self.rgba16_sint_capabilities(),
+            RGBA16Float => self.rgba16_float_capabilities(),
+            RGBA32Uint => self.rgba32_uint_capabilities(),
+            RGBA32Sint => self.rgba32_sint_capabilities(),
+            RGBA32Float => self.rgba32_float_capabilities(),
+            GBGR422 => self.gbgr422_capabilities(),
+            BGRG422 => self.bgrg422_capabilities(),
+            Depth16Unorm => self.depth16_unorm_capabilities(),
+            Depth32Float => self.depth32_float_capabilities(),
+            Stencil8 => self.stencil8_capabilities(),
+            Depth24Unorm_Stencil8 => self.depth24_unorm_stencil8_capabilities(),
+            Depth32Float_Stencil8 => self.depth32_float_stencil8_capabilities(),
+            X24_Stencil8 => self.x24_stencil8_capabilities(),
+            X32_Stencil8 => self.x32_stencil8_capabilities(),
+            BGRA10_XR => self.bgra10_xr_capabilities(),
+            BGRA10_XR_SRGB => self.bgra10_xr_srgb_capabilities(),
+            BGR10_XR => self.bgr10_xr_capabilities(),
+            BGR10_XR_SRGB => self.bgr10_xr_srgb_capabilities(),
+            BC1_RGBA | BC1_RGBA_sRGB | BC2_RGBA | BC2_RGBA_sRGB | BC3_RGBA | BC3_RGBA_sRGB
+            | BC4_RUnorm | BC4_RSnorm | BC5_RGUnorm | BC5_RGSnorm | BC6H_RGBFloat
+            | BC6H_RGBUfloat | BC7_RGBAUnorm | BC7_RGBAUnorm_sRGB => {
+                self.bc_pixel_formats_capabilities()
+            }
+            PVRTC_RGB_2BPP | PVRTC_RGB_2BPP_sRGB | PVRTC_RGB_4BPP | PVRTC_RGB_4BPP_sRGB
+            | PVRTC_RGBA_2BPP | PVRTC_RGBA_2BPP_sRGB | PVRTC_RGBA_4BPP | PVRTC_RGBA_4BPP_sRGB => {
+                self.pvrtc_pixel_formats_capabilities()
+            }
+            EAC_R11Unorm | EAC_R11Snorm | EAC_RG11Unorm | EAC_RG11Snorm | EAC_RGBA8
+            | EAC_RGBA8_sRGB | ETC2_RGB8 | ETC2_RGB8_sRGB | ETC2_RGB8A1 | ETC2_RGB8A1_sRGB => {
+                self.eac_etc_pixel_formats_capabilities()
+            }
+            ASTC_4x4_sRGB | ASTC_5x4_sRGB | ASTC_5x5_sRGB | ASTC_6x5_sRGB | ASTC_6x6_sRGB
+            | ASTC_8x5_sRGB | ASTC_8x6_sRGB | ASTC_8x8_sRGB | ASTC_10x5_sRGB | ASTC_10x6_sRGB
+            | ASTC_10x8_sRGB | ASTC_10x10_sRGB | ASTC_12x10_sRGB | ASTC_12x12_sRGB
+            | ASTC_4x4_LDR | ASTC_5x4_LDR | ASTC_5x5_LDR | ASTC_6x5_LDR | ASTC_6x6_LDR
+            | ASTC_8x5_LDR | ASTC_8x6_LDR | ASTC_8x8_LDR | ASTC_10x5_LDR | ASTC_10x6_LDR
+            | ASTC_10x8_LDR | ASTC_10x10_LDR | ASTC_12x10_LDR | ASTC_12x12_LDR => {
+                self.astc_pixel_formats_capabilities()
+            }
+            ASTC_4x4_HDR | ASTC_5x4_HDR | ASTC_5x5_HDR | ASTC_6x5_HDR | ASTC_6x6_HDR
+            | ASTC_8x5_HDR | ASTC_8x6_HDR | ASTC_8x8_HDR | ASTC_10x5_HDR | ASTC_10x6_HDR
+            | ASTC_10x8_HDR | ASTC_10x10_HDR | ASTC_12x10_HDR | ASTC_12x12_HDR => {
+                PixelFormatCapabilities::empty()
+            }
+        }
+    }
+}
+
+impl TextureLimits for MTLFeatureSet {
+    fn max_1d_texture_size(&self) -> u32 {
+        MTLFeatureSet::max_1d_texture_size(self)
+    }
+
+    fn max_2d_texture_size(&self) -> u32 {
+        MTLFeatureSet::max_2d_texture_size(self)
+    }
+
+    fn max_3d_texture_size(&self) -> u32 {
+        MTLFeatureSet::max_3d_texture_size(self)
+    }
+
+    fn max_cube_map_texture_size(&self) -> u32 {
+        MTLFeatureSet::max_cube_map_texture_size(self)
+    }
+
+    fn max_array_layers(&self) -> u32 {
+        MTLFeatureSet::max_array_layers(self)
+    }
+
+    fn pixel_format_capabilities(&self, format: MTLPixelFormat) -> PixelFormatCapabilities {
+        MTLFeatureSet::pixel_format_capabilities(self, format)
+    }
+
+    fn supports_sample_count(&self, count: NSUInteger) -> bool {
+        match count {
+            1 | 2 | 4 => true,
+            8 => self.os() == OS::macOS,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub(crate) enum GPUFamilyKind {
+    Apple(u32),
+    Mac(u32),
+    Common(u32),
+}
+
+// Values follow Apple's Metal feature set tables and assume the newest OS release,
+// since GPU families are not versioned.
+impl MTLGPUFamily {
+    pub(crate) fn kind(&self) -> GPUFamilyKind {
+        use MTLGPUFamily::*;
+        match self {
+            Apple1 => GPUFamilyKind::Apple(1),
+            Apple2 => GPUFamilyKind::Apple(2),
+            Apple3 => GPUFamilyKind::Apple(3),
+            Apple4 => GPUFamilyKind::Apple(4),
+            Apple5 => GPUFamilyKind::Apple(5),
+            Apple6 => GPUFamilyKind::Apple(6),
+            Apple7 => GPUFamilyKind::Apple(7),
+            Apple8 => GPUFamilyKind::Apple(8),
+            Apple9 => GPUFamilyKind::Apple(9),
+            Mac1 | MacCatalyst1 => GPUFamilyKind::Mac(1),
+            Mac2 | MacCatalyst2 => GPUFamilyKind::Mac(2),
+            Common1 => GPUFamilyKind::Common(1),
+            Common2 => GPUFamilyKind::Common(2),
+            Common3 => GPUFamilyKind::Common(3),
+        }
+    }
+
+    // A common family only guarantees what both its Apple counterpart and `Mac1` provide.
+    fn common_counterparts(&self) -> (MTLGPUFamily, MTLGPUFamily) {
+        match self.kind() {
+            GPUFamilyKind::Common(1) => (MTLGPUFamily::Apple1, MTLGPUFamily::Mac1),
+            GPUFamilyKind::Common(2) => (MTLGPUFamily::Apple3, MTLGPUFamily::Mac1),
+            GPUFamilyKind::Common(_) => (MTLGPUFamily::Apple5, MTLGPUFamily::Mac1),
+            _ => (*self, *self),
+        }
+    }
+
+    fn common_all(&self, feature: fn(&MTLGPUFamily) -> bool) -> bool {
+        let (apple, mac) = self.common_counterparts();
+        feature(&apple) && feature(&mac)
+    }
+
+    fn common_min(&self, limit: fn(&MTLGPUFamily) -> u32) -> u32 {
+        let (apple, mac) = self.common_counterparts();
+        limit(&apple).min(limit(&mac))
+    }
+
+    fn common_max(&self, limit: fn(&MTLGPUFamily) -> u32) -> u32 {
+        let (apple, mac) = self.common_counterparts();
+        limit(&apple).max(limit(&mac))
+    }
+
+    pub fn supports_metal_kit(&self) -> bool {
+        true
+    }
+
+    pub fn supports_metal_performance_shaders(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 2,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_metal_performance_shaders),
+        }
+    }
+
+    pub fn supports_programmable_blending(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => true,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_programmable_blending),
+        }
+    }
+
+    pub fn supports_pvrtc_pixel_formats(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => true,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_pvrtc_pixel_formats),
+        }
+    }
+
+    pub fn supports_eac_etc_pixel_formats(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => true,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_eac_etc_pixel_formats),
+        }
+    }
+
+    pub fn supports_astc_pixel_formats(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 2,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_astc_pixel_formats),
+        }
+    }
+
+    pub fn supports_astc_hdr_pixel_formats(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 6,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_astc_hdr_pixel_formats),
+        }
+    }
+
+    pub fn supports_linear_textures(&self) -> bool {
+        true
+    }
+
+    pub fn supports_bc_pixel_formats(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => false,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_bc_pixel_formats),
+        }
+    }
+
+    pub fn supports_msaa_depth_resolve(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 3,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_msaa_depth_resolve),
+        }
+    }
+
+    pub fn supports_counting_occlusion_query(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 3,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_counting_occlusion_query),
+        }
+    }
+
+    pub fn supports_base_vertex_instance_drawing(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 3,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => {
+                self.common_all(Self::supports_base_vertex_instance_drawing)
+            }
+        }
+    }
+
+    pub fn supports_indirect_buffers(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 3,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_indirect_buffers),
+        }
+    }
+
+    pub fn supports_cube_map_texture_arrays(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 4,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_cube_map_texture_arrays),
+        }
+    }
+
+    pub fn supports_texture_barriers(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => false,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_texture_barriers),
+        }
+    }
+
+    pub fn supports_layered_rendering(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 5,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_layered_rendering),
+        }
+    }
+
+    pub fn supports_tessellation(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 3,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_tessellation),
+        }
+    }
+
+    pub fn supports_resource_heaps(&self) -> bool {
+        true
+    }
+
+    pub fn supports_memoryless_render_targets(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => true,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_memoryless_render_targets),
+        }
+    }
+
+    pub fn supports_function_specialization(&self) -> bool {
+        true
+    }
+
+    pub fn supports_function_buffer_read_writes(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 3,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_function_buffer_read_writes),
+        }
+    }
+
+    pub fn supports_function_texture_read_writes(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 4,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => {
+                self.common_all(Self::supports_function_texture_read_writes)
+            }
+        }
+    }
+
+    pub fn supports_array_of_textures(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 3,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_array_of_textures),
+        }
+    }
+
+    pub fn supports_array_of_samplers(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 3,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_array_of_samplers),
+        }
+    }
+
+    pub fn supports_stencil_texture_views(&self) -> bool {
+        true
+    }
+
+    pub fn supports_depth_16_pixel_format(&self) -> bool {
+        true
+    }
+
+    pub fn supports_extended_range_pixel_formats(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 3,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => {
+                self.common_all(Self::supports_extended_range_pixel_formats)
+            }
+        }
+    }
+
+    pub fn supports_wide_color_pixel_format(&self) -> bool {
+        true
+    }
+
+    pub fn supports_combined_msaa_store_and_resolve_action(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 3,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => {
+                self.common_all(Self::supports_combined_msaa_store_and_resolve_action)
+            }
+        }
+    }
+
+    pub fn supports_deferred_store_action(&self) -> bool {
+        true
+    }
+
+    pub fn supports_msaa_blits(&self) -> bool {
+        true
+    }
+
+    pub fn supports_srgb_writes(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 2,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_srgb_writes),
+        }
+    }
+
+    pub fn supports_16_bit_unsigned_integer_coordinates(&self) -> bool {
+        true
+    }
+
+    pub fn supports_extract_insert_and_reverse_bits(&self) -> bool {
+        true
+    }
+
+    pub fn supports_simd_barrier(&self) -> bool {
+        true
+    }
+
+    pub fn supports_sampler_max_anisotropy(&self) -> bool {
+        true
+    }
+
+    pub fn supports_sampler_lod_clamp(&self) -> bool {
+        true
+    }
+
+    pub fn supports_border_color(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 7,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_border_color),
+        }
+    }
+
+    pub fn supports_dual_source_blending(&self) -> bool {
+        true
+    }
+
+    pub fn supports_argument_buffers(&self) -> bool {
+        true
+    }
+
+    pub fn supports_programmable_sample_positions(&self) -> bool {
+        true
+    }
+
+    pub fn supports_uniform_type(&self) -> bool {
+        true
+    }
+
+    pub fn supports_imageblocks(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 4,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_imageblocks),
+        }
+    }
+
+    pub fn supports_tile_shaders(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 4,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_tile_shaders),
+        }
+    }
+
+    pub fn supports_imageblock_sample_coverage_control(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 4,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => {
+                self.common_all(Self::supports_imageblock_sample_coverage_control)
+            }
+        }
+    }
+
+    pub fn supports_threadgroup_sharing(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 4,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_threadgroup_sharing),
+        }
+    }
+
+    pub fn supports_post_depth_coverage(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 4,
+            GPUFamilyKind::Mac(_) => false,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_post_depth_coverage),
+        }
+    }
+
+    pub fn supports_quad_scoped_permute_operations(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 4,
+            GPUFamilyKind::Mac(family) => family >= 2,
+            GPUFamilyKind::Common(_) => {
+                self.common_all(Self::supports_quad_scoped_permute_operations)
+            }
+        }
+    }
+
+    // `macOS_GPUFamily1_v4` reports raster order groups because some Mac1 GPUs support them,
+    // but the family tables only guarantee them from `Mac2`.
+    pub fn supports_raster_order_groups(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 4,
+            GPUFamilyKind::Mac(family) => family >= 2,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_raster_order_groups),
+        }
+    }
+
+    pub fn supports_non_uniform_threadgroup_size(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 4,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => {
+                self.common_all(Self::supports_non_uniform_threadgroup_size)
+            }
+        }
+    }
+
+    pub fn supports_multiple_viewports(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 5,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_multiple_viewports),
+        }
+    }
+
+    pub fn supports_device_notifications(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => false,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_device_notifications),
+        }
+    }
+
+    pub fn supports_stencil_feedback(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 5,
+            GPUFamilyKind::Mac(family) => family >= 2,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_stencil_feedback),
+        }
+    }
+
+    pub fn supports_stencil_resolve(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 5,
+            GPUFamilyKind::Mac(family) => family >= 2,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_stencil_resolve),
+        }
+    }
+
+    pub fn supports_binary_archive(&self) -> bool {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) => family >= 3,
+            GPUFamilyKind::Mac(_) => true,
+            GPUFamilyKind::Common(_) => self.common_all(Self::supports_binary_archive),
+        }
+    }
+
+    pub fn max_vertex_attributes(&self) -> u32 {
+        31
+    }
+
+    pub fn max_buffer_argument_entries(&self) -> u32 {
+        31
+    }
+
+    pub fn max_texture_argument_entries(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) if family >= 6 => 128,
+            GPUFamilyKind::Apple(_) => 31,
+            GPUFamilyKind::Mac(_) => 128,
+            GPUFamilyKind::Common(_) => self.common_min(Self::max_texture_argument_entries),
+        }
+    }
+
+    pub fn max_sampler_state_argument_entries(&self) -> u32 {
+        16
+    }
+
+    pub fn max_threadgroup_memory_argument_entries(&self) -> u32 {
+        31
+    }
+
+    pub fn max_inlined_constant_data_buffers(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => 31,
+            GPUFamilyKind::Mac(_) => 14,
+            GPUFamilyKind::Common(_) => self.common_min(Self::max_inlined_constant_data_buffers),
+        }
+    }
+
+    pub fn max_inline_constant_buffer_length(&self) -> u32 {
+        4 * KB
+    }
+
+    pub fn max_threads_per_threadgroup(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) if family >= 4 => 1024,
+            GPUFamilyKind::Apple(_) => 512,
+            GPUFamilyKind::Mac(_) => 1024,
+            GPUFamilyKind::Common(_) => self.common_min(Self::max_threads_per_threadgroup),
+        }
+    }
+
+    // The family tables list 32 KB for every Apple family from `Apple4` on, whereas the older
+    // `iOS_GPUFamily4_v2` and `iOS_GPUFamily5_v1` tables list 64 KB. Use the family value, which
+    // is what every device in the family is guaranteed to provide.
+    pub fn max_total_threadgroup_memory_allocation(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) if family >= 4 => 32 * KB,
+            GPUFamilyKind::Apple(3) => 16 * KB,
+            GPUFamilyKind::Apple(_) => 16 * KB - 32,
+            GPUFamilyKind::Mac(_) => 32 * KB,
+            GPUFamilyKind::Common(_) => {
+                self.common_min(Self::max_total_threadgroup_memory_allocation)
+            }
+        }
+    }
+
+    pub fn max_total_tile_memory_allocation(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) if family >= 4 => 32 * KB,
+            _ => 0,
+        }
+    }
+
+    pub fn threadgroup_memory_length_alignment(&self) -> u32 {
+        16
+    }
+
+    pub fn max_constant_buffer_function_memory_allocation(&self) -> Option<u32> {
+        match self.kind() {
+            GPUFamilyKind::Mac(_) => Some(64 * KB),
+            _ => None,
+        }
+    }
+
+    pub fn max_fragment_inputs(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => 60,
+            GPUFamilyKind::Mac(_) => 32,
+            GPUFamilyKind::Common(_) => self.common_min(Self::max_fragment_inputs),
+        }
+    }
+
+    pub fn max_fragment_input_components(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => 60,
+            GPUFamilyKind::Mac(_) => 128,
+            GPUFamilyKind::Common(_) => self.common_min(Self::max_fragment_input_components),
+        }
+    }
+
+    pub fn max_function_constants(&self) -> u32 {
+        65536
+    }
+
+    pub fn max_tessellation_factor(&self) -> u32 {
+        if self.supports_tessellation() {
+            match self.kind() {
+                GPUFamilyKind::Apple(family) if family >= 5 => 64,
+                GPUFamilyKind::Apple(_) => 16,
+                GPUFamilyKind::Mac(_) => 64,
+                GPUFamilyKind::Common(_) => self.common_min(Self::max_tessellation_factor),
+            }
+        } else {
+            0
+        }
+    }
+
+    pub fn max_viewports_and_scissor_rectangles(&self) -> u32 {
+        if self.supports_multiple_viewports() {
+            16
+        } else {
+            1
+        }
+    }
+
+    pub fn max_raster_order_groups(&self) -> u32 {
+        if self.supports_raster_order_groups() {
+            8
+        } else {
+            0
+        }
+    }
+
+    pub fn max_buffer_length(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => 256 * MB,
+            GPUFamilyKind::Mac(_) => GB,
+            GPUFamilyKind::Common(_) => self.common_min(Self::max_buffer_length),
+        }
+    }
+
+    pub fn min_buffer_offset_alignment(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(_) => 4,
+            GPUFamilyKind::Mac(_) => 256,
+            GPUFamilyKind::Common(_) => self.common_max(Self::min_buffer_offset_alignment),
+        }
+    }
+
+    pub fn max_1d_texture_size(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) if family >= 3 => 16384,
+            GPUFamilyKind::Apple(_) => 8192,
+            GPUFamilyKind::Mac(_) => 16384,
+            GPUFamilyKind::Common(_) => self.common_min(Self::max_1d_texture_size),
+        }
+    }
+
+    pub fn max_2d_texture_size(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) if family >= 3 => 16384,
+            GPUFamilyKind::Apple(_) => 8192,
+            GPUFamilyKind::Mac(_) => 16384,
+            GPUFamilyKind::Common(_) => self.common_min(Self::max_2d_texture_size),
+        }
+    }
+
+    pub fn max_cube_map_texture_size(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) if family >= 3 => 16384,
+            GPUFamilyKind::Apple(_) => 8192,
+            GPUFamilyKind::Mac(_) => 16384,
+            GPUFamilyKind::Common(_) => self.common_min(Self::max_cube_map_texture_size),
+        }
+    }
+
+    pub fn max_3d_texture_size(&self) -> u32 {
+        2048
+    }
+
+    pub fn max_array_layers(&self) -> u32 {
+        2048
+    }
+
+    pub fn copy_texture_buffer_alignment(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(family) if family >= 3 => 16,
+            GPUFamilyKind::Apple(_) => 64,
+            GPUFamilyKind::Mac(_) => 256,
+            GPUFamilyKind::Common(_) => self.common_max(Self::copy_texture_buffer_alignment),
+        }
+    }
+
+    /// Every GPU family requires an OS where the linear texture alignment
+    /// has to be discovered via API query
+    pub fn new_texture_buffer_alignment(&self) -> Option<u32> {
+        None
+    }
+
+    pub fn max_color_render_targets(&self) -> u32 {
+        match self.kind() {
+            GPUFamilyKind::Apple(1) => 4,
+            GPUFamilyKind::Apple(_) | GPUFamilyKind::Mac(_) => 8,
+            GPUFamilyKind::Common(_) => self.common_min(Self::max_color_render_targets),
+        }
+    }
+
+    pub fn max_point_primitive_size(&self) -> u32 {
+        511
+    }
+
+    pub fn max_total_color_render_target_size(&self) -> Option<u32> {
+        match self.kind() {
+            GPUFamilyKind::Apple(1) => Some(128),
+            GPUFamilyKind::Apple(2) | GPUFamilyKind::Apple(3) => Some(256),
+            GPUFamilyKind::Apple(_) => Some(512),
+            GPUFamilyKind::Mac(_) => None,
+            GPUFamilyKind::Common(_) => self
+                .common_counterparts()
+                .0
+                .max_total_color_render_target_size(),
+        }
+    }
+
+    pub fn max_visibility_query_offset(&self) -> u32 {
+        64 * KB - 8
+    }
 }
 
 #[repr(u64)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MTLArgumentBuffersTier {
     Tier1 = 0,
     Tier2 = 1,
@@ -1389,6 +2195,7 @@ pub enum MTLArgumentBuffersTier {
 
 #[repr(u64)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MTLReadWriteTextureTier {
     TierNone = 0,
     Tier1 = 1,
@@ -1439,6 +2246,7 @@ pub struct MTLAccelerationStructureSizes {
     pub refit_scratch_buffer_size: NSUInteger,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[link(name = "Metal", kind = "framework")]
 extern "C" {
     fn MTLCreateSystemDefaultDevice() -> *mut MTLDevice;
@@ -1446,21 +2254,18 @@ extern "C" {
     fn MTLCopyAllDevices() -> *mut Object; //TODO: Array
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[allow(non_camel_case_types)]
 type dispatch_data_t = *mut Object;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[allow(non_camel_case_types)]
 pub type dispatch_queue_t = *mut Object;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[allow(non_camel_case_types)]
 type dispatch_block_t = *const Block<(), ()>;
 
-#[cfg_attr(
-    any(target_os = "macos", target_os = "ios"),
-    link(name = "System", kind = "dylib")
-)]
-#[cfg_attr(
-    not(any(target_os = "macos", target_os = "ios")),
-    link(name = "dispatch", kind = "dylib")
-)]
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+#[link(name = "System", kind = "dylib")]
 #[allow(improper_ctypes)]
 extern "C" {
     static _dispatch_main_q: dispatch_queue_t;
@@ -1480,14 +2285,17 @@ type MTLNewRenderPipelineStateWithReflectionCompletionHandler = extern fn(render
 type MTLNewComputePipelineStateCompletionHandler = extern fn(computePipelineState: id, error: id);
 type MTLNewComputePipelineStateWithReflectionCompletionHandler = extern fn(computePipelineState: id, reflection: id, error: id);*/
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLDevice {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLDevice;
     pub struct Device;
     pub struct DeviceRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl Device {
     pub fn system_default() -> Option<Self> {
         // `MTLCreateSystemDefaultDevice` may return null if Metal is not supported
@@ -1516,6 +2324,7 @@ impl Device {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl DeviceRef {
     pub fn name(&self) -> &str {
         unsafe {
@@ -1730,7 +2539,7 @@ impl DeviceRef {
         &self,
         src: &str,
         options: &CompileOptionsRef,
-    ) -> Result<Library, String> {
+    ) -> Result<Library, MetalError> {
         let source = nsstring_from_str(src);
         unsafe {
             let mut err: *mut Object = ptr::null_mut();
@@ -1738,13 +2547,11 @@ impl DeviceRef {
                                                                         options:options
                                                                           error:&mut err];
             if !err.is_null() {
-                let desc: *mut Object = msg_send![err, localizedDescription];
-                let compile_error: *const c_char = msg_send![desc, UTF8String];
-                let message = CStr::from_ptr(compile_error).to_string_lossy().into_owned();
+                let error = MetalError::from_nserror(err);
                 if library.is_null() {
-                    return Err(message);
+                    return Err(error);
                 } else {
-                    warn!("Shader warnings: {}", message);
+                    warn!("Shader warnings: {}", error);
                 }
             }
 
@@ -1753,7 +2560,7 @@ impl DeviceRef {
         }
     }
 
-    pub fn new_library_with_file<P: AsRef<Path>>(&self, file: P) -> Result<Library, String> {
+    pub fn new_library_with_file<P: AsRef<Path>>(&self, file: P) -> Result<Library, MetalError> {
         let filename = nsstring_from_str(file.as_ref().to_string_lossy().as_ref());
         unsafe {
             let library: *mut MTLLibrary = try_objc! { err =>
@@ -1764,7 +2571,7 @@ impl DeviceRef {
         }
     }
 
-    pub fn new_library_with_data(&self, library_data: &[u8]) -> Result<Library, String> {
+    pub fn new_library_with_data(&self, library_data: &[u8]) -> Result<Library, MetalError> {
         unsafe {
             let destructor_block = ConcreteBlock::new(|| {}).copy();
             let data = dispatch_data_create(
@@ -1784,17 +2591,13 @@ impl DeviceRef {
     }
 
     /// Only available on (macos(11.0), ios(14.0))
-    pub fn new_dynamic_library(&self, library: &LibraryRef) -> Result<DynamicLibrary, String> {
+    pub fn new_dynamic_library(&self, library: &LibraryRef) -> Result<DynamicLibrary, MetalError> {
         unsafe {
             let mut err: *mut Object = ptr::null_mut();
             let dynamic_library: *mut MTLDynamicLibrary = msg_send![self, newDynamicLibrary:library
                                                                                       error:&mut err];
             if !err.is_null() {
-                // FIXME: copy pasta
-                let desc: *mut Object = msg_send![err, localizedDescription];
-                let compile_error: *const c_char = msg_send![desc, UTF8String];
-                let message = CStr::from_ptr(compile_error).to_string_lossy().into_owned();
-                Err(message)
+                Err(MetalError::from_nserror(err))
             } else {
                 Ok(DynamicLibrary::from_ptr(dynamic_library))
             }
@@ -1802,17 +2605,13 @@ impl DeviceRef {
     }
 
     /// Only available on (macos(11.0), ios(14.0))
-    pub fn new_dynamic_library_with_url(&self, url: &URLRef) -> Result<DynamicLibrary, String> {
+    pub fn new_dynamic_library_with_url(&self, url: &URLRef) -> Result<DynamicLibrary, MetalError> {
         unsafe {
             let mut err: *mut Object = ptr::null_mut();
             let dynamic_library: *mut MTLDynamicLibrary = msg_send![self, newDynamicLibraryWithURL:url
                                                                                              error:&mut err];
             if !err.is_null() {
-                // FIXME: copy pasta
-                let desc: *mut Object = msg_send![err, localizedDescription];
-                let compile_error: *const c_char = msg_send![desc, UTF8String];
-                let message = CStr::from_ptr(compile_error).to_string_lossy().into_owned();
-                Err(message)
+                Err(MetalError::from_nserror(err))
             } else {
                 Ok(DynamicLibrary::from_ptr(dynamic_library))
             }
@@ -1823,17 +2622,13 @@ impl DeviceRef {
     pub fn new_binary_archive_with_descriptor(
         &self,
         descriptor: &BinaryArchiveDescriptorRef,
-    ) -> Result<BinaryArchive, String> {
+    ) -> Result<BinaryArchive, MetalError> {
         unsafe {
             let mut err: *mut Object = ptr::null_mut();
             let binary_archive: *mut MTLBinaryArchive = msg_send![self, newBinaryArchiveWithDescriptor:descriptor
                                                      error:&mut err];
             if !err.is_null() {
-                // TODO: copy pasta
-                let desc: *mut Object = msg_send![err, localizedDescription];
-                let c_msg: *const c_char = msg_send![desc, UTF8String];
-                let message = CStr::from_ptr(c_msg).to_string_lossy().into_owned();
-                Err(message)
+                Err(MetalError::from_nserror(err))
             } else {
                 Ok(BinaryArchive::from_ptr(binary_archive))
             }
@@ -1845,7 +2640,7 @@ impl DeviceRef {
         &self,
         descriptor: &RenderPipelineDescriptorRef,
         reflection_options: MTLPipelineOption,
-    ) -> Result<(RenderPipelineState, RenderPipelineReflection), String> {
+    ) -> Result<(RenderPipelineState, RenderPipelineReflection), MetalError> {
         unsafe {
             let mut reflection: *mut Object = ptr::null_mut();
             let pipeline_state: *mut MTLRenderPipelineState = try_objc! { err =>
@@ -1867,7 +2662,7 @@ impl DeviceRef {
     pub fn new_render_pipeline_state(
         &self,
         descriptor: &RenderPipelineDescriptorRef,
-    ) -> Result<RenderPipelineState, String> {
+    ) -> Result<RenderPipelineState, MetalError> {
         unsafe {
             let pipeline_state: *mut MTLRenderPipelineState = try_objc! { err =>
                 msg_send![self, newRenderPipelineStateWithDescriptor:descriptor
@@ -1881,7 +2676,7 @@ impl DeviceRef {
     pub fn new_compute_pipeline_state_with_function(
         &self,
         function: &FunctionRef,
-    ) -> Result<ComputePipelineState, String> {
+    ) -> Result<ComputePipelineState, MetalError> {
         unsafe {
             let pipeline_state: *mut MTLComputePipelineState = try_objc! { err =>
                 msg_send![self, newComputePipelineStateWithFunction:function
@@ -1895,7 +2690,7 @@ impl DeviceRef {
     pub fn new_compute_pipeline_state(
         &self,
         descriptor: &ComputePipelineDescriptorRef,
-    ) -> Result<ComputePipelineState, String> {
+    ) -> Result<ComputePipelineState, MetalError> {
         unsafe {
             let pipeline_state: *mut MTLComputePipelineState = try_objc! { err =>
                 msg_send![self, newComputePipelineStateWithDescriptor:descriptor
@@ -1912,7 +2707,7 @@ impl DeviceRef {
         &self,
         descriptor: &ComputePipelineDescriptorRef,
         reflection_options: MTLPipelineOption,
-    ) -> Result<(ComputePipelineState, ComputePipelineReflection), String> {
+    ) -> Result<(ComputePipelineState, ComputePipelineReflection), MetalError> {
         unsafe {
             let mut reflection: *mut Object = ptr::null_mut();
             let pipeline_state: *mut MTLComputePipelineState = try_objc! { err =>
@@ -2115,3 +2910,184 @@ impl DeviceRef {
         unsafe { msg_send![self, maxBufferLength] }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEATURE_SETS: [MTLFeatureSet; 29] = [
+        MTLFeatureSet::iOS_GPUFamily1_v1,
+        MTLFeatureSet::iOS_GPUFamily2_v1,
+        MTLFeatureSet::iOS_GPUFamily1_v2,
+        MTLFeatureSet::iOS_GPUFamily2_v2,
+        MTLFeatureSet::iOS_GPUFamily3_v1,
+        MTLFeatureSet::iOS_GPUFamily1_v3,
+        MTLFeatureSet::iOS_GPUFamily2_v3,
+        MTLFeatureSet::iOS_GPUFamily3_v2,
+        MTLFeatureSet::iOS_GPUFamily1_v4,
+        MTLFeatureSet::iOS_GPUFamily2_v4,
+        MTLFeatureSet::iOS_GPUFamily3_v3,
+        MTLFeatureSet::iOS_GPUFamily4_v1,
+        MTLFeatureSet::iOS_GPUFamily1_v5,
+        MTLFeatureSet::iOS_GPUFamily2_v5,
+        MTLFeatureSet::iOS_GPUFamily3_v4,
+        MTLFeatureSet::iOS_GPUFamily4_v2,
+        MTLFeatureSet::iOS_GPUFamily5_v1,
+        MTLFeatureSet::tvOS_GPUFamily1_v1,
+        MTLFeatureSet::tvOS_GPUFamily1_v2,
+        MTLFeatureSet::tvOS_GPUFamily1_v3,
+        MTLFeatureSet::tvOS_GPUFamily2_v1,
+        MTLFeatureSet::tvOS_GPUFamily1_v4,
+        MTLFeatureSet::tvOS_GPUFamily2_v2,
+        MTLFeatureSet::macOS_GPUFamily1_v1,
+        MTLFeatureSet::macOS_GPUFamily1_v2,
+        MTLFeatureSet::macOS_ReadWriteTextureTier2,
+        MTLFeatureSet::macOS_GPUFamily1_v3,
+        MTLFeatureSet::macOS_GPUFamily1_v4,
+        MTLFeatureSet::macOS_GPUFamily2_v1,
+    ];
+
+    /// Expected values follow the Metal feature set tables rather than the per-format methods.
+    #[test]
+    fn pixel_format_capabilities_per_os() {
+        use MTLPixelFormat::*;
+
+        let all = PixelFormatCapabilities::all();
+        let filter = PixelFormatCapabilities::Filter;
+        let none = PixelFormatCapabilities::empty();
+        for feature_set in &FEATURE_SETS {
+            let macos = feature_set.os() == OS::macOS;
+            let apple_family_1 = feature_set.os() == OS::iOS && feature_set.gpu_family() == 1;
+            let capabilities = |format| feature_set.pixel_format_capabilities(format);
+            let on = |supported: bool, capabilities| if supported { capabilities } else { none };
+
+            assert_eq!(capabilities(Invalid), none, "{:?}", feature_set);
+            assert_eq!(capabilities(RGBA8Unorm), all, "{:?}", feature_set);
+            assert_eq!(capabilities(ASTC_4x4_HDR), none, "{:?}", feature_set);
+            assert_eq!(
+                capabilities(BC1_RGBA),
+                on(macos, filter),
+                "{:?}",
+                feature_set
+            );
+            assert_eq!(capabilities(BC7_RGBAUnorm_sRGB), on(macos, filter));
+            assert_eq!(capabilities(PVRTC_RGBA_4BPP), on(!macos, filter));
+            assert_eq!(capabilities(ETC2_RGB8), on(!macos, filter));
+            assert_eq!(capabilities(EAC_RG11Snorm), on(!macos, filter));
+            assert_eq!(
+                capabilities(ASTC_8x8_LDR),
+                on(!macos && !apple_family_1, filter),
+                "{:?}",
+                feature_set
+            );
+            assert_eq!(
+                capabilities(Depth24Unorm_Stencil8).is_empty(),
+                !macos,
+                "{:?}",
+                feature_set
+            );
+            assert_eq!(
+                capabilities(R32Float).contains(filter),
+                macos,
+                "{:?}",
+                feature_set
+            );
+            assert_eq!(capabilities(GBGR422), filter);
+            assert_eq!(capabilities(Stencil8), PixelFormatCapabilities::Msaa);
+        }
+    }
+
+    /// `(family, astc, bc, raster order groups, tessellation, threadgroup memory,
+    /// texture argument entries, buffer offset alignment, color render targets)`
+    type GPUFamilyRow = (MTLGPUFamily, bool, bool, bool, bool, u32, u32, u32, u32);
+
+    #[rustfmt::skip]
+    const GPU_FAMILY_TABLE: [GPUFamilyRow; 16] = [
+        (MTLGPUFamily::Common1,      false, false, false, false, 16 * KB - 32,  31, 256, 4),
+        (MTLGPUFamily::Common2,      false, false, false, true,  16 * KB,       31, 256, 8),
+        (MTLGPUFamily::Common3,      false, false, false, true,  32 * KB,       31, 256, 8),
+        (MTLGPUFamily::Apple1,       false, false, false, false, 16 * KB - 32,  31,   4, 4),
+        (MTLGPUFamily::Apple2,       true,  false, false, false, 16 * KB - 32,  31,   4, 8),
+        (MTLGPUFamily::Apple3,       true,  false, false, true,  16 * KB,       31,   4, 8),
+        (MTLGPUFamily::Apple4,       true,  false, true,  true,  32 * KB,       31,   4, 8),
+        (MTLGPUFamily::Apple5,       true,  false, true,  true,  32 * KB,       31,   4, 8),
+        (MTLGPUFamily::Apple6,       true,  false, true,  true,  32 * KB,      128,   4, 8),
+        (MTLGPUFamily::Apple7,       true,  false, true,  true,  32 * KB,      128,   4, 8),
+        (MTLGPUFamily::Apple8,       true,  false, true,  true,  32 * KB,      128,   4, 8),
+        (MTLGPUFamily::Apple9,       true,  false, true,  true,  32 * KB,      128,   4, 8),
+        (MTLGPUFamily::Mac1,         false, true,  false, true,  32 * KB,      128, 256, 8),
+        (MTLGPUFamily::Mac2,         false, true,  true,  true,  32 * KB,      128, 256, 8),
+        (MTLGPUFamily::MacCatalyst1, false, true,  false, true,  32 * KB,      128, 256, 8),
+        (MTLGPUFamily::MacCatalyst2, false, true,  true,  true,  32 * KB,      128, 256, 8),
+    ];
+
+    #[test]
+    fn gpu_family_table() {
+        for &(family, astc, bc, rog, tessellation, threadgroup, textures, alignment, targets) in
+            &GPU_FAMILY_TABLE
+        {
+            assert_eq!(family.supports_astc_pixel_formats(), astc, "{:?}", family);
+            assert_eq!(family.supports_bc_pixel_formats(), bc, "{:?}", family);
+            assert_eq!(family.supports_raster_order_groups(), rog, "{:?}", family);
+            assert_eq!(family.supports_tessellation(), tessellation, "{:?}", family);
+            assert_eq!(
+                family.max_total_threadgroup_memory_allocation(),
+                threadgroup,
+                "{:?}",
+                family
+            );
+            assert_eq!(
+                family.max_texture_argument_entries(),
+                textures,
+                "{:?}",
+                family
+            );
+            assert_eq!(
+                family.min_buffer_offset_alignment(),
+                alignment,
+                "{:?}",
+                family
+            );
+            assert_eq!(family.max_color_render_targets(), targets, "{:?}", family);
+        }
+    }
+
+    #[test]
+    fn common_families_fold_apple_and_mac() {
+        use MTLGPUFamily::*;
+
+        // Features only one side has are dropped.
+        for &family in &[Common1, Common2, Common3] {
+            assert!(!family.supports_programmable_blending(), "{:?}", family);
+            assert!(!family.supports_texture_barriers(), "{:?}", family);
+            assert!(!family.supports_pvrtc_pixel_formats(), "{:?}", family);
+            assert!(family.supports_resource_heaps(), "{:?}", family);
+            assert_eq!(family.max_inlined_constant_data_buffers(), 14);
+            assert_eq!(family.max_fragment_input_components(), 60);
+            assert_eq!(family.max_buffer_length(), 256 * MB);
+            assert_eq!(family.copy_texture_buffer_alignment(), 256);
+        }
+        assert_eq!(Common1.max_threads_per_threadgroup(), 512);
+        assert_eq!(Common3.max_threads_per_threadgroup(), 1024);
+        assert_eq!(Common1.max_tessellation_factor(), 0);
+        assert_eq!(Common2.max_tessellation_factor(), 16);
+        assert_eq!(Common3.max_tessellation_factor(), 64);
+        assert_eq!(Common1.max_total_color_render_target_size(), Some(128));
+        assert_eq!(Common2.max_total_color_render_target_size(), Some(256));
+        assert_eq!(Common3.max_total_color_render_target_size(), Some(512));
+    }
+
+    #[test]
+    fn gpu_family_and_feature_set_differences() {
+        assert_eq!(
+            MTLGPUFamily::Apple4.max_total_threadgroup_memory_allocation(),
+            32 * KB
+        );
+        assert_eq!(
+            MTLFeatureSet::iOS_GPUFamily4_v2.max_total_threadgroup_memory_allocation(),
+            64 * KB
+        );
+        assert!(!MTLGPUFamily::Mac1.supports_raster_order_groups());
+        assert!(MTLFeatureSet::macOS_GPUFamily1_v4.supports_raster_order_groups());
+    }
+}
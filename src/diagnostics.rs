@@ -0,0 +1,476 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use super::*;
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use objc::runtime::Object;
+
+use std::{error::Error, fmt};
+
+pub const MTL_LIBRARY_ERROR_DOMAIN: &str = "MTLLibraryErrorDomain";
+pub const MTL_DYNAMIC_LIBRARY_DOMAIN: &str = "MTLDynamicLibraryDomain";
+/// The domain of errors raised by this crate when a Metal call fails without an `NSError`.
+pub const METAL_RS_ERROR_DOMAIN: &str = "metal-rs";
+
+/// The Metal error enum a `MetalError` code belongs to, decided by its domain.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MetalErrorKind {
+    Library(MTLLibraryError),
+    DynamicLibrary(MTLDynamicLibraryError),
+    /// A domain or code without a matching enum, e.g. errors raised by the GPU driver while
+    /// building a pipeline.
+    Other,
+}
+
+/// An `NSError` returned by a Metal call, keeping its domain and code alongside the message.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MetalError {
+    pub domain: String,
+    pub code: NSInteger,
+    /// The error's `localizedDescription`. For shader compile failures this is the compiler
+    /// log, see `diagnostics`.
+    pub message: String,
+}
+
+impl MetalError {
+    pub fn new(domain: &str, code: NSInteger, message: impl Into<String>) -> Self {
+        MetalError {
+            domain: domain.to_owned(),
+            code,
+            message: message.into(),
+        }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub(crate) fn library(code: MTLLibraryError, message: impl Into<String>) -> Self {
+        MetalError::new(MTL_LIBRARY_ERROR_DOMAIN, code as NSInteger, message)
+    }
+
+    /// An error for a Metal call that returned nil without filling in its `NSError`.
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub(crate) fn unreported(message: impl Into<String>) -> Self {
+        MetalError::new(METAL_RS_ERROR_DOMAIN, 0, message)
+    }
+
+    /// Copies the domain, code and description out of an `NSError`. The error is not released.
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub(crate) unsafe fn from_nserror(error: *mut Object) -> Self {
+        unsafe fn to_string(string: *mut Object) -> String {
+            let bytes: *const std::os::raw::c_char = msg_send![string, UTF8String];
+            std::ffi::CStr::from_ptr(bytes)
+                .to_string_lossy()
+                .into_owned()
+        }
+
+        let domain: *mut Object = msg_send![error, domain];
+        let code: NSInteger = msg_send![error, code];
+        let description: *mut Object = msg_send![error, localizedDescription];
+        MetalError {
+            domain: to_string(domain),
+            code,
+            message: to_string(description),
+        }
+    }
+
+    pub fn kind(&self) -> MetalErrorKind {
+        match (self.domain.as_str(), self.code) {
+            (MTL_LIBRARY_ERROR_DOMAIN, 1) => MetalErrorKind::Library(MTLLibraryError::Unsupported),
+            (MTL_LIBRARY_ERROR_DOMAIN, 2) => MetalErrorKind::Library(MTLLibraryError::Internal),
+            (MTL_LIBRARY_ERROR_DOMAIN, 3) => {
+                MetalErrorKind::Library(MTLLibraryError::CompileFailure)
+            }
+            (MTL_LIBRARY_ERROR_DOMAIN, 4) => {
+                MetalErrorKind::Library(MTLLibraryError::CompileWarning)
+            }
+            (MTL_LIBRARY_ERROR_DOMAIN, 5) => {
+                MetalErrorKind::Library(MTLLibraryError::FunctionNotFound)
+            }
+            (MTL_LIBRARY_ERROR_DOMAIN, 6) => MetalErrorKind::Library(MTLLibraryError::FileNotFound),
+            (MTL_DYNAMIC_LIBRARY_DOMAIN, 0) => {
+                MetalErrorKind::DynamicLibrary(MTLDynamicLibraryError::None)
+            }
+            (MTL_DYNAMIC_LIBRARY_DOMAIN, 1) => {
+                MetalErrorKind::DynamicLibrary(MTLDynamicLibraryError::InvalidFile)
+            }
+            (MTL_DYNAMIC_LIBRARY_DOMAIN, 2) => {
+                MetalErrorKind::DynamicLibrary(MTLDynamicLibraryError::CompilationFailure)
+            }
+            (MTL_DYNAMIC_LIBRARY_DOMAIN, 3) => {
+                MetalErrorKind::DynamicLibrary(MTLDynamicLibraryError::UnresolvedInstallName)
+            }
+            (MTL_DYNAMIC_LIBRARY_DOMAIN, 4) => {
+                MetalErrorKind::DynamicLibrary(MTLDynamicLibraryError::DependencyLoadFailure)
+            }
+            (MTL_DYNAMIC_LIBRARY_DOMAIN, 5) => {
+                MetalErrorKind::DynamicLibrary(MTLDynamicLibraryError::Unsupported)
+            }
+            _ => MetalErrorKind::Other,
+        }
+    }
+
+    /// The compiler diagnostics contained in the message, if it is a compiler log.
+    pub fn diagnostics(&self) -> Vec<CompilerDiagnostic> {
+        CompilerDiagnostic::parse_log(&self.message)
+    }
+}
+
+impl fmt::Display for MetalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for MetalError {}
+
+impl From<MetalError> for String {
+    fn from(error: MetalError) -> Self {
+        error.message
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Note,
+    Remark,
+}
+
+impl fmt::Display for DiagnosticSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DiagnosticSeverity::Error => "error",
+            DiagnosticSeverity::Warning => "warning",
+            DiagnosticSeverity::Note => "note",
+            DiagnosticSeverity::Remark => "remark",
+        })
+    }
+}
+
+/// One diagnostic from a Metal shader compiler log.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CompilerDiagnostic {
+    /// The source file, `program_source` for libraries compiled from a string.
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    /// The source excerpt and caret lines printed beneath the diagnostic.
+    pub snippet: Option<String>,
+}
+
+impl CompilerDiagnostic {
+    /// Parses the `file:line:column: severity: message` entries of a compiler log, as found in
+    /// `MetalError::message` or the output of the `metal` command line compiler.
+    ///
+    /// ```
+    /// use metal::{CompilerDiagnostic, DiagnosticSeverity};
+    ///
+    /// let log = "Compilation failed: \n\n\
+    ///            program_source:4:5: error: use of undeclared identifier 'colour'\n    \
+    ///            colour = 1.0;\n    \
+    ///            ^\n\
+    ///            1 error generated.\n";
+    /// let diagnostics = CompilerDiagnostic::parse_log(log);
+    /// assert_eq!(diagnostics.len(), 1);
+    /// assert_eq!(diagnostics[0].file, "program_source");
+    /// assert_eq!((diagnostics[0].line, diagnostics[0].column), (4, Some(5)));
+    /// assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+    /// assert_eq!(diagnostics[0].snippet.as_deref(), Some("    colour = 1.0;\n    ^"));
+    /// ```
+    pub fn parse_log(log: &str) -> Vec<Self> {
+        let mut diagnostics = Vec::new();
+        let mut current: Option<(CompilerDiagnostic, Vec<&str>)> = None;
+
+        for line in log.lines() {
+            let header = Self::parse_header(line);
+            let ends_snippet = header.is_some()
+                || line.trim().is_empty()
+                || line.starts_with("In file included from ")
+                || line.ends_with(" generated.");
+            if ends_snippet {
+                if let Some((diagnostic, snippet)) = current.take() {
+                    diagnostics.push(diagnostic.with_snippet(&snippet));
+                }
+                current = header.map(|diagnostic| (diagnostic, Vec::new()));
+            } else if let Some((_, ref mut snippet)) = current {
+                snippet.push(line);
+            }
+        }
+        if let Some((diagnostic, snippet)) = current {
+            diagnostics.push(diagnostic.with_snippet(&snippet));
+        }
+        diagnostics
+    }
+
+    fn parse_header(line: &str) -> Option<Self> {
+        const MARKERS: [(&str, DiagnosticSeverity); 5] = [
+            (": fatal error: ", DiagnosticSeverity::Error),
+            (": error: ", DiagnosticSeverity::Error),
+            (": warning: ", DiagnosticSeverity::Warning),
+            (": note: ", DiagnosticSeverity::Note),
+            (": remark: ", DiagnosticSeverity::Remark),
+        ];
+
+        // The earliest marker wins so that messages quoting another marker stay intact.
+        let (start, marker, severity) = MARKERS
+            .iter()
+            .filter_map(|&(marker, severity)| {
+                line.find(marker).map(|start| (start, marker, severity))
+            })
+            .min_by_key(|&(start, _, _)| start)?;
+        let (file, line_number, column) = parse_location(&line[..start])?;
+
+        Some(CompilerDiagnostic {
+            file: file.to_owned(),
+            line: line_number,
+            column,
+            severity,
+            message: line[start + marker.len()..].trim_end().to_owned(),
+            snippet: None,
+        })
+    }
+
+    fn with_snippet(mut self, lines: &[&str]) -> Self {
+        if !lines.is_empty() {
+            self.snippet = Some(lines.join("\n"));
+        }
+        self
+    }
+}
+
+/// Formats the diagnostic the way the compiler printed it.
+impl fmt::Display for CompilerDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        write!(f, ": {}: {}", self.severity, self.message)?;
+        if let Some(ref snippet) = self.snippet {
+            write!(f, "\n{}", snippet)?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits `file:line:column` or `file:line`.
+fn parse_location(location: &str) -> Option<(&str, u32, Option<u32>)> {
+    let (rest, last) = location.rsplit_once(':')?;
+    let last = last.parse().ok()?;
+    let (file, line, column) = match rest.rsplit_once(':') {
+        Some((file, line)) if line.parse::<u32>().is_ok() => (file, line.parse().ok()?, Some(last)),
+        _ => (rest, last, None),
+    };
+    if file.is_empty() {
+        None
+    } else {
+        Some((file, line, column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_of_every_domain_and_code() {
+        use MetalErrorKind::*;
+        let table = [
+            (
+                MTL_LIBRARY_ERROR_DOMAIN,
+                1,
+                Library(MTLLibraryError::Unsupported),
+            ),
+            (
+                MTL_LIBRARY_ERROR_DOMAIN,
+                2,
+                Library(MTLLibraryError::Internal),
+            ),
+            (
+                MTL_LIBRARY_ERROR_DOMAIN,
+                3,
+                Library(MTLLibraryError::CompileFailure),
+            ),
+            (
+                MTL_LIBRARY_ERROR_DOMAIN,
+                4,
+                Library(MTLLibraryError::CompileWarning),
+            ),
+            (
+                MTL_LIBRARY_ERROR_DOMAIN,
+                5,
+                Library(MTLLibraryError::FunctionNotFound),
+            ),
+            (
+                MTL_LIBRARY_ERROR_DOMAIN,
+                6,
+                Library(MTLLibraryError::FileNotFound),
+            ),
+            (MTL_LIBRARY_ERROR_DOMAIN, 0, Other),
+            (MTL_LIBRARY_ERROR_DOMAIN, 7, Other),
+            (
+                MTL_DYNAMIC_LIBRARY_DOMAIN,
+                0,
+                DynamicLibrary(MTLDynamicLibraryError::None),
+            ),
+            (
+                MTL_DYNAMIC_LIBRARY_DOMAIN,
+                1,
+                DynamicLibrary(MTLDynamicLibraryError::InvalidFile),
+            ),
+            (
+                MTL_DYNAMIC_LIBRARY_DOMAIN,
+                2,
+                DynamicLibrary(MTLDynamicLibraryError::CompilationFailure),
+            ),
+            (
+                MTL_DYNAMIC_LIBRARY_DOMAIN,
+                3,
+                DynamicLibrary(MTLDynamicLibraryError::UnresolvedInstallName),
+            ),
+            (
+                MTL_DYNAMIC_LIBRARY_DOMAIN,
+                4,
+                DynamicLibrary(MTLDynamicLibraryError::DependencyLoadFailure),
+            ),
+            (
+                MTL_DYNAMIC_LIBRARY_DOMAIN,
+                5,
+                DynamicLibrary(MTLDynamicLibraryError::Unsupported),
+            ),
+            (MTL_DYNAMIC_LIBRARY_DOMAIN, -1, Other),
+            (METAL_RS_ERROR_DOMAIN, 0, Other),
+            ("AGXMetalG13X", 3, Other),
+        ];
+        for &(domain, code, kind) in &table {
+            let error = MetalError::new(domain, code, "");
+            assert_eq!(error.kind(), kind, "{} {}", domain, code);
+        }
+    }
+
+    #[test]
+    fn several_diagnostics_with_notes() {
+        let log = "program_source:3:9: warning: unused variable 'x'\n    \
+                   int x = 0;\n        \
+                   ^\n\
+                   program_source:7:5: error: no matching function for call to 'foo'\n    \
+                   foo(1);\n    \
+                   ^~~\n\
+                   program_source:2:6: note: candidate function not viable: requires 0 arguments\n\
+                   void foo();\n     \
+                   ^\n\
+                   1 warning and 1 error generated.\n";
+        let diagnostics = CompilerDiagnostic::parse_log(log);
+        let summary: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.severity))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (3, Some(9), DiagnosticSeverity::Warning),
+                (7, Some(5), DiagnosticSeverity::Error),
+                (2, Some(6), DiagnosticSeverity::Note),
+            ]
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "no matching function for call to 'foo'"
+        );
+        assert_eq!(
+            diagnostics[1].snippet.as_deref(),
+            Some("    foo(1);\n    ^~~")
+        );
+        assert_eq!(
+            diagnostics[2].snippet.as_deref(),
+            Some("void foo();\n     ^")
+        );
+        assert_eq!(
+            diagnostics[2].to_string(),
+            "program_source:2:6: note: candidate function not viable: requires 0 arguments\n\
+             void foo();\n     ^"
+        );
+    }
+
+    #[test]
+    fn include_chains() {
+        let log = "In file included from program_source:1:\n\
+                   In file included from ./common.h:2:\n\
+                   ./math.h:5:1: error: unknown type name 'flot'\n\
+                   flot x;\n\
+                   ^\n\
+                   1 error generated.\n";
+        let diagnostics = CompilerDiagnostic::parse_log(log);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, "./math.h");
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (5, Some(1)));
+        assert_eq!(diagnostics[0].snippet.as_deref(), Some("flot x;\n^"));
+    }
+
+    #[test]
+    fn fatal_errors() {
+        let log = "program_source:1:10: fatal error: 'missing.h' file not found\n\
+                   #include \"missing.h\"\n         \
+                   ^~~~~~~~~~~\n\
+                   1 error generated.\n";
+        let diagnostics = CompilerDiagnostic::parse_log(log);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+        assert_eq!(diagnostics[0].message, "'missing.h' file not found");
+    }
+
+    #[test]
+    fn warnings_only() {
+        let log = "program_source:1:1: warning: first\n\
+                   program_source:2:1: remark: second\n\
+                   program_source:3:1: warning: third\n\
+                   2 warnings generated.\n";
+        let diagnostics = CompilerDiagnostic::parse_log(log);
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.severity
+            != DiagnosticSeverity::Error
+            && diagnostic.snippet.is_none()));
+        assert_eq!(diagnostics[1].severity, DiagnosticSeverity::Remark);
+        assert!(CompilerDiagnostic::parse_log("").is_empty());
+        assert!(CompilerDiagnostic::parse_log("Compilation succeeded\n").is_empty());
+    }
+
+    #[test]
+    fn messages_quoting_another_marker() {
+        let log = "program_source:4:1: error: static assertion failed: \"a: warning: b\"\n\
+                   program_source:5:1: warning: unknown attribute 'x: error: y'\n";
+        let diagnostics = CompilerDiagnostic::parse_log(log);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+        assert_eq!(
+            diagnostics[0].message,
+            "static assertion failed: \"a: warning: b\""
+        );
+        assert_eq!(diagnostics[1].severity, DiagnosticSeverity::Warning);
+        assert_eq!(diagnostics[1].message, "unknown attribute 'x: error: y'");
+    }
+
+    #[test]
+    fn locations_without_columns() {
+        let log = "shader.metal:12: error: something went wrong\n\
+                   /Users/me/Shaders:v2/shader.metal:3:4: warning: path with a colon\n\
+                   :3:4: error: no file\n";
+        let diagnostics = CompilerDiagnostic::parse_log(log);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, "shader.metal");
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (12, None));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "shader.metal:12: error: something went wrong"
+        );
+        assert_eq!(diagnostics[1].file, "/Users/me/Shaders:v2/shader.metal");
+        assert_eq!((diagnostics[1].line, diagnostics[1].column), (3, Some(4)));
+    }
+}
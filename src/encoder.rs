@@ -7,6 +7,7 @@
 
 use super::*;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use std::ops::Range;
 
 #[repr(u64)]
@@ -125,14 +126,17 @@ pub struct VertexAmplificationViewMapping {
     pub viewportArrayIndexOffset: u32,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLCommandEncoder {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLCommandEncoder;
     pub struct CommandEncoder;
     pub struct CommandEncoderRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CommandEncoderRef {
     pub fn label(&self) -> &str {
         unsafe {
@@ -171,8 +175,10 @@ impl CommandEncoderRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLParallelRenderCommandEncoder {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLParallelRenderCommandEncoder;
     pub struct ParallelRenderCommandEncoder;
@@ -180,14 +186,17 @@ foreign_obj_type! {
     type ParentType = CommandEncoderRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ParallelRenderCommandEncoderRef {
     pub fn render_command_encoder(&self) -> &RenderCommandEncoderRef {
         unsafe { msg_send![self, renderCommandEncoder] }
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderCommandEncoder {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderCommandEncoder;
     pub struct RenderCommandEncoder;
@@ -195,6 +204,7 @@ foreign_obj_type! {
     type ParentType = CommandEncoderRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderCommandEncoderRef {
     pub fn set_render_pipeline_state(&self, pipeline_state: &RenderPipelineStateRef) {
         unsafe { msg_send![self, setRenderPipelineState: pipeline_state] }
@@ -819,8 +829,10 @@ impl RenderCommandEncoderRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLBlitCommandEncoder {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLBlitCommandEncoder;
     pub struct BlitCommandEncoder;
@@ -828,6 +840,7 @@ foreign_obj_type! {
     type ParentType = CommandEncoderRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl BlitCommandEncoderRef {
     pub fn synchronize_resource(&self, resource: &ResourceRef) {
         unsafe { msg_send![self, synchronizeResource: resource] }
@@ -999,8 +1012,10 @@ impl BlitCommandEncoderRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLComputeCommandEncoder {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLComputeCommandEncoder;
     pub struct ComputeCommandEncoder;
@@ -1008,6 +1023,7 @@ foreign_obj_type! {
     type ParentType = CommandEncoderRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ComputeCommandEncoderRef {
     pub fn set_compute_pipeline_state(&self, state: &ComputePipelineStateRef) {
         unsafe { msg_send![self, setComputePipelineState: state] }
@@ -1222,14 +1238,17 @@ impl ComputeCommandEncoderRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLArgumentEncoder {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLArgumentEncoder;
     pub struct ArgumentEncoder;
     pub struct ArgumentEncoderRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ArgumentEncoderRef {
     pub fn encoded_length(&self) -> NSUInteger {
         unsafe { msg_send![self, encodedLength] }
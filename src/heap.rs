@@ -5,6 +5,7 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use super::*;
 
 /// Only available on macos(10.15), ios(13.0)
@@ -17,14 +18,17 @@ pub enum MTLHeapType {
     Sparse = 2,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLHeap {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLHeap;
     pub struct Heap;
     pub struct HeapRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl HeapRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
@@ -148,14 +152,17 @@ impl HeapRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLHeapDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLHeapDescriptor;
     pub struct HeapDescriptor;
     pub struct HeapDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl HeapDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -165,6 +172,7 @@ impl HeapDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl HeapDescriptorRef {
     pub fn cpu_cache_mode(&self) -> MTLCPUCacheMode {
         unsafe { msg_send![self, cpuCacheMode] }
@@ -204,4 +212,9 @@ impl HeapDescriptorRef {
     pub fn heap_type(&self) -> MTLHeapType {
         unsafe { msg_send![self, type] }
     }
+
+    /// Only available on macos(10.15), ios(13.0)
+    pub fn set_heap_type(&self, heap_type: MTLHeapType) {
+        unsafe { msg_send![self, setType: heap_type] }
+    }
 }
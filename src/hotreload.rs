@@ -0,0 +1,522 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use super::*;
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+/// Turns assembled MSL into a library. `ShaderReloader` calls this for every (re)compile.
+pub trait ShaderCompiler {
+    type Library;
+    type Error;
+
+    fn compile(&mut self, source: &AssembledSource) -> Result<Self::Library, Self::Error>;
+}
+
+/// Compiles with `DeviceRef::new_library_with_source`.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub struct DeviceShaderCompiler {
+    pub device: Device,
+    pub options: CompileOptions,
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl ShaderCompiler for DeviceShaderCompiler {
+    type Library = Library;
+    type Error = MetalError;
+
+    fn compile(&mut self, source: &AssembledSource) -> Result<Library, MetalError> {
+        self.device
+            .new_library_with_source(&source.source, &self.options)
+    }
+}
+
+/// A value that `ShaderReloader` replaces whenever its program is rebuilt.
+///
+/// Clones share the same slot, so a renderer can hold one and pick up new pipeline states
+/// without being told about reloads. `version` starts at 0 and increments with each rebuild.
+pub struct ReloadHandle<T> {
+    slot: Arc<RwLock<(u64, T)>>,
+}
+
+impl<T> Clone for ReloadHandle<T> {
+    fn clone(&self) -> Self {
+        ReloadHandle {
+            slot: Arc::clone(&self.slot),
+        }
+    }
+}
+
+impl<T: Clone> ReloadHandle<T> {
+    pub fn get(&self) -> T {
+        self.slot.read().unwrap().1.clone()
+    }
+}
+
+impl<T> ReloadHandle<T> {
+    fn new(value: T) -> Self {
+        ReloadHandle {
+            slot: Arc::new(RwLock::new((0, value))),
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.slot.read().unwrap().0
+    }
+
+    fn replace(&self, value: T) {
+        let mut slot = self.slot.write().unwrap();
+        *slot = (slot.0 + 1, value);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ReloadHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let slot = self.slot.read().unwrap();
+        f.debug_struct("ReloadHandle")
+            .field("version", &slot.0)
+            .field("value", &slot.1)
+            .finish()
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ProgramId(usize);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReloadError<E> {
+    Include(IncludeError),
+    Compile(E),
+    /// The library compiled but rebuilding the pipeline with the given index failed.
+    Pipeline {
+        index: usize,
+        error: E,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for ReloadError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReloadError::Include(error) => write!(f, "{}", error),
+            ReloadError::Compile(error) => write!(f, "{}", error),
+            ReloadError::Pipeline { index, error } => {
+                write!(f, "pipeline {} failed to rebuild: {}", index, error)
+            }
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> Error for ReloadError<E> {}
+
+/// The outcome of rebuilding one program in `ShaderReloader::poll`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReloadEvent<E> {
+    /// The library was replaced; pipelines whose rebuild failed are reported separately.
+    Reloaded { program: ProgramId, version: u64 },
+    /// The previous library, or pipeline for `ReloadError::Pipeline`, stays in use.
+    Failed {
+        program: ProgramId,
+        error: ReloadError<E>,
+    },
+}
+
+type PipelineBuilder<L, E> = Box<dyn FnMut(&L) -> Result<(), E>>;
+
+struct Program<C: ShaderCompiler> {
+    root: PathBuf,
+    dependencies: Vec<PathBuf>,
+    /// Files watched since the last rebuild failed: those read before the failure and the
+    /// locations a missing include is looked up at, so that fixing or creating them retries.
+    pending: Vec<PathBuf>,
+    library: C::Library,
+    version: u64,
+    pipelines: Vec<PipelineBuilder<C::Library, C::Error>>,
+}
+
+/// A freshly compiled library and the files it was assembled from.
+type Compiled<C> = (<C as ShaderCompiler>::Library, Vec<PathBuf>);
+
+/// A failed compile and the files whose changes could make it succeed.
+type Failed<C> = (ReloadError<<C as ShaderCompiler>::Error>, Vec<PathBuf>);
+
+/// Modification time and length, compared between polls to detect edits.
+type FileStamp = Option<(SystemTime, u64)>;
+
+/// Recompiles MSL programs when their source files or any file they include change, and
+/// rebuilds the pipelines created from them.
+///
+/// Change detection polls file metadata, so `poll` should be called regularly, e.g. once
+/// per frame or from a timer. Several edits to one program between polls cause one rebuild.
+pub struct ShaderReloader<C: ShaderCompiler> {
+    compiler: C,
+    assembler: SourceAssembler,
+    programs: Vec<Program<C>>,
+    stamps: HashMap<PathBuf, FileStamp>,
+}
+
+impl<C: ShaderCompiler> ShaderReloader<C> {
+    pub fn new(compiler: C, assembler: SourceAssembler) -> Self {
+        ShaderReloader {
+            compiler,
+            assembler,
+            programs: Vec::new(),
+            stamps: HashMap::new(),
+        }
+    }
+
+    /// Compiles the MSL file at `root` and starts watching it and its includes.
+    pub fn add_program<P: AsRef<Path>>(
+        &mut self,
+        root: P,
+    ) -> Result<ProgramId, ReloadError<C::Error>> {
+        let root = root.as_ref().to_owned();
+        let (library, dependencies) = self.compile(&root).map_err(|(error, _)| error)?;
+        self.watch(&dependencies);
+        self.programs.push(Program {
+            root,
+            dependencies,
+            pending: Vec::new(),
+            library,
+            version: 0,
+            pipelines: Vec::new(),
+        });
+        Ok(ProgramId(self.programs.len() - 1))
+    }
+
+    pub fn library(&self, program: ProgramId) -> &C::Library {
+        &self.programs[program.0].library
+    }
+
+    /// The number of successful rebuilds of `program` since it was added.
+    pub fn version(&self, program: ProgramId) -> u64 {
+        self.programs[program.0].version
+    }
+
+    pub fn dependencies(&self, program: ProgramId) -> &[PathBuf] {
+        &self.programs[program.0].dependencies
+    }
+
+    /// Builds a pipeline from the program's library now and again after every reload.
+    ///
+    /// `build` typically looks up functions with `LibraryRef::get_function` and creates a
+    /// render or compute pipeline state from them.
+    pub fn add_pipeline<T, F>(
+        &mut self,
+        program: ProgramId,
+        mut build: F,
+    ) -> Result<ReloadHandle<T>, C::Error>
+    where
+        T: 'static,
+        F: FnMut(&C::Library) -> Result<T, C::Error> + 'static,
+    {
+        let program = &mut self.programs[program.0];
+        let handle = ReloadHandle::new(build(&program.library)?);
+        let target = handle.clone();
+        program.pipelines.push(Box::new(move |library| {
+            build(library).map(|value| target.replace(value))
+        }));
+        Ok(handle)
+    }
+
+    /// Checks watched files for changes and rebuilds the affected programs.
+    pub fn poll(&mut self) -> Vec<ReloadEvent<C::Error>> {
+        let changed: Vec<PathBuf> = self
+            .stamps
+            .iter_mut()
+            .filter_map(|(path, stamp)| {
+                let current = file_stamp(path);
+                if current != *stamp {
+                    *stamp = current;
+                    Some(path.clone())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let affected: BTreeSet<ProgramId> = self
+            .programs
+            .iter()
+            .enumerate()
+            .filter(|(_, program)| {
+                program
+                    .dependencies
+                    .iter()
+                    .chain(&program.pending)
+                    .any(|dependency| changed.contains(dependency))
+            })
+            .map(|(index, _)| ProgramId(index))
+            .collect();
+
+        affected
+            .into_iter()
+            .flat_map(|program| self.rebuild(program))
+            .collect()
+    }
+
+    /// Recompiles a program and its pipelines regardless of whether its files changed.
+    pub fn rebuild(&mut self, program: ProgramId) -> Vec<ReloadEvent<C::Error>> {
+        let root = self.programs[program.0].root.clone();
+        let (library, dependencies) = match self.compile(&root) {
+            Ok(compiled) => compiled,
+            Err((error, watched)) => {
+                self.watch(&watched);
+                self.programs[program.0].pending = watched;
+                return vec![ReloadEvent::Failed { program, error }];
+            }
+        };
+        self.watch(&dependencies);
+
+        let entry = &mut self.programs[program.0];
+        entry.library = library;
+        entry.dependencies = dependencies;
+        entry.pending.clear();
+        entry.version += 1;
+        let mut events = vec![ReloadEvent::Reloaded {
+            program,
+            version: entry.version,
+        }];
+        for (index, pipeline) in entry.pipelines.iter_mut().enumerate() {
+            if let Err(error) = pipeline(&entry.library) {
+                events.push(ReloadEvent::Failed {
+                    program,
+                    error: ReloadError::Pipeline { index, error },
+                });
+            }
+        }
+        events
+    }
+
+    fn compile(&mut self, root: &Path) -> Result<Compiled<C>, Failed<C>> {
+        let assembled = match self.assembler.assemble_file(root) {
+            Ok(assembled) => assembled,
+            Err(error) => {
+                let mut watched = vec![root.to_owned()];
+                if let IncludeError::NotFound {
+                    name,
+                    included_from: Some((file, _)),
+                } = &error
+                {
+                    watched.push(file.clone());
+                    watched.extend(self.assembler.include_candidates(name, file));
+                }
+                return Err((ReloadError::Include(error), watched));
+            }
+        };
+        match self.compiler.compile(&assembled) {
+            Ok(library) => Ok((library, assembled.dependencies)),
+            Err(error) => Err((ReloadError::Compile(error), assembled.dependencies)),
+        }
+    }
+
+    fn watch(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            self.stamps
+                .entry(path.clone())
+                .or_insert_with(|| file_stamp(path));
+        }
+    }
+}
+
+fn file_stamp(path: &Path) -> FileStamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    /// "Compiles" to the assembled source and fails on sources containing `#error`.
+    struct StubCompiler;
+
+    impl ShaderCompiler for StubCompiler {
+        type Library = String;
+        type Error = String;
+
+        fn compile(&mut self, source: &AssembledSource) -> Result<String, String> {
+            if source.source.contains("#error") {
+                Err("stub compile error".to_owned())
+            } else {
+                Ok(source.source.clone())
+            }
+        }
+    }
+
+    /// Builds a "pipeline" holding the library, failing when it mentions `broken_pipeline`.
+    fn build_pipeline(library: &str) -> Result<String, String> {
+        if library.contains("broken_pipeline") {
+            Err("stub pipeline error".to_owned())
+        } else {
+            Ok(library.to_owned())
+        }
+    }
+
+    fn reloader() -> ShaderReloader<StubCompiler> {
+        ShaderReloader::new(StubCompiler, SourceAssembler::new())
+    }
+
+    #[test]
+    fn editing_include_rebuilds_only_dependents() {
+        let dir = TempDir::new("hotreload-include");
+        dir.write("common.h", "int common;");
+        dir.write("other.h", "int other;");
+        let a = dir.write("a.metal", "#include \"common.h\"\nint a;");
+        let b = dir.write("b.metal", "#include \"other.h\"\nint b;");
+
+        let mut reloader = reloader();
+        let a = reloader.add_program(&a).unwrap();
+        let b = reloader.add_program(&b).unwrap();
+        assert!(reloader.poll().is_empty());
+
+        dir.write("common.h", "int common_edited;");
+        assert_eq!(
+            reloader.poll(),
+            vec![ReloadEvent::Reloaded {
+                program: a,
+                version: 1
+            }]
+        );
+        assert!(reloader.library(a).contains("common_edited"));
+        assert_eq!(reloader.version(b), 0);
+        assert!(reloader.poll().is_empty());
+    }
+
+    #[test]
+    fn compile_failure_keeps_library_and_pipeline() {
+        let dir = TempDir::new("hotreload-compile");
+        let root = dir.write("shader.metal", "int good;");
+
+        let mut reloader = reloader();
+        let program = reloader.add_program(&root).unwrap();
+        let pipeline = reloader
+            .add_pipeline(program, |library: &String| build_pipeline(library))
+            .unwrap();
+
+        dir.write("shader.metal", "#error broken");
+        assert_eq!(
+            reloader.poll(),
+            vec![ReloadEvent::Failed {
+                program,
+                error: ReloadError::Compile("stub compile error".to_owned()),
+            }]
+        );
+        assert!(reloader.library(program).contains("int good;"));
+        assert_eq!(reloader.version(program), 0);
+        assert_eq!(pipeline.version(), 0);
+        assert!(pipeline.get().contains("int good;"));
+
+        dir.write("shader.metal", "int fixed_again;");
+        assert_eq!(
+            reloader.poll(),
+            vec![ReloadEvent::Reloaded {
+                program,
+                version: 1
+            }]
+        );
+        assert_eq!(pipeline.version(), 1);
+        assert!(pipeline.get().contains("fixed_again"));
+    }
+
+    #[test]
+    fn pipeline_failure_is_reported() {
+        let dir = TempDir::new("hotreload-pipeline");
+        let root = dir.write("shader.metal", "int good;");
+
+        let mut reloader = reloader();
+        let program = reloader.add_program(&root).unwrap();
+        let first = reloader
+            .add_pipeline(program, |library: &String| build_pipeline(library))
+            .unwrap();
+        let second = reloader
+            .add_pipeline(program, |library: &String| Ok(library.len()))
+            .unwrap();
+
+        dir.write("shader.metal", "int broken_pipeline;");
+        assert_eq!(
+            reloader.poll(),
+            vec![
+                ReloadEvent::Reloaded {
+                    program,
+                    version: 1
+                },
+                ReloadEvent::Failed {
+                    program,
+                    error: ReloadError::Pipeline {
+                        index: 0,
+                        error: "stub pipeline error".to_owned(),
+                    },
+                },
+            ]
+        );
+        assert_eq!(first.version(), 0);
+        assert!(first.get().contains("int good;"));
+        assert_eq!(second.version(), 1);
+    }
+
+    #[test]
+    fn creating_missing_include_reloads() {
+        let dir = TempDir::new("hotreload-missing");
+        let root = dir.write("shader.metal", "int good;");
+
+        let mut reloader = reloader();
+        let program = reloader.add_program(&root).unwrap();
+
+        dir.write("shader.metal", "#include \"later.h\"\nint good;");
+        match reloader.poll().as_slice() {
+            [ReloadEvent::Failed {
+                error: ReloadError::Include(IncludeError::NotFound { name, .. }),
+                ..
+            }] => assert_eq!(name, "later.h"),
+            events => panic!("unexpected events {:?}", events),
+        }
+        assert!(reloader.poll().is_empty());
+
+        dir.write("later.h", "int later;");
+        assert_eq!(
+            reloader.poll(),
+            vec![ReloadEvent::Reloaded {
+                program,
+                version: 1
+            }]
+        );
+        assert!(reloader.library(program).contains("int later;"));
+        assert!(reloader
+            .dependencies(program)
+            .contains(&dir.path().join("later.h")));
+    }
+
+    #[test]
+    fn editing_header_added_by_failed_compile_reloads() {
+        let dir = TempDir::new("hotreload-new-header");
+        let root = dir.write("shader.metal", "int good;");
+        dir.write("new.h", "#error unfinished");
+
+        let mut reloader = reloader();
+        let program = reloader.add_program(&root).unwrap();
+
+        dir.write("shader.metal", "#include \"new.h\"\nint good;");
+        assert_eq!(reloader.poll().len(), 1);
+        assert_eq!(reloader.version(program), 0);
+
+        dir.write("new.h", "int finished_header;");
+        assert_eq!(
+            reloader.poll(),
+            vec![ReloadEvent::Reloaded {
+                program,
+                version: 1
+            }]
+        );
+    }
+}
@@ -12,14 +12,17 @@ bitflags! {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLIndirectCommandBufferDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLIndirectCommandBufferDescriptor;
     pub struct IndirectCommandBufferDescriptor;
     pub struct IndirectCommandBufferDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl IndirectCommandBufferDescriptorRef {
     pub fn command_types(&self) -> MTLIndirectCommandType {
         unsafe { msg_send![self, commandTypes] }
@@ -82,8 +85,10 @@ impl IndirectCommandBufferDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLIndirectCommandBuffer {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLIndirectCommandBuffer;
     pub struct IndirectCommandBuffer;
@@ -91,6 +96,7 @@ foreign_obj_type! {
     type ParentType = ResourceRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl IndirectCommandBufferRef {
     pub fn size(&self) -> NSUInteger {
         unsafe { msg_send![self, size] }
@@ -112,14 +118,17 @@ impl IndirectCommandBufferRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLIndirectRenderCommand {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLIndirectRenderCommand;
     pub struct IndirectRenderCommand;
     pub struct IndirectRenderCommandRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl IndirectRenderCommandRef {
     pub fn set_render_pipeline_state(&self, pipeline_state: &RenderPipelineStateRef) {
         unsafe { msg_send![self, setRenderPipelineState: pipeline_state] }
@@ -266,14 +275,17 @@ impl IndirectRenderCommandRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLIndirectComputeCommand {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLIndirectComputeCommand;
     pub struct IndirectComputeCommand;
     pub struct IndirectComputeCommandRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl IndirectComputeCommandRef {
     pub fn set_compute_pipeline_state(&self, state: &ComputePipelineStateRef) {
         unsafe { msg_send![self, setComputePipelineState: state] }
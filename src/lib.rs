@@ -10,13 +10,17 @@
 
 #[macro_use]
 extern crate bitflags;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[macro_use]
 extern crate log;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[macro_use]
 extern crate objc;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[macro_use]
 extern crate foreign_types;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use std::{
     borrow::{Borrow, ToOwned},
     marker::PhantomData,
@@ -25,8 +29,11 @@ use std::{
     os::raw::c_void,
 };
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use core_graphics_types::{base::CGFloat, geometry::CGSize};
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use foreign_types::ForeignType;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use objc::runtime::{Object, NO, YES};
 
 #[cfg(target_pointer_width = "64")]
@@ -52,6 +59,7 @@ impl NSRange {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 fn nsstring_as_str(nsstr: &objc::runtime::Object) -> &str {
     let bytes = unsafe {
         let bytes: *const std::os::raw::c_char = msg_send![nsstr, UTF8String];
@@ -64,6 +72,7 @@ fn nsstring_as_str(nsstr: &objc::runtime::Object) -> &str {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 fn nsstring_from_str(string: &str) -> *mut objc::runtime::Object {
     const UTF8_ENCODING: usize = 4;
 
@@ -82,6 +91,7 @@ fn nsstring_from_str(string: &str) -> *mut objc::runtime::Object {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 macro_rules! foreign_obj_type {
     {type CType = $raw_ident:ident;
     pub struct $owned_ident:ident;
@@ -137,6 +147,7 @@ macro_rules! foreign_obj_type {
     };
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 macro_rules! try_objc {
     {
         $err_name: ident => $body:expr
@@ -145,30 +156,32 @@ macro_rules! try_objc {
             let mut $err_name: *mut ::objc::runtime::Object = ::std::ptr::null_mut();
             let value = $body;
             if !$err_name.is_null() {
-                let desc: *mut Object = msg_send![$err_name, localizedDescription];
-                let compile_error: *const std::os::raw::c_char = msg_send![desc, UTF8String];
-                let message = CStr::from_ptr(compile_error).to_string_lossy().into_owned();
+                let error = $crate::MetalError::from_nserror($err_name);
                 let () = msg_send![$err_name, release];
-                return Err(message);
+                return Err(error);
             }
             value
         }
     };
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub struct NSArray<T> {
     _phantom: PhantomData<T>,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub struct Array<T>(*mut NSArray<T>)
 where
     T: ForeignType + 'static,
     T::Ref: objc::Message + 'static;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub struct ArrayRef<T>(foreign_types::Opaque, PhantomData<T>)
 where
     T: ForeignType + 'static,
     T::Ref: objc::Message + 'static;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl<T> Drop for Array<T>
 where
     T: ForeignType + 'static,
@@ -181,6 +194,7 @@ where
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl<T> Clone for Array<T>
 where
     T: ForeignType + 'static,
@@ -191,12 +205,14 @@ where
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 unsafe impl<T> objc::Message for NSArray<T>
 where
     T: ForeignType + 'static,
     T::Ref: objc::Message + 'static,
 {
 }
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 unsafe impl<T> objc::Message for ArrayRef<T>
 where
     T: ForeignType + 'static,
@@ -204,6 +220,7 @@ where
 {
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl<T> Array<T>
 where
     T: ForeignType + 'static,
@@ -224,6 +241,7 @@ where
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl<T> foreign_types::ForeignType for Array<T>
 where
     T: ForeignType + 'static,
@@ -241,6 +259,7 @@ where
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl<T> foreign_types::ForeignTypeRef for ArrayRef<T>
 where
     T: ForeignType + 'static,
@@ -249,6 +268,7 @@ where
     type CType = NSArray<T>;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl<T> Deref for Array<T>
 where
     T: ForeignType + 'static,
@@ -262,6 +282,7 @@ where
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl<T> Borrow<ArrayRef<T>> for Array<T>
 where
     T: ForeignType + 'static,
@@ -272,6 +293,7 @@ where
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl<T> ToOwned for ArrayRef<T>
 where
     T: ForeignType + 'static,
@@ -284,8 +306,10 @@ where
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum CAMetalDrawable {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = CAMetalDrawable;
     pub struct MetalDrawable;
@@ -293,20 +317,24 @@ foreign_obj_type! {
     type ParentType = DrawableRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl MetalDrawableRef {
     pub fn texture(&self) -> &TextureRef {
         unsafe { msg_send![self, texture] }
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum CAMetalLayer {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = CAMetalLayer;
     pub struct MetalLayer;
     pub struct MetalLayerRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl MetalLayer {
     pub fn new() -> Self {
         unsafe {
@@ -316,6 +344,7 @@ impl MetalLayer {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl MetalLayerRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
@@ -453,82 +482,124 @@ impl MetalLayerRef {
     }
 }
 
+mod aliasing;
 mod argument;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 mod buffer;
+pub mod build;
 mod capturedescriptor;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 mod capturemanager;
 mod commandbuffer;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 mod commandqueue;
 mod constants;
 mod depthstencil;
 mod device;
+mod diagnostics;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 mod drawable;
 mod encoder;
 mod heap;
+mod hotreload;
 mod indirect_encoder;
 mod library;
-#[cfg(feature = "mps")]
+mod metallib;
+#[cfg(all(feature = "mps", any(target_os = "macos", target_os = "ios")))]
 mod mps;
 mod pipeline;
+mod preprocessor;
+mod profile;
+mod reflection;
+mod rendergraph;
 mod renderpass;
 mod resource;
+mod ring;
 mod sampler;
 mod sync;
+#[cfg(test)]
+mod test_util;
 mod texture;
+mod tlsf;
+mod typedbuffer;
 mod types;
+mod uniforms;
 mod vertexdescriptor;
 
 #[rustfmt::skip]
 pub use {
+    aliasing::*,
     argument::*,
-    buffer::*,
     capturedescriptor::*,
-    capturemanager::*,
     commandbuffer::*,
-    commandqueue::*,
     constants::*,
     depthstencil::*,
     device::*,
-    drawable::*,
+    diagnostics::*,
     encoder::*,
     heap::*,
+    hotreload::*,
     indirect_encoder::*,
     library::*,
+    metallib::*,
     pipeline::*,
+    preprocessor::*,
+    profile::*,
+    reflection::*,
+    rendergraph::*,
     renderpass::*,
     resource::*,
+    ring::*,
     sampler::*,
     texture::*,
+    tlsf::*,
+    typedbuffer::*,
     types::*,
+    uniforms::*,
     vertexdescriptor::*,
     sync::*,
 };
 
-#[cfg(feature = "mps")]
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+#[rustfmt::skip]
+pub use {
+    buffer::*,
+    capturemanager::*,
+    commandqueue::*,
+    drawable::*,
+};
+
+#[cfg(all(feature = "mps", any(target_os = "macos", target_os = "ios")))]
 pub use mps::*;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[inline]
 unsafe fn obj_drop<T>(p: *mut T) {
     msg_send![(p as *mut Object), release]
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[inline]
 unsafe fn obj_clone<T: 'static>(p: *mut T) -> *mut T {
     msg_send![(p as *mut Object), retain]
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[allow(non_camel_case_types)]
 type c_size_t = usize;
 
 // TODO: expand supported interface
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum NSURL {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = NSURL;
     pub struct URL;
     pub struct URLRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl URL {
     pub fn new_with_string(string: &str) -> Self {
         unsafe {
@@ -539,6 +610,7 @@ impl URL {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl URLRef {
     pub fn absolute_string(&self) -> &str {
         unsafe {
@@ -7,11 +7,14 @@
 
 use super::*;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use foreign_types::ForeignType;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use objc::runtime::{Object, BOOL, NO, YES};
 
-use std::ffi::CStr;
-use std::os::raw::{c_char, c_void};
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use std::os::raw::c_void;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use std::ptr;
 
 /// Only available on (macos(10.12), ios(10.0)
@@ -23,14 +26,17 @@ pub enum MTLPatchType {
     Quad = 2,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLVertexAttribute {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLVertexAttribute;
     pub struct VertexAttribute;
     pub struct VertexAttributeRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl VertexAttributeRef {
     pub fn name(&self) -> &str {
         unsafe {
@@ -81,14 +87,17 @@ impl VertexAttributeRef {
 }
 
 /// Only available on (macos(10.12), ios(10.0))
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLAttribute {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLAttribute;
     pub struct Attribute;
     pub struct AttributeRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl AttributeRef {
     pub fn name(&self) -> &str {
         unsafe {
@@ -151,14 +160,17 @@ pub enum MTLFunctionType {
 }
 
 /// Only available on (macos(10.12), ios(10.0))
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLFunctionConstant {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLFunctionConstant;
     pub struct FunctionConstant;
     pub struct FunctionConstantRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl FunctionConstantRef {
     pub fn name(&self) -> &str {
         unsafe {
@@ -195,14 +207,17 @@ bitflags! {
 }
 
 /// Only available on (macos(11.0), ios(14.0))
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLFunctionDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLFunctionDescriptor;
     pub struct FunctionDescriptor;
     pub struct FunctionDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl FunctionDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -212,6 +227,7 @@ impl FunctionDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl FunctionDescriptorRef {
     pub fn name(&self) -> &str {
         unsafe {
@@ -259,8 +275,10 @@ impl FunctionDescriptorRef {
 }
 
 /// Only available on (macos(11.0), ios(14.0))
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLIntersectionFunctionDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLIntersectionFunctionDescriptor;
     pub struct IntersectionFunctionDescriptor;
@@ -269,14 +287,17 @@ foreign_obj_type! {
 }
 
 /// Only available on (macos(11.0), ios(14.0))
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLFunctionHandle {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLFunctionHandle;
     pub struct FunctionHandle;
     pub struct FunctionHandleRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl FunctionHandleRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
@@ -301,14 +322,17 @@ impl FunctionHandleRef {
 // MTLIntersectionFunctionTableDescriptor
 // MTLIntersectionFunctionTable
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLFunction {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLFunction;
     pub struct Function;
     pub struct FunctionRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl FunctionRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
@@ -393,14 +417,17 @@ pub enum MTLLanguageVersion {
     V2_4 = 0x20004,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLFunctionConstantValues {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLFunctionConstantValues;
     pub struct FunctionConstantValues;
     pub struct FunctionConstantValuesRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl FunctionConstantValues {
     pub fn new() -> Self {
         unsafe {
@@ -410,6 +437,7 @@ impl FunctionConstantValues {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl FunctionConstantValuesRef {
     pub fn set_constant_value_at_index(
         &self,
@@ -445,14 +473,17 @@ pub enum MTLLibraryType {
     Dynamic = 1,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLCompileOptions {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLCompileOptions;
     pub struct CompileOptions;
     pub struct CompileOptionsRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CompileOptions {
     pub fn new() -> Self {
         unsafe {
@@ -462,6 +493,7 @@ impl CompileOptions {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl CompileOptionsRef {
     pub unsafe fn preprocessor_macros(&self) -> *mut Object {
         msg_send![self, preprocessorMacros]
@@ -590,14 +622,17 @@ pub enum MTLLibraryError {
     FileNotFound = 6,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLLibrary {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLLibrary;
     pub struct Library;
     pub struct LibraryRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl LibraryRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
@@ -622,11 +657,11 @@ impl LibraryRef {
         &self,
         name: &str,
         constants: Option<FunctionConstantValues>,
-    ) -> Result<Function, String> {
+    ) -> Result<Function, MetalError> {
         unsafe {
             let nsname = crate::nsstring_from_str(name);
 
-            let function: *mut MTLFunction = match constants {
+            let function: *mut MTLFunction = match &constants {
                 Some(c) => try_objc! { err => msg_send![self,
                     newFunctionWithName: nsname.as_ref()
                     constantValues: c.as_ref()
@@ -637,8 +672,17 @@ impl LibraryRef {
 
             if !function.is_null() {
                 Ok(Function::from_ptr(function))
+            } else if constants.is_some() {
+                Err(MetalError::unreported(format!(
+                    "Specializing function '{}' returned nil without an error",
+                    name
+                )))
             } else {
-                Err(format!("Function '{}' does not exist", name))
+                // Without constants a nil result only means the name lookup failed.
+                Err(MetalError::library(
+                    MTLLibraryError::FunctionNotFound,
+                    format!("Function '{}' does not exist", name),
+                ))
             }
         }
     }
@@ -677,7 +721,7 @@ impl LibraryRef {
     pub fn new_function_with_descriptor(
         &self,
         descriptor: &FunctionDescriptorRef,
-    ) -> Result<Function, String> {
+    ) -> Result<Function, MetalError> {
         unsafe {
             let function: *mut MTLFunction = try_objc! {
                 err => msg_send![self,
@@ -689,7 +733,9 @@ impl LibraryRef {
             if !function.is_null() {
                 Ok(Function::from_ptr(function))
             } else {
-                Err(String::from("new_function_with_descriptor() failed"))
+                Err(MetalError::unreported(
+                    "new_function_with_descriptor() returned nil without an error",
+                ))
             }
         }
     }
@@ -698,7 +744,7 @@ impl LibraryRef {
     pub fn new_intersection_function_with_descriptor(
         &self,
         descriptor: &IntersectionFunctionDescriptorRef,
-    ) -> Result<Function, String> {
+    ) -> Result<Function, MetalError> {
         unsafe {
             let function: *mut MTLFunction = try_objc! {
                 err => msg_send![self,
@@ -710,8 +756,8 @@ impl LibraryRef {
             if !function.is_null() {
                 Ok(Function::from_ptr(function))
             } else {
-                Err(String::from(
-                    "new_intersection_function_with_descriptor() failed",
+                Err(MetalError::unreported(
+                    "new_intersection_function_with_descriptor() returned nil without an error",
                 ))
             }
         }
@@ -730,14 +776,17 @@ pub enum MTLDynamicLibraryError {
     Unsupported = 5,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLDynamicLibrary {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLDynamicLibrary;
     pub struct DynamicLibrary;
     pub struct DynamicLibraryRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl DynamicLibraryRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
@@ -764,17 +813,13 @@ impl DynamicLibraryRef {
         }
     }
 
-    pub fn serialize_to_url(&self, url: &URLRef) -> Result<bool, String> {
+    pub fn serialize_to_url(&self, url: &URLRef) -> Result<bool, MetalError> {
         unsafe {
             let mut err: *mut Object = ptr::null_mut();
             let result: BOOL = msg_send![self, serializeToURL:url
                                                         error:&mut err];
             if !err.is_null() {
-                // FIXME: copy pasta
-                let desc: *mut Object = msg_send![err, localizedDescription];
-                let c_msg: *const c_char = msg_send![desc, UTF8String];
-                let message = CStr::from_ptr(c_msg).to_string_lossy().into_owned();
-                Err(message)
+                Err(MetalError::from_nserror(err))
             } else {
                 match result {
                     YES => Ok(true),
@@ -787,14 +832,17 @@ impl DynamicLibraryRef {
 }
 
 /// macOS 11.0+ iOS 14.0+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLBinaryArchiveDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLBinaryArchiveDescriptor;
     pub struct BinaryArchiveDescriptor;
     pub struct BinaryArchiveDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl BinaryArchiveDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -804,6 +852,7 @@ impl BinaryArchiveDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl BinaryArchiveDescriptorRef {
     pub fn url(&self) -> &URLRef {
         unsafe { msg_send![self, url] }
@@ -814,14 +863,17 @@ impl BinaryArchiveDescriptorRef {
 }
 
 /// macOS 11.0+ iOS 14.0+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLBinaryArchive {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLBinaryArchive;
     pub struct BinaryArchive;
     pub struct BinaryArchiveRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl BinaryArchiveRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
@@ -844,17 +896,13 @@ impl BinaryArchiveRef {
     pub fn add_compute_pipeline_functions_with_descriptor(
         &self,
         descriptor: &ComputePipelineDescriptorRef,
-    ) -> Result<bool, String> {
+    ) -> Result<bool, MetalError> {
         unsafe {
             let mut err: *mut Object = ptr::null_mut();
             let result: BOOL = msg_send![self, addComputePipelineFunctionsWithDescriptor:descriptor
                                                                         error:&mut err];
             if !err.is_null() {
-                // FIXME: copy pasta
-                let desc: *mut Object = msg_send![err, localizedDescription];
-                let c_msg: *const c_char = msg_send![desc, UTF8String];
-                let message = CStr::from_ptr(c_msg).to_string_lossy().into_owned();
-                Err(message)
+                Err(MetalError::from_nserror(err))
             } else {
                 match result {
                     YES => Ok(true),
@@ -868,17 +916,13 @@ impl BinaryArchiveRef {
     pub fn add_render_pipeline_functions_with_descriptor(
         &self,
         descriptor: &RenderPipelineDescriptorRef,
-    ) -> Result<bool, String> {
+    ) -> Result<bool, MetalError> {
         unsafe {
             let mut err: *mut Object = ptr::null_mut();
             let result: BOOL = msg_send![self, addRenderPipelineFunctionsWithDescriptor:descriptor
                                                                         error:&mut err];
             if !err.is_null() {
-                // FIXME: copy pasta
-                let desc: *mut Object = msg_send![err, localizedDescription];
-                let c_msg: *const c_char = msg_send![desc, UTF8String];
-                let message = CStr::from_ptr(c_msg).to_string_lossy().into_owned();
-                Err(message)
+                Err(MetalError::from_nserror(err))
             } else {
                 match result {
                     YES => Ok(true),
@@ -893,17 +937,13 @@ impl BinaryArchiveRef {
     // - (BOOL)addTileRenderPipelineFunctionsWithDescriptor:(MTLTileRenderPipelineDescriptor *)descriptor
     // error:(NSError * _Nullable *)error;
 
-    pub fn serialize_to_url(&self, url: &URLRef) -> Result<bool, String> {
+    pub fn serialize_to_url(&self, url: &URLRef) -> Result<bool, MetalError> {
         unsafe {
             let mut err: *mut Object = ptr::null_mut();
             let result: BOOL = msg_send![self, serializeToURL:url
                                                         error:&mut err];
             if !err.is_null() {
-                // FIXME: copy pasta
-                let desc: *mut Object = msg_send![err, localizedDescription];
-                let c_msg: *const c_char = msg_send![desc, UTF8String];
-                let message = CStr::from_ptr(c_msg).to_string_lossy().into_owned();
-                Err(message)
+                Err(MetalError::from_nserror(err))
             } else {
                 match result {
                     YES => Ok(true),
@@ -916,14 +956,17 @@ impl BinaryArchiveRef {
 }
 
 /// macOS 11.0+ iOS 14.0+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLLinkedFunctions {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLLinkedFunctions;
     pub struct LinkedFunctions;
     pub struct LinkedFunctionsRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl LinkedFunctions {
     pub fn new() -> Self {
         unsafe {
@@ -933,6 +976,7 @@ impl LinkedFunctions {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl LinkedFunctionsRef {
     /// Marshal to Rust Vec
     pub fn functions(&self) -> Vec<Function> {
@@ -0,0 +1,681 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use super::*;
+
+use std::{error::Error, fmt, ops::Range};
+
+/// The four bytes every metallib container starts with.
+pub const METALLIB_MAGIC: [u8; 4] = *b"MTLB";
+
+const HEADER_LENGTH: usize = 88;
+const END_TAG: [u8; 4] = *b"ENDT";
+
+/// The operating system a metallib was compiled for.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MetallibPlatform {
+    MacOS,
+    IOS,
+    TvOS,
+    WatchOS,
+    BridgeOS,
+    MacCatalyst,
+    IOSSimulator,
+    TvOSSimulator,
+    WatchOSSimulator,
+    Unknown(u8),
+}
+
+impl MetallibPlatform {
+    fn from_raw(raw: u8) -> Self {
+        match raw & 0x7f {
+            1 => MetallibPlatform::MacOS,
+            2 => MetallibPlatform::IOS,
+            3 => MetallibPlatform::TvOS,
+            4 => MetallibPlatform::WatchOS,
+            5 => MetallibPlatform::BridgeOS,
+            6 => MetallibPlatform::MacCatalyst,
+            7 => MetallibPlatform::IOSSimulator,
+            8 => MetallibPlatform::TvOSSimulator,
+            9 => MetallibPlatform::WatchOSSimulator,
+            _ => MetallibPlatform::Unknown(raw),
+        }
+    }
+}
+
+/// The kind of library stored in a metallib container.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MetallibType {
+    Executable,
+    CoreImage,
+    Dynamic,
+    /// A `.metallibsym` companion holding the debug information stripped from a library.
+    SymbolCompanion,
+    Unknown(u8),
+}
+
+impl MetallibType {
+    fn from_raw(raw: u8) -> Self {
+        match raw {
+            0 => MetallibType::Executable,
+            1 => MetallibType::CoreImage,
+            2 => MetallibType::Dynamic,
+            3 => MetallibType::SymbolCompanion,
+            _ => MetallibType::Unknown(raw),
+        }
+    }
+}
+
+/// A function entry from the metallib function list.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MetallibFunction {
+    pub name: String,
+    /// `None` for library-internal functions (unqualified or `extern`) that have no
+    /// `MTLFunctionType`; see `raw_function_type`.
+    pub function_type: Option<MTLFunctionType>,
+    pub raw_function_type: Option<u8>,
+    /// SHA-256 of the function's bitcode.
+    pub hash: Option<[u8; 32]>,
+    pub air_version: Option<(u16, u16)>,
+    pub language_version: Option<(u16, u16)>,
+    /// Byte range of the function's bitcode module within the container.
+    pub bitcode: Option<Range<usize>>,
+}
+
+/// A section referenced from the extended header, such as the dynamic header (`HDYN`),
+/// the variable list (`VLST`), imported symbols (`ILST`) or embedded sources (`HSRD`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MetallibSection {
+    pub tag: [u8; 4],
+    /// Byte range of the section within the container.
+    pub range: Range<usize>,
+}
+
+/// Why a byte buffer could not be read as a metallib container.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MetallibError {
+    InvalidMagic,
+    /// The data ends before a field that starts at `offset`.
+    UnexpectedEof {
+        offset: usize,
+    },
+    /// The header records a larger file than the data provided.
+    FileSizeMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    SectionOutOfBounds {
+        offset: u64,
+        length: u64,
+    },
+    MissingFunctionName {
+        index: usize,
+    },
+    InvalidFunctionName {
+        index: usize,
+    },
+}
+
+impl fmt::Display for MetallibError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MetallibError::InvalidMagic => write!(f, "data does not start with the MTLB magic"),
+            MetallibError::UnexpectedEof { offset } => {
+                write!(f, "unexpected end of data at offset {}", offset)
+            }
+            MetallibError::FileSizeMismatch { expected, actual } => write!(
+                f,
+                "header records a file size of {} bytes but only {} are available",
+                expected, actual
+            ),
+            MetallibError::SectionOutOfBounds { offset, length } => write!(
+                f,
+                "section of {} bytes at offset {} lies outside the file",
+                length, offset
+            ),
+            MetallibError::MissingFunctionName { index } => {
+                write!(f, "function {} has no NAME tag", index)
+            }
+            MetallibError::InvalidFunctionName { index } => {
+                write!(f, "function {} has a name that is not valid UTF-8", index)
+            }
+        }
+    }
+}
+
+impl Error for MetallibError {}
+
+/// The contents of a compiled `.metallib` container, read without Metal.
+///
+/// This is the data `DeviceRef::new_library_with_data` accepts; parsing it lets tooling on
+/// any platform inspect which entry points a library provides.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Metallib {
+    /// Container format version as (major, minor).
+    pub version: (u16, u16),
+    pub library_type: MetallibType,
+    pub platform: MetallibPlatform,
+    /// Minimum OS version the library targets as (major, minor).
+    pub platform_version: (u16, u16),
+    pub file_size: u64,
+    pub functions: Vec<MetallibFunction>,
+    pub sections: Vec<MetallibSection>,
+    pub uuid: Option<[u8; 16]>,
+}
+
+impl Metallib {
+    pub fn parse(data: &[u8]) -> Result<Self, MetallibError> {
+        if data.len() < METALLIB_MAGIC.len() || data[..4] != METALLIB_MAGIC {
+            return Err(MetallibError::InvalidMagic);
+        }
+        let mut header = Reader::new(data, 4);
+        header.require(HEADER_LENGTH - 4)?;
+        let _target = header.u16()?;
+        let version = (header.u16()? & 0x7fff, header.u16()?);
+        let library_type = MetallibType::from_raw(header.u8()?);
+        let platform = MetallibPlatform::from_raw(header.u8()?);
+        let platform_version = (header.u16()?, header.u16()?);
+        let file_size = header.u64()?;
+        if file_size > data.len() as u64 {
+            return Err(MetallibError::FileSizeMismatch {
+                expected: file_size,
+                actual: data.len() as u64,
+            });
+        }
+        let data = &data[..file_size as usize];
+
+        let function_list = section_range(data, header.u64()?, header.u64()?)?;
+        let public_metadata = section_range(data, header.u64()?, header.u64()?)?;
+        let private_metadata = section_range(data, header.u64()?, header.u64()?)?;
+        let bitcode = section_range(data, header.u64()?, header.u64()?)?;
+
+        let mut list = Reader::new(data, function_list.start);
+        let function_count = list.u32()? as usize;
+        let mut functions = Vec::with_capacity(function_count.min(data.len() / 8));
+        for index in 0..function_count {
+            functions.push(read_function(&mut list, index, &bitcode)?);
+        }
+
+        // Newer containers fill the gap between the function list and the next section
+        // with an extended header of tagged section references.
+        let next_section = [&public_metadata, &private_metadata, &bitcode]
+            .iter()
+            .map(|range| range.start)
+            .filter(|&start| start >= list.pos)
+            .min()
+            .unwrap_or(data.len());
+        let mut sections = Vec::new();
+        let mut uuid = None;
+        if list.pos + 8 <= next_section {
+            let _group_length = list.u32()?;
+            while let Some((tag, payload)) = list.tag()? {
+                match (&tag, payload.len()) {
+                    (b"UUID", 16) => {
+                        let mut bytes = [0; 16];
+                        bytes.copy_from_slice(payload);
+                        uuid = Some(bytes);
+                    }
+                    (_, 16) => {
+                        let mut reader = Reader::new(payload, 0);
+                        let range = section_range(data, reader.u64()?, reader.u64()?)?;
+                        sections.push(MetallibSection { tag, range });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Metallib {
+            version,
+            library_type,
+            platform,
+            platform_version,
+            file_size,
+            functions,
+            sections,
+            uuid,
+        })
+    }
+
+    pub fn function(&self, name: &str) -> Option<&MetallibFunction> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn contains_function(&self, name: &str) -> bool {
+        self.function(name).is_some()
+    }
+
+    /// Returns the names from `names` that the library does not provide.
+    pub fn missing_functions<'n, I>(&self, names: I) -> Vec<&'n str>
+    where
+        I: IntoIterator<Item = &'n str>,
+    {
+        names
+            .into_iter()
+            .filter(|name| !self.contains_function(name))
+            .collect()
+    }
+
+    pub fn section(&self, tag: &[u8; 4]) -> Option<&MetallibSection> {
+        self.sections.iter().find(|section| &section.tag == tag)
+    }
+
+    /// The embedded source archive, present when the library was built with
+    /// `-frecord-sources`.
+    pub fn embedded_source(&self) -> Option<&MetallibSection> {
+        self.section(b"HSRD").or_else(|| self.section(b"HSRC"))
+    }
+
+    /// Whether the container carries debug information rather than executable code.
+    pub fn is_symbol_companion(&self) -> bool {
+        self.library_type == MetallibType::SymbolCompanion
+    }
+}
+
+fn read_function(
+    reader: &mut Reader,
+    index: usize,
+    bitcode_section: &Range<usize>,
+) -> Result<MetallibFunction, MetallibError> {
+    let _entry_length = reader.u32()?;
+    let mut name = None;
+    let mut raw_function_type = None;
+    let mut hash = None;
+    let mut air_version = None;
+    let mut language_version = None;
+    let mut bitcode_offset = None;
+    let mut bitcode_size = None;
+
+    while let Some((tag, payload)) = reader.tag()? {
+        let mut fields = Reader::new(payload, 0);
+        match &tag {
+            b"NAME" => {
+                let bytes = payload.split(|&b| b == 0).next().unwrap_or(payload);
+                let string = std::str::from_utf8(bytes)
+                    .map_err(|_| MetallibError::InvalidFunctionName { index })?;
+                name = Some(string.to_owned());
+            }
+            b"TYPE" => raw_function_type = Some(fields.u8()?),
+            b"HASH" if payload.len() == 32 => {
+                let mut bytes = [0; 32];
+                bytes.copy_from_slice(payload);
+                hash = Some(bytes);
+            }
+            b"VERS" => {
+                air_version = Some((fields.u16()?, fields.u16()?));
+                language_version = Some((fields.u16()?, fields.u16()?));
+            }
+            b"MDSZ" => bitcode_size = Some(fields.u64()?),
+            b"OFFT" => {
+                let _public_metadata = fields.u64()?;
+                let _private_metadata = fields.u64()?;
+                bitcode_offset = Some(fields.u64()?);
+            }
+            _ => {}
+        }
+    }
+
+    let name = name.ok_or(MetallibError::MissingFunctionName { index })?;
+    let function_type = raw_function_type.and_then(|raw| match raw {
+        0 => Some(MTLFunctionType::Vertex),
+        1 => Some(MTLFunctionType::Fragment),
+        2 => Some(MTLFunctionType::Kernel),
+        4 => Some(MTLFunctionType::Visible),
+        6 => Some(MTLFunctionType::Intersection),
+        _ => None,
+    });
+    let bitcode = match (bitcode_offset, bitcode_size) {
+        (Some(offset), Some(size)) => {
+            let start = (bitcode_section.start as u64).checked_add(offset);
+            let end = start.and_then(|start| start.checked_add(size));
+            match (start, end) {
+                (Some(start), Some(end)) if end <= bitcode_section.end as u64 => {
+                    Some(start as usize..end as usize)
+                }
+                _ => {
+                    return Err(MetallibError::SectionOutOfBounds {
+                        offset,
+                        length: size,
+                    })
+                }
+            }
+        }
+        _ => None,
+    };
+
+    Ok(MetallibFunction {
+        name,
+        function_type,
+        raw_function_type,
+        hash,
+        air_version,
+        language_version,
+        bitcode,
+    })
+}
+
+fn section_range(data: &[u8], offset: u64, length: u64) -> Result<Range<usize>, MetallibError> {
+    match offset.checked_add(length) {
+        Some(end) if end <= data.len() as u64 => Ok(offset as usize..end as usize),
+        _ => Err(MetallibError::SectionOutOfBounds { offset, length }),
+    }
+}
+
+/// A tag name and its payload.
+type Tag<'a> = ([u8; 4], &'a [u8]);
+
+/// Little-endian cursor over a byte slice.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Reader { data, pos }
+    }
+
+    fn require(&self, length: usize) -> Result<(), MetallibError> {
+        match self.pos.checked_add(length) {
+            Some(end) if end <= self.data.len() => Ok(()),
+            _ => Err(MetallibError::UnexpectedEof { offset: self.pos }),
+        }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], MetallibError> {
+        self.require(length)?;
+        let bytes = &self.data[self.pos..self.pos + length];
+        self.pos += length;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], MetallibError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, MetallibError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, MetallibError> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, MetallibError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, MetallibError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    /// Reads the next `(tag, payload)` pair of a tag group, or `None` at its `ENDT` marker.
+    fn tag(&mut self) -> Result<Option<Tag<'a>>, MetallibError> {
+        let tag = self.array::<4>()?;
+        if tag == END_TAG {
+            return Ok(None);
+        }
+        let length = self.u16()? as usize;
+        Ok(Some((tag, self.bytes(length)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(out: &mut Vec<u8>, name: &[u8; 4], payload: &[u8]) {
+        out.extend_from_slice(name);
+        out.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+        out.extend_from_slice(payload);
+    }
+
+    fn u64s(values: &[u64]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    /// A container with a vertex function, an untyped helper, a UUID, an embedded source
+    /// section and a bitcode section holding one module per function.
+    fn fixture() -> Vec<u8> {
+        let bitcode_modules: [&[u8]; 2] = [b"BC-vertex", b"BC-helper"];
+
+        let mut list = Vec::new();
+        list.extend_from_slice(&2u32.to_le_bytes());
+        let mut bitcode_offset = 0;
+        for (name, raw_type, module) in [
+            (&b"main_vertex\0"[..], Some(0u8), bitcode_modules[0]),
+            (&b"helper\0"[..], None, bitcode_modules[1]),
+        ] {
+            let mut entry = Vec::new();
+            tag(&mut entry, b"NAME", name);
+            if let Some(raw_type) = raw_type {
+                tag(&mut entry, b"TYPE", &[raw_type]);
+            }
+            tag(&mut entry, b"HASH", &[0xab; 32]);
+            tag(&mut entry, b"VERS", &[2, 0, 4, 0, 2, 0, 4, 0]);
+            tag(&mut entry, b"MDSZ", &u64s(&[module.len() as u64]));
+            tag(&mut entry, b"OFFT", &u64s(&[0, 0, bitcode_offset]));
+            entry.extend_from_slice(&END_TAG);
+            list.extend_from_slice(&(entry.len() as u32 + 4).to_le_bytes());
+            list.extend_from_slice(&entry);
+            bitcode_offset += module.len() as u64;
+        }
+
+        let source = b"source archive";
+        let list_start = HEADER_LENGTH as u64;
+        // Extended header: group length, UUID, HSRD and ENDT.
+        let extended_length = 4 + (6 + 16) * 2 + 4;
+        let source_start = list_start + list.len() as u64 + extended_length;
+        let bitcode_start = source_start + source.len() as u64;
+        let file_size = bitcode_start + bitcode_offset;
+
+        let mut extended = Vec::new();
+        extended.extend_from_slice(&(extended_length as u32).to_le_bytes());
+        tag(&mut extended, b"UUID", &[7; 16]);
+        tag(
+            &mut extended,
+            b"HSRD",
+            &u64s(&[source_start, source.len() as u64]),
+        );
+        extended.extend_from_slice(&END_TAG);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&METALLIB_MAGIC);
+        data.extend_from_slice(&0x8001u16.to_le_bytes());
+        data.extend_from_slice(&(0x8000u16 | 1).to_le_bytes());
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.push(0);
+        data.push(0x81);
+        data.extend_from_slice(&14u16.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&file_size.to_le_bytes());
+        data.extend_from_slice(&u64s(&[list_start, list.len() as u64]));
+        data.extend_from_slice(&u64s(&[source_start, 0]));
+        data.extend_from_slice(&u64s(&[source_start, 0]));
+        data.extend_from_slice(&u64s(&[bitcode_start, bitcode_offset]));
+        assert_eq!(data.len(), HEADER_LENGTH);
+        data.extend_from_slice(&list);
+        data.extend_from_slice(&extended);
+        data.extend_from_slice(source);
+        for module in &bitcode_modules {
+            data.extend_from_slice(module);
+        }
+        assert_eq!(data.len() as u64, file_size);
+        data
+    }
+
+    #[test]
+    fn parses_header() {
+        let data = fixture();
+        let library = Metallib::parse(&data).unwrap();
+        assert_eq!(library.version, (1, 2));
+        assert_eq!(library.library_type, MetallibType::Executable);
+        assert_eq!(library.platform, MetallibPlatform::MacOS);
+        assert_eq!(library.platform_version, (14, 0));
+        assert_eq!(library.file_size, data.len() as u64);
+        assert_eq!(library.uuid, Some([7; 16]));
+        assert!(!library.is_symbol_companion());
+    }
+
+    #[test]
+    fn parses_functions() {
+        let data = fixture();
+        let library = Metallib::parse(&data).unwrap();
+        let names: Vec<_> = library.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["main_vertex", "helper"]);
+        assert_eq!(
+            library.missing_functions(vec!["main_vertex", "main_fragment"]),
+            ["main_fragment"]
+        );
+
+        let vertex = library.function("main_vertex").unwrap();
+        assert_eq!(vertex.function_type, Some(MTLFunctionType::Vertex));
+        assert_eq!(vertex.raw_function_type, Some(0));
+        assert_eq!(vertex.hash, Some([0xab; 32]));
+        assert_eq!(vertex.air_version, Some((2, 4)));
+        assert_eq!(vertex.language_version, Some((2, 4)));
+        assert_eq!(&data[vertex.bitcode.clone().unwrap()], b"BC-vertex");
+
+        let helper = library.function("helper").unwrap();
+        assert_eq!(helper.function_type, None);
+        assert_eq!(helper.raw_function_type, None);
+        assert_eq!(&data[helper.bitcode.clone().unwrap()], b"BC-helper");
+    }
+
+    #[test]
+    fn parses_sections() {
+        let data = fixture();
+        let library = Metallib::parse(&data).unwrap();
+        let source = library.embedded_source().unwrap();
+        assert_eq!(&source.tag, b"HSRD");
+        assert_eq!(&data[source.range.clone()], b"source archive");
+        assert!(library.section(b"HDYN").is_none());
+    }
+
+    #[test]
+    fn parses_symbol_companion() {
+        let mut data = fixture();
+        data[10] = 3;
+        let library = Metallib::parse(&data).unwrap();
+        assert_eq!(library.library_type, MetallibType::SymbolCompanion);
+        assert!(library.is_symbol_companion());
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut data = fixture();
+        data[0] = b'X';
+        assert_eq!(Metallib::parse(&data), Err(MetallibError::InvalidMagic));
+        assert_eq!(Metallib::parse(b"MT"), Err(MetallibError::InvalidMagic));
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let data = fixture();
+        for length in 0..data.len() {
+            assert!(
+                Metallib::parse(&data[..length]).is_err(),
+                "length {}",
+                length
+            );
+        }
+        assert_eq!(
+            Metallib::parse(&data[..40]),
+            Err(MetallibError::UnexpectedEof { offset: 4 })
+        );
+        assert_eq!(
+            Metallib::parse(&data[..HEADER_LENGTH]),
+            Err(MetallibError::FileSizeMismatch {
+                expected: data.len() as u64,
+                actual: HEADER_LENGTH as u64,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_truncation_behind_matching_file_size() {
+        let data = fixture();
+        for length in HEADER_LENGTH..data.len() {
+            let mut truncated = data[..length].to_vec();
+            truncated[16..24].copy_from_slice(&(length as u64).to_le_bytes());
+            assert!(Metallib::parse(&truncated).is_err(), "length {}", length);
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_function_bitcode() {
+        let mut data = fixture();
+        // MDSZ payload of the first function.
+        let position = data
+            .windows(4)
+            .position(|window| window == b"MDSZ")
+            .unwrap()
+            + 6;
+        data[position..position + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            Metallib::parse(&data),
+            Err(MetallibError::SectionOutOfBounds { .. })
+        ));
+
+        let mut data = fixture();
+        // Bitcode offset, the last field of the first function's OFFT payload.
+        let position = data
+            .windows(4)
+            .position(|window| window == b"OFFT")
+            .unwrap()
+            + 6
+            + 16;
+        data[position..position + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            Metallib::parse(&data),
+            Err(MetallibError::SectionOutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_function_names() {
+        let mut data = fixture();
+        let position = data
+            .windows(4)
+            .position(|window| window == b"NAME")
+            .unwrap()
+            + 6;
+        data[position] = 0xff;
+        assert_eq!(
+            Metallib::parse(&data),
+            Err(MetallibError::InvalidFunctionName { index: 0 })
+        );
+
+        let mut data = fixture();
+        let position = data
+            .windows(4)
+            .position(|window| window == b"NAME")
+            .unwrap();
+        data[position..position + 4].copy_from_slice(b"XXXX");
+        assert_eq!(
+            Metallib::parse(&data),
+            Err(MetallibError::MissingFunctionName { index: 0 })
+        );
+    }
+
+    #[test]
+    fn corrupt_bytes_never_panic() {
+        let data = fixture();
+        for position in 4..data.len() {
+            for &value in &[0x00, 0x7f, 0xff] {
+                let mut corrupt = data.clone();
+                corrupt[position] = value;
+                let _ = Metallib::parse(&corrupt);
+            }
+        }
+    }
+}
@@ -7,6 +7,7 @@
 
 use super::*;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use objc::runtime::{NO, YES};
 
 #[repr(u64)]
@@ -67,6 +68,160 @@ pub enum MTLAttributeFormat {
     Half = 53,
 }
 
+impl From<MTLVertexFormat> for MTLAttributeFormat {
+    fn from(format: MTLVertexFormat) -> Self {
+        match format {
+            MTLVertexFormat::Invalid => MTLAttributeFormat::Invalid,
+            MTLVertexFormat::UChar2 => MTLAttributeFormat::UChar2,
+            MTLVertexFormat::UChar3 => MTLAttributeFormat::UChar3,
+            MTLVertexFormat::UChar4 => MTLAttributeFormat::UChar4,
+            MTLVertexFormat::Char2 => MTLAttributeFormat::Char2,
+            MTLVertexFormat::Char3 => MTLAttributeFormat::Char3,
+            MTLVertexFormat::Char4 => MTLAttributeFormat::Char4,
+            MTLVertexFormat::UChar2Normalized => MTLAttributeFormat::UChar2Normalized,
+            MTLVertexFormat::UChar3Normalized => MTLAttributeFormat::UChar3Normalized,
+            MTLVertexFormat::UChar4Normalized => MTLAttributeFormat::UChar4Normalized,
+            MTLVertexFormat::Char2Normalized => MTLAttributeFormat::Char2Normalized,
+            MTLVertexFormat::Char3Normalized => MTLAttributeFormat::Char3Normalized,
+            MTLVertexFormat::Char4Normalized => MTLAttributeFormat::Char4Normalized,
+            MTLVertexFormat::UShort2 => MTLAttributeFormat::UShort2,
+            MTLVertexFormat::UShort3 => MTLAttributeFormat::UShort3,
+            MTLVertexFormat::UShort4 => MTLAttributeFormat::UShort4,
+            MTLVertexFormat::Short2 => MTLAttributeFormat::Short2,
+            MTLVertexFormat::Short3 => MTLAttributeFormat::Short3,
+            MTLVertexFormat::Short4 => MTLAttributeFormat::Short4,
+            MTLVertexFormat::UShort2Normalized => MTLAttributeFormat::UShort2Normalized,
+            MTLVertexFormat::UShort3Normalized => MTLAttributeFormat::UShort3Normalized,
+            MTLVertexFormat::UShort4Normalized => MTLAttributeFormat::UShort4Normalized,
+            MTLVertexFormat::Short2Normalized => MTLAttributeFormat::Short2Normalized,
+            MTLVertexFormat::Short3Normalized => MTLAttributeFormat::Short3Normalized,
+            MTLVertexFormat::Short4Normalized => MTLAttributeFormat::Short4Normalized,
+            MTLVertexFormat::Half2 => MTLAttributeFormat::Half2,
+            MTLVertexFormat::Half3 => MTLAttributeFormat::Half3,
+            MTLVertexFormat::Half4 => MTLAttributeFormat::Half4,
+            MTLVertexFormat::Float => MTLAttributeFormat::Float,
+            MTLVertexFormat::Float2 => MTLAttributeFormat::Float2,
+            MTLVertexFormat::Float3 => MTLAttributeFormat::Float3,
+            MTLVertexFormat::Float4 => MTLAttributeFormat::Float4,
+            MTLVertexFormat::Int => MTLAttributeFormat::Int,
+            MTLVertexFormat::Int2 => MTLAttributeFormat::Int2,
+            MTLVertexFormat::Int3 => MTLAttributeFormat::Int3,
+            MTLVertexFormat::Int4 => MTLAttributeFormat::Int4,
+            MTLVertexFormat::UInt => MTLAttributeFormat::UInt,
+            MTLVertexFormat::UInt2 => MTLAttributeFormat::UInt2,
+            MTLVertexFormat::UInt3 => MTLAttributeFormat::UInt3,
+            MTLVertexFormat::UInt4 => MTLAttributeFormat::UInt4,
+            MTLVertexFormat::Int1010102Normalized => MTLAttributeFormat::Int1010102Normalized,
+            MTLVertexFormat::UInt1010102Normalized => MTLAttributeFormat::UInt1010102Normalized,
+            MTLVertexFormat::UChar4Normalized_BGRA => MTLAttributeFormat::UChar4Normalized_BGRA,
+            MTLVertexFormat::UChar => MTLAttributeFormat::UChar,
+            MTLVertexFormat::Char => MTLAttributeFormat::Char,
+            MTLVertexFormat::UCharNormalized => MTLAttributeFormat::UCharNormalized,
+            MTLVertexFormat::CharNormalized => MTLAttributeFormat::CharNormalized,
+            MTLVertexFormat::UShort => MTLAttributeFormat::UShort,
+            MTLVertexFormat::Short => MTLAttributeFormat::Short,
+            MTLVertexFormat::UShortNormalized => MTLAttributeFormat::UShortNormalized,
+            MTLVertexFormat::ShortNormalized => MTLAttributeFormat::ShortNormalized,
+            MTLVertexFormat::Half => MTLAttributeFormat::Half,
+        }
+    }
+}
+
+impl From<MTLAttributeFormat> for MTLVertexFormat {
+    fn from(format: MTLAttributeFormat) -> Self {
+        match format {
+            MTLAttributeFormat::Invalid => MTLVertexFormat::Invalid,
+            MTLAttributeFormat::UChar2 => MTLVertexFormat::UChar2,
+            MTLAttributeFormat::UChar3 => MTLVertexFormat::UChar3,
+            MTLAttributeFormat::UChar4 => MTLVertexFormat::UChar4,
+            MTLAttributeFormat::Char2 => MTLVertexFormat::Char2,
+            MTLAttributeFormat::Char3 => MTLVertexFormat::Char3,
+            MTLAttributeFormat::Char4 => MTLVertexFormat::Char4,
+            MTLAttributeFormat::UChar2Normalized => MTLVertexFormat::UChar2Normalized,
+            MTLAttributeFormat::UChar3Normalized => MTLVertexFormat::UChar3Normalized,
+            MTLAttributeFormat::UChar4Normalized => MTLVertexFormat::UChar4Normalized,
+            MTLAttributeFormat::Char2Normalized => MTLVertexFormat::Char2Normalized,
+            MTLAttributeFormat::Char3Normalized => MTLVertexFormat::Char3Normalized,
+            MTLAttributeFormat::Char4Normalized => MTLVertexFormat::Char4Normalized,
+            MTLAttributeFormat::UShort2 => MTLVertexFormat::UShort2,
+            MTLAttributeFormat::UShort3 => MTLVertexFormat::UShort3,
+            MTLAttributeFormat::UShort4 => MTLVertexFormat::UShort4,
+            MTLAttributeFormat::Short2 => MTLVertexFormat::Short2,
+            MTLAttributeFormat::Short3 => MTLVertexFormat::Short3,
+            MTLAttributeFormat::Short4 => MTLVertexFormat::Short4,
+            MTLAttributeFormat::UShort2Normalized => MTLVertexFormat::UShort2Normalized,
+            MTLAttributeFormat::UShort3Normalized => MTLVertexFormat::UShort3Normalized,
+            MTLAttributeFormat::UShort4Normalized => MTLVertexFormat::UShort4Normalized,
+            MTLAttributeFormat::Short2Normalized => MTLVertexFormat::Short2Normalized,
+            MTLAttributeFormat::Short3Normalized => MTLVertexFormat::Short3Normalized,
+            MTLAttributeFormat::Short4Normalized => MTLVertexFormat::Short4Normalized,
+            MTLAttributeFormat::Half2 => MTLVertexFormat::Half2,
+            MTLAttributeFormat::Half3 => MTLVertexFormat::Half3,
+            MTLAttributeFormat::Half4 => MTLVertexFormat::Half4,
+            MTLAttributeFormat::Float => MTLVertexFormat::Float,
+            MTLAttributeFormat::Float2 => MTLVertexFormat::Float2,
+            MTLAttributeFormat::Float3 => MTLVertexFormat::Float3,
+            MTLAttributeFormat::Float4 => MTLVertexFormat::Float4,
+            MTLAttributeFormat::Int => MTLVertexFormat::Int,
+            MTLAttributeFormat::Int2 => MTLVertexFormat::Int2,
+            MTLAttributeFormat::Int3 => MTLVertexFormat::Int3,
+            MTLAttributeFormat::Int4 => MTLVertexFormat::Int4,
+            MTLAttributeFormat::UInt => MTLVertexFormat::UInt,
+            MTLAttributeFormat::UInt2 => MTLVertexFormat::UInt2,
+            MTLAttributeFormat::UInt3 => MTLVertexFormat::UInt3,
+            MTLAttributeFormat::UInt4 => MTLVertexFormat::UInt4,
+            MTLAttributeFormat::Int1010102Normalized => MTLVertexFormat::Int1010102Normalized,
+            MTLAttributeFormat::UInt1010102Normalized => MTLVertexFormat::UInt1010102Normalized,
+            MTLAttributeFormat::UChar4Normalized_BGRA => MTLVertexFormat::UChar4Normalized_BGRA,
+            MTLAttributeFormat::UChar => MTLVertexFormat::UChar,
+            MTLAttributeFormat::Char => MTLVertexFormat::Char,
+            MTLAttributeFormat::UCharNormalized => MTLVertexFormat::UCharNormalized,
+            MTLAttributeFormat::CharNormalized => MTLVertexFormat::CharNormalized,
+            MTLAttributeFormat::UShort => MTLVertexFormat::UShort,
+            MTLAttributeFormat::Short => MTLVertexFormat::Short,
+            MTLAttributeFormat::UShortNormalized => MTLVertexFormat::UShortNormalized,
+            MTLAttributeFormat::ShortNormalized => MTLVertexFormat::ShortNormalized,
+            MTLAttributeFormat::Half => MTLVertexFormat::Half,
+        }
+    }
+}
+
+impl MTLAttributeFormat {
+    /// Returns the size, component layout and scalar type of this format, or `None` for
+    /// `MTLAttributeFormat::Invalid`.
+    pub fn info(&self) -> Option<VertexFormatInfo> {
+        MTLVertexFormat::from(*self).info()
+    }
+
+    /// Size in bytes of one attribute value, or 0 for `MTLAttributeFormat::Invalid`.
+    pub fn size(&self) -> u32 {
+        MTLVertexFormat::from(*self).size()
+    }
+
+    /// Number of components, or 0 for `MTLAttributeFormat::Invalid`.
+    pub fn component_count(&self) -> u32 {
+        MTLVertexFormat::from(*self).component_count()
+    }
+
+    pub fn scalar_type(&self) -> Option<VertexScalarType> {
+        MTLVertexFormat::from(*self).scalar_type()
+    }
+
+    pub fn is_normalized(&self) -> bool {
+        MTLVertexFormat::from(*self).is_normalized()
+    }
+
+    /// See `MTLVertexFormat::encode`.
+    pub fn encode(&self, value: [f32; 4], bytes: &mut [u8]) {
+        MTLVertexFormat::from(*self).encode(value, bytes)
+    }
+
+    /// See `MTLVertexFormat::decode`.
+    pub fn decode(&self, bytes: &[u8]) -> [f32; 4] {
+        MTLVertexFormat::from(*self).decode(bytes)
+    }
+}
+
 #[repr(u64)]
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -82,14 +237,17 @@ pub enum MTLStepFunction {
     ThreadPositionInGridYIndexed = 8,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLComputePipelineDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLComputePipelineDescriptor;
     pub struct ComputePipelineDescriptor;
     pub struct ComputePipelineDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ComputePipelineDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -99,6 +257,7 @@ impl ComputePipelineDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ComputePipelineDescriptorRef {
     pub fn label(&self) -> &str {
         unsafe {
@@ -268,14 +427,17 @@ impl ComputePipelineDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLComputePipelineState {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLComputePipelineState;
     pub struct ComputePipelineState;
     pub struct ComputePipelineStateRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ComputePipelineStateRef {
     pub fn label(&self) -> &str {
         unsafe {
@@ -332,14 +494,17 @@ impl ComputePipelineStateRef {
     // - (nullable id <MTLIntersectionFunctionTable>)newIntersectionFunctionTableWithDescriptor:(MTLIntersectionFunctionTableDescriptor * _Nonnull)descriptor
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLStageInputOutputDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLStageInputOutputDescriptor;
     pub struct StageInputOutputDescriptor;
     pub struct StageInputOutputDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl StageInputOutputDescriptor {
     pub fn new<'a>() -> &'a StageInputOutputDescriptorRef {
         unsafe {
@@ -349,6 +514,7 @@ impl StageInputOutputDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl StageInputOutputDescriptorRef {
     pub fn attributes(&self) -> Option<&AttributeDescriptorArrayRef> {
         unsafe { msg_send![self, attributes] }
@@ -379,14 +545,17 @@ impl StageInputOutputDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLAttributeDescriptorArray {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLAttributeDescriptorArray;
     pub struct AttributeDescriptorArray;
     pub struct AttributeDescriptorArrayRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl AttributeDescriptorArrayRef {
     pub fn object_at(&self, index: NSUInteger) -> Option<&AttributeDescriptorRef> {
         unsafe { msg_send![self, objectAtIndexedSubscript: index] }
@@ -397,14 +566,17 @@ impl AttributeDescriptorArrayRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLAttributeDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLAttributeDescriptor;
     pub struct AttributeDescriptor;
     pub struct AttributeDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl AttributeDescriptorRef {
     pub fn buffer_index(&self) -> NSUInteger {
         unsafe { msg_send![self, bufferIndex] }
@@ -431,14 +603,17 @@ impl AttributeDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLBufferLayoutDescriptorArray {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLBufferLayoutDescriptorArray;
     pub struct BufferLayoutDescriptorArray;
     pub struct BufferLayoutDescriptorArrayRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl BufferLayoutDescriptorArrayRef {
     pub fn object_at(&self, index: NSUInteger) -> Option<&BufferLayoutDescriptorRef> {
         unsafe { msg_send![self, objectAtIndexedSubscript: index] }
@@ -453,14 +628,17 @@ impl BufferLayoutDescriptorArrayRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLBufferLayoutDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLBufferLayoutDescriptor;
     pub struct BufferLayoutDescriptor;
     pub struct BufferLayoutDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl BufferLayoutDescriptorRef {
     pub fn step_function(&self) -> MTLStepFunction {
         unsafe { msg_send![self, stepFunction] }
@@ -29,14 +29,17 @@ impl Default for MTLMutability {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLPipelineBufferDescriptorArray {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLPipelineBufferDescriptorArray;
     pub struct PipelineBufferDescriptorArray;
     pub struct PipelineBufferDescriptorArrayRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl PipelineBufferDescriptorArrayRef {
     pub fn object_at(&self, index: NSUInteger) -> Option<&PipelineBufferDescriptorRef> {
         unsafe { msg_send![self, objectAtIndexedSubscript: index] }
@@ -51,14 +54,17 @@ impl PipelineBufferDescriptorArrayRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLPipelineBufferDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLPipelineBufferDescriptor;
     pub struct PipelineBufferDescriptor;
     pub struct PipelineBufferDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl PipelineBufferDescriptorRef {
     pub fn mutability(&self) -> MTLMutability {
         unsafe { msg_send![self, mutability] }
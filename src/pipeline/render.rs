@@ -7,6 +7,7 @@
 
 use super::*;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use objc::runtime::{NO, YES};
 
 #[repr(u64)]
@@ -71,14 +72,17 @@ pub enum MTLPrimitiveTopologyClass {
 // TODO: MTLTessellationFactorFormat
 // TODO: MTLTessellationControlPointIndexType
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderPipelineColorAttachmentDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderPipelineColorAttachmentDescriptor;
     pub struct RenderPipelineColorAttachmentDescriptor;
     pub struct RenderPipelineColorAttachmentDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPipelineColorAttachmentDescriptorRef {
     pub fn pixel_format(&self) -> MTLPixelFormat {
         unsafe { msg_send![self, pixelFormat] }
@@ -159,14 +163,17 @@ impl RenderPipelineColorAttachmentDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderPipelineReflection {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderPipelineReflection;
     pub struct RenderPipelineReflection;
     pub struct RenderPipelineReflectionRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPipelineReflection {
     #[cfg(feature = "private")]
     pub unsafe fn new(
@@ -192,6 +199,7 @@ impl RenderPipelineReflection {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPipelineReflectionRef {
     /// An array of objects that describe the arguments of a fragment function.
     pub fn fragment_arguments(&self) -> &ArgumentArrayRef {
@@ -209,14 +217,17 @@ impl RenderPipelineReflectionRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLArgumentArray {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLArgumentArray;
     pub struct ArgumentArray;
     pub struct ArgumentArrayRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ArgumentArrayRef {
     pub fn object_at(&self, index: NSUInteger) -> Option<&ArgumentRef> {
         unsafe { msg_send![self, objectAtIndexedSubscript: index] }
@@ -227,14 +238,17 @@ impl ArgumentArrayRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLComputePipelineReflection {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLComputePipelineReflection;
     pub struct ComputePipelineReflection;
     pub struct ComputePipelineReflectionRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ComputePipelineReflectionRef {
     /// An array of objects that describe the arguments of a compute function.
     pub fn arguments(&self) -> &ArgumentArrayRef {
@@ -242,14 +256,17 @@ impl ComputePipelineReflectionRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderPipelineDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderPipelineDescriptor;
     pub struct RenderPipelineDescriptor;
     pub struct RenderPipelineDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPipelineDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -259,6 +276,7 @@ impl RenderPipelineDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPipelineDescriptorRef {
     pub fn label(&self) -> &str {
         unsafe {
@@ -460,14 +478,17 @@ impl RenderPipelineDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderPipelineState {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderPipelineState;
     pub struct RenderPipelineState;
     pub struct RenderPipelineStateRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPipelineStateRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
@@ -481,14 +502,17 @@ impl RenderPipelineStateRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderPipelineColorAttachmentDescriptorArray {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderPipelineColorAttachmentDescriptorArray;
     pub struct RenderPipelineColorAttachmentDescriptorArray;
     pub struct RenderPipelineColorAttachmentDescriptorArrayRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPipelineColorAttachmentDescriptorArrayRef {
     pub fn object_at(
         &self,
@@ -0,0 +1,627 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+/// A single MSL translation unit with its quoted includes inlined, ready for
+/// `DeviceRef::new_library_with_source`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AssembledSource {
+    pub source: String,
+    /// Every file that was read, in the order it was first included, starting with the root.
+    pub dependencies: Vec<PathBuf>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IncludeError {
+    /// No file matched an `#include`; `included_from` holds the including file and line.
+    NotFound {
+        name: String,
+        included_from: Option<(PathBuf, u32)>,
+    },
+    /// A file includes itself, directly or indirectly, without `#pragma once` or a guard.
+    Cycle {
+        path: PathBuf,
+    },
+    Io {
+        path: PathBuf,
+        kind: io::ErrorKind,
+    },
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IncludeError::NotFound {
+                name,
+                included_from: Some((file, line)),
+            } => write!(
+                f,
+                "\"{}\" not found (included from {}:{})",
+                name,
+                file.display(),
+                line
+            ),
+            IncludeError::NotFound {
+                name,
+                included_from: None,
+            } => write!(f, "\"{}\" not found", name),
+            IncludeError::Cycle { path } => write!(f, "{} includes itself", path.display()),
+            IncludeError::Io { path, kind } => {
+                write!(f, "failed to read {}: {:?}", path.display(), kind)
+            }
+        }
+    }
+}
+
+impl Error for IncludeError {}
+
+/// Resolves quoted `#include` and `#import` directives in MSL and joins the result into one
+/// source string.
+///
+/// Includes are looked up relative to the including file first, then in each search path in
+/// the order they were added. At each location, virtual files take precedence over the disk.
+/// Imported files, and files marked `#pragma once` or wrapped in an include guard, are inlined
+/// only once. System includes such as `<metal_stdlib>` are left for the compiler. Directives
+/// inside comments are ignored, but conditional directives are not evaluated, so an include
+/// inside `#if 0` must still resolve.
+///
+/// `#line` markers are emitted around every inlined file so that compiler diagnostics refer
+/// to the original file and line.
+#[derive(Clone, Debug, Default)]
+pub struct SourceAssembler {
+    search_paths: Vec<PathBuf>,
+    virtual_files: HashMap<PathBuf, String>,
+}
+
+impl SourceAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_search_path<P: AsRef<Path>>(&mut self, path: P) {
+        self.search_paths.push(normalize(path.as_ref()));
+    }
+
+    /// Registers in-memory file contents under `path`, shadowing any file on disk.
+    pub fn add_virtual_file<P: AsRef<Path>>(&mut self, path: P, contents: impl Into<String>) {
+        self.virtual_files
+            .insert(normalize(path.as_ref()), contents.into());
+    }
+
+    pub fn assemble_file<P: AsRef<Path>>(&self, path: P) -> Result<AssembledSource, IncludeError> {
+        let path = normalize(path.as_ref());
+        let source = self.load(&path)?.ok_or_else(|| IncludeError::NotFound {
+            name: path.display().to_string(),
+            included_from: None,
+        })?;
+        self.assemble_source(path, &source)
+    }
+
+    /// Assembles `source`, resolving its relative includes against the directory of `path`.
+    pub fn assemble_source<P: AsRef<Path>>(
+        &self,
+        path: P,
+        source: &str,
+    ) -> Result<AssembledSource, IncludeError> {
+        let mut state = State::default();
+        self.process(&normalize(path.as_ref()), source, &mut state)?;
+        Ok(AssembledSource {
+            source: state.output,
+            dependencies: state.dependencies,
+        })
+    }
+
+    fn process(&self, path: &Path, source: &str, state: &mut State) -> Result<(), IncludeError> {
+        if state.stack.iter().any(|open| open == path) {
+            return Err(IncludeError::Cycle {
+                path: path.to_owned(),
+            });
+        }
+        if !state.dependencies.iter().any(|known| known == path) {
+            state.dependencies.push(path.to_owned());
+        }
+        if is_included_once(source) {
+            state.included_once.insert(path.to_owned());
+        }
+        state.stack.push(path.to_owned());
+        push_line_marker(&mut state.output, 1, path);
+
+        for (index, (line, code)) in source.lines().zip(code_lines(source)).enumerate() {
+            let line_number = index as u32 + 1;
+            match directive(&code) {
+                Some(Directive::Include { name, import }) => {
+                    let (resolved, contents) = self.resolve(name, path, line_number)?;
+                    if state.included_once.contains(&resolved) {
+                        state.output.push('\n');
+                    } else {
+                        if import {
+                            state.included_once.insert(resolved.clone());
+                        }
+                        self.process(&resolved, &contents, state)?;
+                        push_line_marker(&mut state.output, line_number + 1, path);
+                    }
+                }
+                // The assembled output is a main file, where `#pragma once` only warns.
+                Some(Directive::PragmaOnce) => state.output.push('\n'),
+                _ => {
+                    state.output.push_str(line);
+                    state.output.push('\n');
+                }
+            }
+        }
+
+        state.stack.pop();
+        Ok(())
+    }
+
+    /// The paths an `#include "name"` in `including_file` is looked up at, in order.
+    pub fn include_candidates<P: AsRef<Path>>(
+        &self,
+        name: &str,
+        including_file: P,
+    ) -> Vec<PathBuf> {
+        let local = including_file.as_ref().parent().map(|dir| dir.join(name));
+        let searched = self.search_paths.iter().map(|dir| dir.join(name));
+        local
+            .into_iter()
+            .chain(searched)
+            .map(|candidate| normalize(&candidate))
+            .collect()
+    }
+
+    fn resolve(
+        &self,
+        name: &str,
+        including_file: &Path,
+        line: u32,
+    ) -> Result<(PathBuf, String), IncludeError> {
+        for candidate in self.include_candidates(name, including_file) {
+            if let Some(contents) = self.load(&candidate)? {
+                return Ok((candidate, contents));
+            }
+        }
+        Err(IncludeError::NotFound {
+            name: name.to_owned(),
+            included_from: Some((including_file.to_owned(), line)),
+        })
+    }
+
+    fn load(&self, path: &Path) -> Result<Option<String>, IncludeError> {
+        if let Some(contents) = self.virtual_files.get(path) {
+            return Ok(Some(contents.clone()));
+        }
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(IncludeError::Io {
+                path: path.to_owned(),
+                kind: error.kind(),
+            }),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    output: String,
+    dependencies: Vec<PathBuf>,
+    included_once: HashSet<PathBuf>,
+    stack: Vec<PathBuf>,
+}
+
+enum Directive<'a> {
+    /// `#include`, or `#import` when `import` is set, which never inlines a file twice.
+    Include {
+        name: &'a str,
+        import: bool,
+    },
+    /// `#include <name>` or `#import <name>`, left for the compiler.
+    SystemInclude(&'a str),
+    PragmaOnce,
+    IfNotDefined(&'a str),
+    Define(&'a str),
+    EndIf,
+    Other,
+}
+
+/// Classifies a preprocessor line, ignoring anything after the directive's operand.
+fn directive(line: &str) -> Option<Directive<'_>> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let keyword_end = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(rest.len());
+    let (keyword, operand) = rest.split_at(keyword_end);
+    let operand = operand.trim_start();
+    let first_word = || operand.split_whitespace().next().unwrap_or("");
+
+    Some(match keyword {
+        "include" | "import" => {
+            if let Some(quoted) = operand.strip_prefix('"') {
+                match quoted.find('"') {
+                    Some(end) => Directive::Include {
+                        name: &quoted[..end],
+                        import: keyword == "import",
+                    },
+                    None => Directive::Other,
+                }
+            } else if let Some(angled) = operand.strip_prefix('<') {
+                match angled.find('>') {
+                    Some(end) => Directive::SystemInclude(&angled[..end]),
+                    None => Directive::Other,
+                }
+            } else {
+                Directive::Other
+            }
+        }
+        "pragma" if first_word() == "once" => Directive::PragmaOnce,
+        "ifndef" => Directive::IfNotDefined(first_word()),
+        "define" => Directive::Define(first_word().split('(').next().unwrap_or("")),
+        "endif" => Directive::EndIf,
+        _ => Directive::Other,
+    })
+}
+
+/// The lines of `source` with comments blanked out, so that commented-out directives are
+/// not mistaken for real ones.
+fn code_lines(source: &str) -> impl Iterator<Item = String> + '_ {
+    let mut in_block_comment = false;
+    source
+        .lines()
+        .map(move |line| strip_comments(line, &mut in_block_comment))
+}
+
+/// Replaces the comments in `line` with a space, continuing and updating the state of a
+/// `/* */` comment spanning several lines. Comment markers inside literals are kept.
+fn strip_comments(line: &str, in_block_comment: &mut bool) -> String {
+    let mut code = String::with_capacity(line.len());
+    let mut literal = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if *in_block_comment {
+            if c == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                *in_block_comment = false;
+                code.push(' ');
+            }
+            continue;
+        }
+        code.push(c);
+        if let Some(quote) = literal {
+            if c == '\\' {
+                code.extend(chars.next());
+            } else if c == quote {
+                literal = None;
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('/', Some(&'*')) => {
+                chars.next();
+                code.pop();
+                *in_block_comment = true;
+            }
+            ('/', Some(&'/')) => {
+                code.pop();
+                break;
+            }
+            ('"', _) | ('\'', _) => literal = Some(c),
+            _ => {}
+        }
+    }
+    code
+}
+
+/// The names of every `#include` and `#import` in `source`, paired with whether the name was
+/// written in angle brackets. Conditional directives are not evaluated.
+pub(crate) fn include_directives(source: &str) -> impl Iterator<Item = (String, bool)> + '_ {
+    code_lines(source).filter_map(|line| match directive(&line)? {
+        Directive::Include { name, .. } => Some((name.to_owned(), false)),
+        Directive::SystemInclude(name) => Some((name.to_owned(), true)),
+        _ => None,
+    })
+}
+
+/// Whether a file uses `#pragma once` or is wrapped in an `#ifndef X` / `#define X` / `#endif`
+/// guard, and so contributes nothing when included a second time.
+fn is_included_once(source: &str) -> bool {
+    let lines: Vec<String> = code_lines(source)
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if lines
+        .iter()
+        .any(|line| matches!(directive(line), Some(Directive::PragmaOnce)))
+    {
+        return true;
+    }
+
+    let directive_at = |index: usize| lines.get(index).and_then(|line| directive(line));
+    let guarded = match (directive_at(0), directive_at(1)) {
+        (Some(Directive::IfNotDefined(guard)), Some(Directive::Define(defined))) => {
+            !guard.is_empty() && guard == defined
+        }
+        _ => false,
+    };
+    guarded && lines.len() > 2 && matches!(directive_at(lines.len() - 1), Some(Directive::EndIf))
+}
+
+fn push_line_marker(output: &mut String, line: u32, path: &Path) {
+    let name = path.display().to_string();
+    output.push_str(&format!(
+        "#line {} \"{}\"\n",
+        line,
+        name.replace('\\', "\\\\").replace('"', "\\\"")
+    ));
+}
+
+/// Removes `.` components and folds `..` into the preceding component without touching the
+/// file system, so virtual and on-disk paths compare equal.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assembler(files: &[(&str, &str)]) -> SourceAssembler {
+        let mut assembler = SourceAssembler::new();
+        for &(path, contents) in files {
+            assembler.add_virtual_file(path, contents);
+        }
+        assembler
+    }
+
+    fn count(haystack: &str, needle: &str) -> usize {
+        haystack.matches(needle).count()
+    }
+
+    #[test]
+    fn line_markers() {
+        let assembler = assembler(&[("/src/x.h", "int x;\n")]);
+        let main = "float a;\n#include \"x.h\"\n#include \"x.h\"\nfloat b;\n";
+        let assembled = assembler.assemble_source("/src/main.metal", main).unwrap();
+        assert_eq!(
+            assembled.source,
+            "#line 1 \"/src/main.metal\"\n\
+             float a;\n\
+             #line 1 \"/src/x.h\"\n\
+             int x;\n\
+             #line 3 \"/src/main.metal\"\n\
+             #line 1 \"/src/x.h\"\n\
+             int x;\n\
+             #line 4 \"/src/main.metal\"\n\
+             float b;\n"
+        );
+        assert_eq!(
+            assembled.dependencies,
+            [PathBuf::from("/src/main.metal"), PathBuf::from("/src/x.h")]
+        );
+    }
+
+    #[test]
+    fn include_guards_and_pragma_once() {
+        let assembler = assembler(&[
+            ("/src/once.h", "#pragma once\nint once;\n"),
+            (
+                "/src/guarded.h",
+                "// Guarded header\n#ifndef GUARDED_H\n#define GUARDED_H\nint guarded;\n#endif\n",
+            ),
+            ("/src/plain.h", "int plain;\n"),
+        ]);
+        let main = "#include \"once.h\"\n#include \"guarded.h\"\n#include \"plain.h\"\n\
+                    #include \"once.h\"\n#include \"guarded.h\"\n#include \"plain.h\"\n";
+        let source = assembler
+            .assemble_source("/src/main.metal", main)
+            .unwrap()
+            .source;
+        assert_eq!(count(&source, "int once;"), 1);
+        assert_eq!(count(&source, "int guarded;"), 1);
+        assert_eq!(count(&source, "int plain;"), 2);
+        assert!(!source.contains("#pragma once"));
+    }
+
+    #[test]
+    fn mismatched_guards_do_not_dedupe() {
+        assert!(!is_included_once(
+            "#ifndef A_H\n#define B_H\nint a;\n#endif\n"
+        ));
+        assert!(!is_included_once("#ifndef A_H\n#define A_H\n"));
+        assert!(!is_included_once(
+            "int before;\n#ifndef A_H\n#define A_H\n#endif\n"
+        ));
+        assert!(is_included_once(
+            "/* License\n * text\n */\n#ifndef A_H\n#define A_H\n#endif\n"
+        ));
+    }
+
+    #[test]
+    fn import_inlines_once() {
+        let assembler = assembler(&[("/src/types.h", "struct T {};\n")]);
+        let main = "#import \"types.h\"\n#import \"types.h\"\n#include \"types.h\"\n";
+        let source = assembler
+            .assemble_source("/src/main.metal", main)
+            .unwrap()
+            .source;
+        assert_eq!(count(&source, "struct T {};"), 1);
+    }
+
+    #[test]
+    fn system_includes_are_kept() {
+        let main = "#include <metal_stdlib>\nusing namespace metal;\n";
+        let source = SourceAssembler::new()
+            .assemble_source("/src/main.metal", main)
+            .unwrap()
+            .source;
+        assert!(source.contains("#include <metal_stdlib>\n"));
+    }
+
+    #[test]
+    fn search_path_order() {
+        let mut assembler = assembler(&[
+            ("/first/common.h", "int first;\n"),
+            ("/second/common.h", "int second;\n"),
+            ("/second/only.h", "int only;\n"),
+        ]);
+        assembler.add_search_path("/first");
+        assembler.add_search_path("/second");
+        assert_eq!(
+            assembler.include_candidates("common.h", "/src/main.metal"),
+            [
+                PathBuf::from("/src/common.h"),
+                PathBuf::from("/first/common.h"),
+                PathBuf::from("/second/common.h"),
+            ]
+        );
+
+        let main = "#include \"common.h\"\n#include \"only.h\"\n";
+        let source = assembler
+            .assemble_source("/src/main.metal", main)
+            .unwrap()
+            .source;
+        assert!(source.contains("int first;") && !source.contains("int second;"));
+        assert!(source.contains("int only;"));
+
+        // The including file's directory comes before every search path.
+        assembler.add_virtual_file("/src/common.h", "int local;\n");
+        let source = assembler
+            .assemble_source("/src/main.metal", main)
+            .unwrap()
+            .source;
+        assert!(source.contains("int local;") && !source.contains("int first;"));
+    }
+
+    #[test]
+    fn virtual_files_shadow_the_disk() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let main = "#include \"Cargo.toml\"\n";
+        let root = manifest_dir.join("main.metal");
+
+        let mut assembler = SourceAssembler::new();
+        let source = assembler.assemble_source(&root, main).unwrap().source;
+        assert!(source.contains("[package]"));
+
+        assembler.add_virtual_file(manifest_dir.join("Cargo.toml"), "int shadowed;\n");
+        let source = assembler.assemble_source(&root, main).unwrap().source;
+        assert!(source.contains("int shadowed;") && !source.contains("[package]"));
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let cyclic = assembler(&[
+            ("/src/a.h", "#include \"b.h\"\n"),
+            ("/src/b.h", "#include \"a.h\"\n"),
+        ]);
+        assert_eq!(
+            cyclic.assemble_source("/src/main.metal", "#include \"a.h\"\n"),
+            Err(IncludeError::Cycle {
+                path: PathBuf::from("/src/a.h")
+            })
+        );
+        assert_eq!(
+            cyclic.assemble_file("/src/a.h"),
+            Err(IncludeError::Cycle {
+                path: PathBuf::from("/src/a.h")
+            })
+        );
+
+        let guarded = assembler(&[
+            ("/src/a.h", "#pragma once\n#include \"b.h\"\n"),
+            ("/src/b.h", "#pragma once\n#include \"a.h\"\n"),
+        ]);
+        assert!(guarded
+            .assemble_source("/src/main.metal", "#include \"a.h\"\n")
+            .is_ok());
+    }
+
+    #[test]
+    fn missing_includes_name_the_including_line() {
+        let assembler = assembler(&[("/src/a.h", "int a;\n#include \"missing.h\"\n")]);
+        assert_eq!(
+            assembler.assemble_file("/src/a.h"),
+            Err(IncludeError::NotFound {
+                name: "missing.h".to_owned(),
+                included_from: Some((PathBuf::from("/src/a.h"), 2)),
+            })
+        );
+        assert_eq!(
+            assembler.assemble_file("/src/nope.metal"),
+            Err(IncludeError::NotFound {
+                name: "/src/nope.metal".to_owned(),
+                included_from: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parent_directories_are_normalized() {
+        assert_eq!(
+            normalize(Path::new("/a/b/../c/./d.h")),
+            Path::new("/a/c/d.h")
+        );
+        assert_eq!(normalize(Path::new("/../x.h")), Path::new("/x.h"));
+        assert_eq!(normalize(Path::new("a/../../b.h")), Path::new("../b.h"));
+        assert_eq!(normalize(Path::new("./a/./b.h")), Path::new("a/b.h"));
+
+        let assembler = assembler(&[("/src/common/util.h", "int util;\n")]);
+        let main = "#include \"../common/util.h\"\n#include \"./../common/util.h\"\n";
+        let assembled = assembler
+            .assemble_source("/src/shaders/./main.metal", main)
+            .unwrap();
+        assert_eq!(
+            assembled.dependencies,
+            [
+                PathBuf::from("/src/shaders/main.metal"),
+                PathBuf::from("/src/common/util.h"),
+            ]
+        );
+        assert_eq!(count(&assembled.source, "int util;"), 2);
+    }
+
+    #[test]
+    fn commented_out_includes_are_ignored() {
+        let assembler = assembler(&[("/src/real.h", "int real;\n")]);
+        let main = "// #include \"line.h\"\n\
+                    /* #include \"inline.h\" */\n\
+                    /*\n\
+                    #include \"block.h\"\n\
+                    */ #include \"real.h\"\n\
+                    constant char *text = \"/*\";\n\
+                    #include \"real.h\" // trailing comment\n";
+        let source = assembler
+            .assemble_source("/src/main.metal", main)
+            .unwrap()
+            .source;
+        assert_eq!(count(&source, "int real;"), 2);
+        assert!(source.contains("#include \"block.h\"\n"));
+
+        let includes: Vec<_> = include_directives(main).collect();
+        assert_eq!(
+            includes,
+            [("real.h".to_owned(), false), ("real.h".to_owned(), false)]
+        );
+    }
+}
@@ -0,0 +1,667 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use super::*;
+
+/// The device queries that capability-driven code paths depend on.
+///
+/// Implemented by `DeviceRef` for live devices and by `DeviceProfile` for captured or
+/// preset answers, so feature selection can be written once and exercised offline.
+pub trait DeviceCapabilities {
+    fn name(&self) -> &str;
+    fn supports_family(&self, family: MTLGPUFamily) -> bool;
+    fn argument_buffers_support(&self) -> MTLArgumentBuffersTier;
+    fn read_write_texture_support(&self) -> MTLReadWriteTextureTier;
+    fn max_threads_per_threadgroup(&self) -> MTLSize;
+    fn max_threadgroup_memory_length(&self) -> NSUInteger;
+    fn supports_texture_sample_count(&self, count: NSUInteger) -> bool;
+    fn max_buffer_length(&self) -> NSUInteger;
+    fn max_argument_buffer_sampler_count(&self) -> NSUInteger;
+    fn recommended_max_working_set_size(&self) -> u64;
+    fn has_unified_memory(&self) -> bool;
+    fn is_low_power(&self) -> bool;
+    fn is_headless(&self) -> bool;
+    fn is_removable(&self) -> bool;
+    fn supports_raytracing(&self) -> bool;
+    fn raster_order_groups_supported(&self) -> bool;
+    fn supports_32bit_float_filtering(&self) -> bool;
+    fn supports_32bit_MSAA(&self) -> bool;
+    fn supports_BC_texture_compression(&self) -> bool;
+    fn d24_s8_supported(&self) -> bool;
+}
+
+const GPU_FAMILIES: [MTLGPUFamily; 16] = [
+    MTLGPUFamily::Common1,
+    MTLGPUFamily::Common2,
+    MTLGPUFamily::Common3,
+    MTLGPUFamily::Apple1,
+    MTLGPUFamily::Apple2,
+    MTLGPUFamily::Apple3,
+    MTLGPUFamily::Apple4,
+    MTLGPUFamily::Apple5,
+    MTLGPUFamily::Apple6,
+    MTLGPUFamily::Apple7,
+    MTLGPUFamily::Apple8,
+    MTLGPUFamily::Apple9,
+    MTLGPUFamily::Mac1,
+    MTLGPUFamily::Mac2,
+    MTLGPUFamily::MacCatalyst1,
+    MTLGPUFamily::MacCatalyst2,
+];
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const TEXTURE_SAMPLE_COUNTS: [NSUInteger; 6] = [1, 2, 4, 8, 16, 32];
+
+/// A snapshot of the answers a device gives to the `DeviceCapabilities` queries.
+///
+/// Profiles are captured from a live device with `DeviceProfile::capture`, or loaded
+/// from one of the bundled presets with `DeviceProfile::preset`. With the `serde`
+/// feature enabled they can be stored and loaded in any serde format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceProfile {
+    pub name: String,
+    pub families: Vec<MTLGPUFamily>,
+    pub argument_buffers_support: MTLArgumentBuffersTier,
+    pub read_write_texture_support: MTLReadWriteTextureTier,
+    pub max_threads_per_threadgroup: MTLSize,
+    pub max_threadgroup_memory_length: NSUInteger,
+    pub texture_sample_counts: Vec<NSUInteger>,
+    pub max_buffer_length: NSUInteger,
+    pub max_argument_buffer_sampler_count: NSUInteger,
+    pub recommended_max_working_set_size: u64,
+    pub has_unified_memory: bool,
+    pub is_low_power: bool,
+    pub is_headless: bool,
+    pub is_removable: bool,
+    pub supports_raytracing: bool,
+    pub raster_order_groups_supported: bool,
+    pub supports_32bit_float_filtering: bool,
+    pub supports_32bit_msaa: bool,
+    pub supports_bc_texture_compression: bool,
+    pub d24_s8_supported: bool,
+}
+
+impl DeviceProfile {
+    /// Names accepted by `DeviceProfile::preset`.
+    pub const PRESETS: &'static [&'static str] = &["M1", "M2", "A14", "A15", "Radeon Pro 5500M"];
+
+    /// Records the answers of a live device.
+    ///
+    /// Some of the queries are only available on macOS 11.0+ and iOS 14.0+.
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn capture(device: &DeviceRef) -> Self {
+        DeviceProfile {
+            name: device.name().to_owned(),
+            families: GPU_FAMILIES
+                .iter()
+                .cloned()
+                .filter(|&family| device.supports_family(family))
+                .collect(),
+            argument_buffers_support: device.argument_buffers_support(),
+            read_write_texture_support: device.read_write_texture_support(),
+            max_threads_per_threadgroup: device.max_threads_per_threadgroup(),
+            max_threadgroup_memory_length: device.max_threadgroup_memory_length(),
+            texture_sample_counts: TEXTURE_SAMPLE_COUNTS
+                .iter()
+                .cloned()
+                .filter(|&count| device.supports_texture_sample_count(count))
+                .collect(),
+            max_buffer_length: device.max_buffer_length(),
+            max_argument_buffer_sampler_count: device.max_argument_buffer_sampler_count(),
+            recommended_max_working_set_size: device.recommended_max_working_set_size(),
+            has_unified_memory: device.has_unified_memory(),
+            is_low_power: device.is_low_power(),
+            is_headless: device.is_headless(),
+            is_removable: device.is_removable(),
+            supports_raytracing: device.supports_raytracing(),
+            raster_order_groups_supported: device.raster_order_groups_supported(),
+            supports_32bit_float_filtering: device.supports_32bit_float_filtering(),
+            supports_32bit_msaa: device.supports_32bit_MSAA(),
+            supports_bc_texture_compression: device.supports_BC_texture_compression(),
+            d24_s8_supported: device.d24_s8_supported(),
+        }
+    }
+
+    /// Returns one of the bundled presets listed in `DeviceProfile::PRESETS`.
+    ///
+    /// Memory sizes are those of the base configuration of each device.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "M1" => Some(Self::apple_silicon_mac("Apple M1", MTLGPUFamily::Apple7)),
+            "M2" => Some(Self::apple_silicon_mac("Apple M2", MTLGPUFamily::Apple8)),
+            "A14" => Some(Self::apple_mobile("Apple A14 GPU", MTLGPUFamily::Apple7)),
+            "A15" => Some(Self::apple_mobile("Apple A15 GPU", MTLGPUFamily::Apple8)),
+            "Radeon Pro 5500M" => Some(DeviceProfile {
+                name: "AMD Radeon Pro 5500M".to_owned(),
+                families: vec![
+                    MTLGPUFamily::Common1,
+                    MTLGPUFamily::Common2,
+                    MTLGPUFamily::Common3,
+                    MTLGPUFamily::Mac1,
+                    MTLGPUFamily::Mac2,
+                    MTLGPUFamily::MacCatalyst1,
+                    MTLGPUFamily::MacCatalyst2,
+                ],
+                argument_buffers_support: MTLArgumentBuffersTier::Tier2,
+                read_write_texture_support: MTLReadWriteTextureTier::Tier2,
+                max_threads_per_threadgroup: MTLSize::new(1024, 1024, 1024),
+                max_threadgroup_memory_length: 64 * 1024,
+                texture_sample_counts: vec![1, 2, 4, 8],
+                max_buffer_length: 3584 << 20,
+                max_argument_buffer_sampler_count: 1024,
+                recommended_max_working_set_size: 4 << 30,
+                has_unified_memory: false,
+                is_low_power: false,
+                is_headless: false,
+                is_removable: false,
+                supports_raytracing: true,
+                raster_order_groups_supported: true,
+                supports_32bit_float_filtering: true,
+                supports_32bit_msaa: true,
+                supports_bc_texture_compression: true,
+                d24_s8_supported: true,
+            }),
+            _ => None,
+        }
+    }
+
+    fn apple_families(newest: MTLGPUFamily) -> impl Iterator<Item = MTLGPUFamily> {
+        GPU_FAMILIES
+            .iter()
+            .cloned()
+            .filter(move |&family| Self::is_apple_family(family) && family as i64 <= newest as i64)
+    }
+
+    fn common_families() -> impl Iterator<Item = MTLGPUFamily> {
+        GPU_FAMILIES
+            .iter()
+            .cloned()
+            .filter(|family| matches!(family.kind(), GPUFamilyKind::Common(_)))
+    }
+
+    fn apple_mobile(name: &str, newest: MTLGPUFamily) -> Self {
+        DeviceProfile {
+            name: name.to_owned(),
+            families: Self::common_families()
+                .chain(Self::apple_families(newest))
+                .collect(),
+            argument_buffers_support: MTLArgumentBuffersTier::Tier2,
+            read_write_texture_support: MTLReadWriteTextureTier::Tier2,
+            max_threads_per_threadgroup: MTLSize::new(1024, 1024, 1024),
+            max_threadgroup_memory_length: 32 * 1024,
+            texture_sample_counts: vec![1, 2, 4],
+            max_buffer_length: 1 << 30,
+            max_argument_buffer_sampler_count: 1024,
+            recommended_max_working_set_size: 3 << 30,
+            has_unified_memory: true,
+            is_low_power: false,
+            is_headless: false,
+            is_removable: false,
+            supports_raytracing: true,
+            raster_order_groups_supported: true,
+            supports_32bit_float_filtering: true,
+            supports_32bit_msaa: true,
+            supports_bc_texture_compression: false,
+            d24_s8_supported: false,
+        }
+    }
+
+    fn apple_silicon_mac(name: &str, newest: MTLGPUFamily) -> Self {
+        DeviceProfile {
+            families: GPU_FAMILIES
+                .iter()
+                .cloned()
+                .filter(|&family| !Self::is_apple_family(family) || family as i64 <= newest as i64)
+                .collect(),
+            texture_sample_counts: vec![1, 2, 4, 8],
+            max_buffer_length: 4 << 30,
+            recommended_max_working_set_size: 5461 << 20,
+            supports_bc_texture_compression: true,
+            ..Self::apple_mobile(name, newest)
+        }
+    }
+
+    fn is_apple_family(family: MTLGPUFamily) -> bool {
+        matches!(family.kind(), GPUFamilyKind::Apple(_))
+    }
+}
+
+impl DeviceCapabilities for DeviceProfile {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn supports_family(&self, family: MTLGPUFamily) -> bool {
+        self.families.contains(&family)
+    }
+
+    fn argument_buffers_support(&self) -> MTLArgumentBuffersTier {
+        self.argument_buffers_support
+    }
+
+    fn read_write_texture_support(&self) -> MTLReadWriteTextureTier {
+        self.read_write_texture_support
+    }
+
+    fn max_threads_per_threadgroup(&self) -> MTLSize {
+        self.max_threads_per_threadgroup
+    }
+
+    fn max_threadgroup_memory_length(&self) -> NSUInteger {
+        self.max_threadgroup_memory_length
+    }
+
+    fn supports_texture_sample_count(&self, count: NSUInteger) -> bool {
+        self.texture_sample_counts.contains(&count)
+    }
+
+    fn max_buffer_length(&self) -> NSUInteger {
+        self.max_buffer_length
+    }
+
+    fn max_argument_buffer_sampler_count(&self) -> NSUInteger {
+        self.max_argument_buffer_sampler_count
+    }
+
+    fn recommended_max_working_set_size(&self) -> u64 {
+        self.recommended_max_working_set_size
+    }
+
+    fn has_unified_memory(&self) -> bool {
+        self.has_unified_memory
+    }
+
+    fn is_low_power(&self) -> bool {
+        self.is_low_power
+    }
+
+    fn is_headless(&self) -> bool {
+        self.is_headless
+    }
+
+    fn is_removable(&self) -> bool {
+        self.is_removable
+    }
+
+    fn supports_raytracing(&self) -> bool {
+        self.supports_raytracing
+    }
+
+    fn raster_order_groups_supported(&self) -> bool {
+        self.raster_order_groups_supported
+    }
+
+    fn supports_32bit_float_filtering(&self) -> bool {
+        self.supports_32bit_float_filtering
+    }
+
+    fn supports_32bit_MSAA(&self) -> bool {
+        self.supports_32bit_msaa
+    }
+
+    fn supports_BC_texture_compression(&self) -> bool {
+        self.supports_bc_texture_compression
+    }
+
+    fn d24_s8_supported(&self) -> bool {
+        self.d24_s8_supported
+    }
+}
+
+impl DeviceProfile {
+    /// Returns the newest feature sets implied by the supported GPU families, one per
+    /// platform, used to answer the static texture limits of a profile.
+    fn feature_sets(&self) -> Vec<MTLFeatureSet> {
+        let newest_mac = self
+            .families
+            .iter()
+            .filter_map(|family| match family {
+                MTLGPUFamily::Mac2 | MTLGPUFamily::MacCatalyst2 => {
+                    Some(MTLFeatureSet::macOS_GPUFamily2_v1)
+                }
+                MTLGPUFamily::Mac1 | MTLGPUFamily::MacCatalyst1 => {
+                    Some(MTLFeatureSet::macOS_GPUFamily1_v4)
+                }
+                _ => None,
+            })
+            .max_by_key(|&feature_set| feature_set as u64);
+        let newest_apple = self
+            .families
+            .iter()
+            .filter_map(|family| match family {
+                MTLGPUFamily::Apple1 => Some(MTLFeatureSet::iOS_GPUFamily1_v5),
+                MTLGPUFamily::Apple2 => Some(MTLFeatureSet::iOS_GPUFamily2_v5),
+                MTLGPUFamily::Apple3 => Some(MTLFeatureSet::iOS_GPUFamily3_v4),
+                MTLGPUFamily::Apple4 => Some(MTLFeatureSet::iOS_GPUFamily4_v2),
+                MTLGPUFamily::Apple5
+                | MTLGPUFamily::Apple6
+                | MTLGPUFamily::Apple7
+                | MTLGPUFamily::Apple8
+                | MTLGPUFamily::Apple9 => Some(MTLFeatureSet::iOS_GPUFamily5_v1),
+                _ => None,
+            })
+            .max_by_key(|&feature_set| feature_set as u64);
+        newest_mac.into_iter().chain(newest_apple).collect()
+    }
+
+    fn max_texture_limit(&self, limit: fn(&MTLFeatureSet) -> u32) -> u32 {
+        self.feature_sets().iter().map(limit).max().unwrap_or(0)
+    }
+}
+
+/// Texture limits of a profile are those of the feature sets its GPU families imply; a
+/// device in both an Apple and a Mac family gets the union of their capabilities.
+impl TextureLimits for DeviceProfile {
+    fn max_1d_texture_size(&self) -> u32 {
+        self.max_texture_limit(MTLFeatureSet::max_1d_texture_size)
+    }
+
+    fn max_2d_texture_size(&self) -> u32 {
+        self.max_texture_limit(MTLFeatureSet::max_2d_texture_size)
+    }
+
+    fn max_3d_texture_size(&self) -> u32 {
+        self.max_texture_limit(MTLFeatureSet::max_3d_texture_size)
+    }
+
+    fn max_cube_map_texture_size(&self) -> u32 {
+        self.max_texture_limit(MTLFeatureSet::max_cube_map_texture_size)
+    }
+
+    fn max_array_layers(&self) -> u32 {
+        self.max_texture_limit(MTLFeatureSet::max_array_layers)
+    }
+
+    /// The union of the feature set capabilities, narrowed by the device's own answers for
+    /// depth24/stencil8, BC compression and 32-bit float filtering and multisampling.
+    fn pixel_format_capabilities(&self, format: MTLPixelFormat) -> PixelFormatCapabilities {
+        use MTLPixelFormat::*;
+
+        let capabilities = self.feature_sets().iter().fold(
+            PixelFormatCapabilities::empty(),
+            |capabilities, feature_set| {
+                capabilities | feature_set.pixel_format_capabilities(format)
+            },
+        );
+        match format {
+            Depth24Unorm_Stencil8 | X24_Stencil8 if !self.d24_s8_supported => {
+                PixelFormatCapabilities::empty()
+            }
+            BC1_RGBA | BC1_RGBA_sRGB | BC2_RGBA | BC2_RGBA_sRGB | BC3_RGBA | BC3_RGBA_sRGB
+            | BC4_RUnorm | BC4_RSnorm | BC5_RGUnorm | BC5_RGSnorm | BC6H_RGBFloat
+            | BC6H_RGBUfloat | BC7_RGBAUnorm | BC7_RGBAUnorm_sRGB
+                if !self.supports_bc_texture_compression =>
+            {
+                PixelFormatCapabilities::empty()
+            }
+            R32Float | RG32Float | RGBA32Float => {
+                let mut capabilities = capabilities;
+                if !self.supports_32bit_float_filtering {
+                    capabilities.remove(PixelFormatCapabilities::Filter);
+                }
+                if !self.supports_32bit_msaa {
+                    capabilities
+                        .remove(PixelFormatCapabilities::Msaa | PixelFormatCapabilities::Resolve);
+                }
+                capabilities
+            }
+            _ => capabilities,
+        }
+    }
+
+    fn supports_sample_count(&self, count: NSUInteger) -> bool {
+        self.texture_sample_counts.contains(&count)
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl DeviceCapabilities for DeviceRef {
+    fn name(&self) -> &str {
+        DeviceRef::name(self)
+    }
+
+    fn supports_family(&self, family: MTLGPUFamily) -> bool {
+        DeviceRef::supports_family(self, family)
+    }
+
+    fn argument_buffers_support(&self) -> MTLArgumentBuffersTier {
+        DeviceRef::argument_buffers_support(self)
+    }
+
+    fn read_write_texture_support(&self) -> MTLReadWriteTextureTier {
+        DeviceRef::read_write_texture_support(self)
+    }
+
+    fn max_threads_per_threadgroup(&self) -> MTLSize {
+        DeviceRef::max_threads_per_threadgroup(self)
+    }
+
+    fn max_threadgroup_memory_length(&self) -> NSUInteger {
+        DeviceRef::max_threadgroup_memory_length(self)
+    }
+
+    fn supports_texture_sample_count(&self, count: NSUInteger) -> bool {
+        DeviceRef::supports_texture_sample_count(self, count)
+    }
+
+    fn max_buffer_length(&self) -> NSUInteger {
+        DeviceRef::max_buffer_length(self)
+    }
+
+    fn max_argument_buffer_sampler_count(&self) -> NSUInteger {
+        DeviceRef::max_argument_buffer_sampler_count(self)
+    }
+
+    fn recommended_max_working_set_size(&self) -> u64 {
+        DeviceRef::recommended_max_working_set_size(self)
+    }
+
+    fn has_unified_memory(&self) -> bool {
+        DeviceRef::has_unified_memory(self)
+    }
+
+    fn is_low_power(&self) -> bool {
+        DeviceRef::is_low_power(self)
+    }
+
+    fn is_headless(&self) -> bool {
+        DeviceRef::is_headless(self)
+    }
+
+    fn is_removable(&self) -> bool {
+        DeviceRef::is_removable(self)
+    }
+
+    fn supports_raytracing(&self) -> bool {
+        DeviceRef::supports_raytracing(self)
+    }
+
+    fn raster_order_groups_supported(&self) -> bool {
+        DeviceRef::raster_order_groups_supported(self)
+    }
+
+    fn supports_32bit_float_filtering(&self) -> bool {
+        DeviceRef::supports_32bit_float_filtering(self)
+    }
+
+    fn supports_32bit_MSAA(&self) -> bool {
+        DeviceRef::supports_32bit_MSAA(self)
+    }
+
+    fn supports_BC_texture_compression(&self) -> bool {
+        DeviceRef::supports_BC_texture_compression(self)
+    }
+
+    fn d24_s8_supported(&self) -> bool {
+        DeviceRef::d24_s8_supported(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_resolve() {
+        for &name in DeviceProfile::PRESETS {
+            let profile = DeviceProfile::preset(name).expect(name);
+            assert!(profile.name.contains(name), "{}", name);
+            for family in &[
+                MTLGPUFamily::Common1,
+                MTLGPUFamily::Common2,
+                MTLGPUFamily::Common3,
+            ] {
+                assert!(profile.supports_family(*family), "{} {:?}", name, family);
+            }
+        }
+        assert_eq!(DeviceProfile::preset("Voodoo2"), None);
+    }
+
+    #[test]
+    fn preset_families() {
+        let m1 = DeviceProfile::preset("M1").unwrap();
+        assert!(m1.supports_family(MTLGPUFamily::Apple7));
+        assert!(!m1.supports_family(MTLGPUFamily::Apple8));
+        assert!(m1.supports_family(MTLGPUFamily::Mac2));
+
+        let a15 = DeviceProfile::preset("A15").unwrap();
+        assert!(a15.supports_family(MTLGPUFamily::Apple1));
+        assert!(a15.supports_family(MTLGPUFamily::Apple8));
+        assert!(!a15.supports_family(MTLGPUFamily::Apple9));
+        assert!(!a15.supports_family(MTLGPUFamily::Mac1));
+        assert!(!a15.supports_family(MTLGPUFamily::MacCatalyst1));
+
+        let radeon = DeviceProfile::preset("Radeon Pro 5500M").unwrap();
+        assert!(!radeon.supports_family(MTLGPUFamily::Apple1));
+    }
+
+    fn describe<C: DeviceCapabilities + ?Sized>(device: &C) -> String {
+        format!(
+            "{} unified={} bc={}",
+            device.name(),
+            device.has_unified_memory(),
+            device.supports_BC_texture_compression()
+        )
+    }
+
+    #[test]
+    fn capabilities_are_usable_generically() {
+        let a14 = DeviceProfile::preset("A14").unwrap();
+        assert_eq!(describe(&a14), "Apple A14 GPU unified=true bc=false");
+
+        let radeon = DeviceProfile::preset("Radeon Pro 5500M").unwrap();
+        let devices: Vec<&dyn DeviceCapabilities> = vec![&a14, &radeon];
+        assert_eq!(
+            describe(devices[1]),
+            "AMD Radeon Pro 5500M unified=false bc=true"
+        );
+    }
+
+    fn render_target(pixel_format: MTLPixelFormat, sample_count: NSUInteger) -> TextureLayout {
+        let texture_type = if sample_count > 1 {
+            MTLTextureType::D2Multisample
+        } else {
+            MTLTextureType::D2
+        };
+        let mut texture = TextureLayout::new(texture_type, pixel_format, MTLSize::new(64, 64, 1));
+        texture.sample_count = sample_count;
+        texture
+    }
+
+    #[test]
+    fn profile_capabilities_follow_device_flags() {
+        use MTLPixelFormat::*;
+
+        let m1 = DeviceProfile::preset("M1").unwrap();
+        let radeon = DeviceProfile::preset("Radeon Pro 5500M").unwrap();
+        assert!(m1
+            .pixel_format_capabilities(Depth24Unorm_Stencil8)
+            .is_empty());
+        assert!(m1.pixel_format_capabilities(X24_Stencil8).is_empty());
+        assert!(m1
+            .pixel_format_capabilities(BC3_RGBA_sRGB)
+            .contains(PixelFormatCapabilities::Filter));
+        assert!(radeon
+            .pixel_format_capabilities(Depth24Unorm_Stencil8)
+            .contains(PixelFormatCapabilities::Msaa));
+
+        let mut profile = m1.clone();
+        profile.supports_bc_texture_compression = false;
+        profile.supports_32bit_float_filtering = false;
+        profile.supports_32bit_msaa = false;
+        assert!(profile.pixel_format_capabilities(BC3_RGBA_sRGB).is_empty());
+        let capabilities = profile.pixel_format_capabilities(RG32Float);
+        assert!(!capabilities.intersects(
+            PixelFormatCapabilities::Filter
+                | PixelFormatCapabilities::Msaa
+                | PixelFormatCapabilities::Resolve
+        ));
+        assert!(capabilities.contains(PixelFormatCapabilities::Color));
+        assert_eq!(
+            profile.pixel_format_capabilities(RGBA8Unorm),
+            m1.pixel_format_capabilities(RGBA8Unorm)
+        );
+    }
+
+    #[test]
+    fn profile_validate() {
+        use MTLPixelFormat::*;
+
+        let target = MTLTextureUsage::RenderTarget;
+        let m1 = DeviceProfile::preset("M1").unwrap();
+        let radeon = DeviceProfile::preset("Radeon Pro 5500M").unwrap();
+        assert_eq!(
+            render_target(Depth24Unorm_Stencil8, 1).validate(target, &m1),
+            Err(TextureValidationError::UnsupportedPixelFormat {
+                pixel_format: Depth24Unorm_Stencil8
+            })
+        );
+        assert_eq!(
+            render_target(Depth24Unorm_Stencil8, 4).validate(target, &radeon),
+            Ok(())
+        );
+        assert_eq!(render_target(R32Float, 4).validate(target, &m1), Ok(()));
+
+        let mut profile = m1.clone();
+        profile.supports_32bit_msaa = false;
+        assert_eq!(
+            render_target(R32Float, 4).validate(target, &profile),
+            Err(TextureValidationError::MsaaNotSupported {
+                pixel_format: R32Float
+            })
+        );
+
+        // Sample counts come from the profile, not from its feature sets.
+        let a14 = DeviceProfile::preset("A14").unwrap();
+        assert_eq!(
+            render_target(RGBA8Unorm, 8).validate(target, &a14),
+            Err(TextureValidationError::InvalidSampleCount { count: 8 })
+        );
+        assert_eq!(render_target(RGBA8Unorm, 8).validate(target, &m1), Ok(()));
+
+        let texture = TextureLayout::new(MTLTextureType::D2, RGBA8Unorm, MTLSize::new(16385, 1, 1));
+        assert_eq!(
+            texture.validate(MTLTextureUsage::ShaderRead, &a14),
+            Err(TextureValidationError::SizeExceedsLimit {
+                size: MTLSize::new(16385, 1, 1),
+                limit: 16384
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn profile_serde_round_trip() {
+        for &name in DeviceProfile::PRESETS {
+            let profile = DeviceProfile::preset(name).unwrap();
+            let json = serde_json::to_string(&profile).unwrap();
+            let loaded: DeviceProfile = serde_json::from_str(&json).unwrap();
+            assert_eq!(loaded, profile);
+        }
+    }
+}
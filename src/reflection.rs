@@ -0,0 +1,1164 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Plain Rust copies of pipeline reflection, so argument layouts can be stored with compiled
+//! shaders and inspected without a device. Each type has a `capture` constructor that reads
+//! the corresponding live object.
+
+use super::*;
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use objc::runtime::{BOOL, NO, YES};
+
+use std::{error::Error, fmt};
+
+/// A captured `RenderPipelineReflectionRef`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RenderPipelineReflectionInfo {
+    pub vertex_arguments: Vec<ArgumentInfo>,
+    pub fragment_arguments: Vec<ArgumentInfo>,
+    /// Empty unless the pipeline has a tile function.
+    pub tile_arguments: Vec<ArgumentInfo>,
+}
+
+impl RenderPipelineReflectionInfo {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn capture(reflection: &RenderPipelineReflectionRef) -> Self {
+        unsafe {
+            let vertex_arguments: Option<&ArgumentArrayRef> =
+                msg_send![reflection, vertexArguments];
+            let fragment_arguments: Option<&ArgumentArrayRef> =
+                msg_send![reflection, fragmentArguments];
+            // `tileArguments` is missing before macOS 11.
+            let has_tile_arguments: BOOL =
+                msg_send![reflection, respondsToSelector: sel!(tileArguments)];
+            let tile_arguments: Option<&ArgumentArrayRef> = match has_tile_arguments {
+                YES => msg_send![reflection, tileArguments],
+                NO => None,
+                _ => unreachable!(),
+            };
+
+            RenderPipelineReflectionInfo {
+                vertex_arguments: ArgumentInfo::capture_all(vertex_arguments),
+                fragment_arguments: ArgumentInfo::capture_all(fragment_arguments),
+                tile_arguments: ArgumentInfo::capture_all(tile_arguments),
+            }
+        }
+    }
+}
+
+/// A captured `ComputePipelineReflectionRef`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComputePipelineReflectionInfo {
+    pub arguments: Vec<ArgumentInfo>,
+}
+
+impl ComputePipelineReflectionInfo {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn capture(reflection: &ComputePipelineReflectionRef) -> Self {
+        let arguments: Option<&ArgumentArrayRef> = unsafe { msg_send![reflection, arguments] };
+        ComputePipelineReflectionInfo {
+            arguments: ArgumentInfo::capture_all(arguments),
+        }
+    }
+}
+
+/// A captured `ArgumentRef`. Only the section matching `argument_type` is set.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArgumentInfo {
+    pub name: String,
+    pub argument_type: MTLArgumentType,
+    pub access: MTLArgumentAccess,
+    pub index: NSUInteger,
+    pub is_active: bool,
+    pub buffer: Option<BufferInfo>,
+    pub threadgroup_memory: Option<ThreadgroupMemoryInfo>,
+    pub texture: Option<TextureInfo>,
+}
+
+impl ArgumentInfo {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn capture(argument: &ArgumentRef) -> Self {
+        let argument_type = argument.type_();
+        let buffer = match argument_type {
+            MTLArgumentType::Buffer => {
+                let data_type = argument.buffer_data_type();
+                Some(BufferInfo {
+                    alignment: argument.buffer_alignment(),
+                    data_size: argument.buffer_data_size(),
+                    data_type,
+                    struct_type: match data_type {
+                        MTLDataType::Struct => {
+                            Some(StructInfo::capture(argument.buffer_struct_type()))
+                        }
+                        _ => None,
+                    },
+                })
+            }
+            _ => None,
+        };
+        let threadgroup_memory = match argument_type {
+            MTLArgumentType::ThreadgroupMemory => Some(ThreadgroupMemoryInfo {
+                alignment: argument.threadgroup_memory_alignment(),
+                data_size: argument.threadgroup_memory_data_size(),
+            }),
+            _ => None,
+        };
+        let texture = match argument_type {
+            MTLArgumentType::Texture => Some(TextureInfo {
+                texture_type: argument.texture_type(),
+                data_type: argument.texture_data_type(),
+            }),
+            _ => None,
+        };
+
+        ArgumentInfo {
+            name: argument.name().to_owned(),
+            argument_type,
+            access: argument.access(),
+            index: argument.index(),
+            is_active: argument.is_active(),
+            buffer,
+            threadgroup_memory,
+            texture,
+        }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    fn capture_all(arguments: Option<&ArgumentArrayRef>) -> Vec<Self> {
+        let arguments = match arguments {
+            Some(arguments) => arguments,
+            None => return Vec::new(),
+        };
+        (0..arguments.count())
+            .filter_map(|index| arguments.object_at(index))
+            .map(ArgumentInfo::capture)
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BufferInfo {
+    pub alignment: NSUInteger,
+    pub data_size: NSUInteger,
+    pub data_type: MTLDataType,
+    /// Set when `data_type` is `MTLDataType::Struct`.
+    pub struct_type: Option<StructInfo>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThreadgroupMemoryInfo {
+    pub alignment: NSUInteger,
+    pub data_size: NSUInteger,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureInfo {
+    pub texture_type: MTLTextureType,
+    pub data_type: MTLDataType,
+}
+
+/// A captured `StructTypeRef`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructInfo {
+    pub members: Vec<MemberInfo>,
+}
+
+impl StructInfo {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn capture(struct_type: &StructTypeRef) -> Self {
+        let members = struct_type.members();
+        StructInfo {
+            members: (0..members.count())
+                .filter_map(|index| members.object_at(index))
+                .map(MemberInfo::capture)
+                .collect(),
+        }
+    }
+
+    pub fn member(&self, name: &str) -> Option<&MemberInfo> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+/// A captured `StructMemberRef`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemberInfo {
+    pub name: String,
+    pub offset: NSUInteger,
+    pub data_type: MTLDataType,
+    /// Set when `data_type` is `MTLDataType::Struct`.
+    pub struct_type: Option<StructInfo>,
+    /// Set when `data_type` is `MTLDataType::Array`.
+    pub array_type: Option<ArrayInfo>,
+}
+
+impl MemberInfo {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn capture(member: &StructMemberRef) -> Self {
+        MemberInfo {
+            name: member.name().to_owned(),
+            offset: member.offset(),
+            data_type: member.data_type(),
+            struct_type: member.struct_type().map(StructInfo::capture),
+            array_type: member.array_type().map(ArrayInfo::capture),
+        }
+    }
+}
+
+/// A captured `ArrayTypeRef`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArrayInfo {
+    pub array_length: NSUInteger,
+    pub stride: NSUInteger,
+    pub element_type: MTLDataType,
+    /// Set when `element_type` is `MTLDataType::Struct`.
+    pub element_struct_type: Option<StructInfo>,
+    /// Set when `element_type` is `MTLDataType::Array`.
+    pub element_array_type: Option<Box<ArrayInfo>>,
+}
+
+impl ArrayInfo {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn capture(array_type: &ArrayTypeRef) -> Self {
+        ArrayInfo {
+            array_length: array_type.array_length(),
+            stride: array_type.stride(),
+            element_type: array_type.element_type(),
+            element_struct_type: array_type.element_struct_type().map(StructInfo::capture),
+            element_array_type: array_type
+                .element_array_type()
+                .map(|element| Box::new(ArrayInfo::capture(element))),
+        }
+    }
+}
+
+/// A field of a Rust struct together with the MSL data type it mirrors.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MslMember {
+    pub name: &'static str,
+    pub offset: NSUInteger,
+    pub size: NSUInteger,
+    pub data_type: MTLDataType,
+    /// The fields of the nested struct when `data_type` is `MTLDataType::Struct`.
+    pub struct_members: Option<Vec<MslMember>>,
+    /// Set when `data_type` is `MTLDataType::Array`.
+    pub array: Option<MslArray>,
+}
+
+/// A Rust array field together with the MSL element type it mirrors.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MslArray {
+    pub length: NSUInteger,
+    pub stride: NSUInteger,
+    pub element_type: MTLDataType,
+    /// Set when `element_type` is `MTLDataType::Struct`.
+    pub element_struct_members: Option<Vec<MslMember>>,
+    /// Set when `element_type` is `MTLDataType::Array`.
+    pub element_array: Option<Box<MslArray>>,
+}
+
+/// Length and element type of a Rust array, used by `msl_struct!` to describe array fields.
+#[doc(hidden)]
+pub trait MslArrayType {
+    type Element;
+    const LENGTH: usize;
+}
+
+impl<T, const N: usize> MslArrayType for [T; N] {
+    type Element = T;
+    const LENGTH: usize = N;
+}
+
+/// A `#[repr(C)]` struct shared with MSL, usually implemented with `msl_struct!`.
+pub trait MslStruct: Sized {
+    /// The fields of `Self` in declaration order.
+    fn msl_members() -> Vec<MslMember>;
+
+    /// Compares the fields of `Self` with the members of the MSL struct, in order.
+    fn verify_layout(msl: &StructInfo) -> Result<(), LayoutMismatch> {
+        let differences = compare_members(&Self::msl_members(), msl, None);
+        LayoutMismatch::check(std::any::type_name::<Self>(), differences)
+    }
+
+    /// Compares `Self` with a live `ArgumentRef::buffer_struct_type`.
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    fn verify_struct_type(msl: &StructTypeRef) -> Result<(), LayoutMismatch> {
+        Self::verify_layout(&StructInfo::capture(msl))
+    }
+
+    /// Compares `Self` with the struct a buffer argument points to, including its size.
+    fn verify_buffer_argument(argument: &ArgumentInfo) -> Result<(), LayoutMismatch> {
+        let type_name = std::any::type_name::<Self>();
+        let buffer = argument.buffer.as_ref();
+        let msl = match buffer.and_then(|buffer| buffer.struct_type.as_ref()) {
+            Some(msl) => msl,
+            None => {
+                let difference = LayoutDifference::NotAStruct {
+                    argument: argument.name.clone(),
+                };
+                return LayoutMismatch::check(type_name, vec![difference]);
+            }
+        };
+        let size = buffer.map(|buffer| buffer.data_size);
+        let mut differences = compare_members(&Self::msl_members(), msl, size);
+        let rust_size = std::mem::size_of::<Self>() as NSUInteger;
+        match size {
+            Some(msl_size) if msl_size != rust_size => {
+                differences.push(LayoutDifference::StructSize {
+                    rust: rust_size,
+                    msl: msl_size,
+                });
+            }
+            _ => {}
+        }
+        LayoutMismatch::check(type_name, differences)
+    }
+}
+
+/// One disagreement between a Rust struct and its MSL counterpart.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum LayoutDifference {
+    MemberCount {
+        rust: usize,
+        msl: usize,
+    },
+    Offset {
+        index: usize,
+        rust_name: &'static str,
+        msl_name: String,
+        rust: NSUInteger,
+        msl: NSUInteger,
+    },
+    DataType {
+        index: usize,
+        rust_name: &'static str,
+        msl_name: String,
+        rust: MTLDataType,
+        msl: MTLDataType,
+    },
+    /// A field whose size differs from its MSL type and shifts whatever follows it, typically
+    /// a `[f32; 3]` standing in for a 16-byte `float3`.
+    MemberSize {
+        index: usize,
+        rust_name: &'static str,
+        data_type: MTLDataType,
+        rust: NSUInteger,
+        msl: NSUInteger,
+    },
+    /// Differences inside a struct or array member, with offsets relative to the member.
+    Nested {
+        index: usize,
+        rust_name: &'static str,
+        differences: Vec<LayoutDifference>,
+    },
+    ArrayLength {
+        rust: NSUInteger,
+        msl: NSUInteger,
+    },
+    ArrayStride {
+        rust: NSUInteger,
+        msl: NSUInteger,
+    },
+    ElementType {
+        rust: MTLDataType,
+        msl: MTLDataType,
+    },
+    /// Differences inside the struct or array each element of an array is made of.
+    Elements {
+        differences: Vec<LayoutDifference>,
+    },
+    StructSize {
+        rust: NSUInteger,
+        msl: NSUInteger,
+    },
+    /// The buffer argument does not point to a struct.
+    NotAStruct {
+        argument: String,
+    },
+}
+
+impl fmt::Display for LayoutDifference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutDifference::MemberCount { rust, msl } => {
+                write!(f, "{} fields in Rust but {} members in MSL", rust, msl)
+            }
+            LayoutDifference::Offset {
+                index,
+                rust_name,
+                msl_name,
+                rust,
+                msl,
+            } => write!(
+                f,
+                "member {} `{}` is at offset {} in Rust but `{}` is at offset {} in MSL",
+                index, rust_name, rust, msl_name, msl
+            ),
+            LayoutDifference::DataType {
+                index,
+                rust_name,
+                msl_name,
+                rust,
+                msl,
+            } => write!(
+                f,
+                "member {} `{}` is declared {:?} in Rust but `{}` is {:?} in MSL",
+                index, rust_name, rust, msl_name, msl
+            ),
+            LayoutDifference::MemberSize {
+                index,
+                rust_name,
+                data_type,
+                rust,
+                msl,
+            } => write!(
+                f,
+                "member {} `{}` is {} bytes in Rust but {:?} takes {} bytes in MSL",
+                index, rust_name, rust, data_type, msl
+            ),
+            LayoutDifference::Nested {
+                index,
+                rust_name,
+                differences,
+            } => {
+                write!(f, "member {} `{}`:", index, rust_name)?;
+                write_nested(f, differences)
+            }
+            LayoutDifference::ArrayLength { rust, msl } => {
+                write!(f, "array length is {} in Rust but {} in MSL", rust, msl)
+            }
+            LayoutDifference::ArrayStride { rust, msl } => {
+                write!(
+                    f,
+                    "array stride is {} bytes in Rust but {} bytes in MSL",
+                    rust, msl
+                )
+            }
+            LayoutDifference::ElementType { rust, msl } => {
+                write!(
+                    f,
+                    "elements are declared {:?} in Rust but are {:?} in MSL",
+                    rust, msl
+                )
+            }
+            LayoutDifference::Elements { differences } => {
+                write!(f, "in every element:")?;
+                write_nested(f, differences)
+            }
+            LayoutDifference::StructSize { rust, msl } => {
+                write!(f, "size is {} bytes in Rust but {} bytes in MSL", rust, msl)
+            }
+            LayoutDifference::NotAStruct { argument } => {
+                write!(f, "argument `{}` is not a struct buffer", argument)
+            }
+        }
+    }
+}
+
+/// Writes nested differences one per line, indented below their parent.
+fn write_nested(f: &mut fmt::Formatter, differences: &[LayoutDifference]) -> fmt::Result {
+    for difference in differences {
+        write!(f, "\n  {}", difference.to_string().replace('\n', "\n  "))?;
+    }
+    Ok(())
+}
+
+/// The differences found by `MslStruct::verify_layout`, printed one per line.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct LayoutMismatch {
+    pub type_name: &'static str,
+    pub differences: Vec<LayoutDifference>,
+}
+
+impl LayoutMismatch {
+    fn check(
+        type_name: &'static str,
+        differences: Vec<LayoutDifference>,
+    ) -> Result<(), LayoutMismatch> {
+        if differences.is_empty() {
+            Ok(())
+        } else {
+            Err(LayoutMismatch {
+                type_name,
+                differences,
+            })
+        }
+    }
+}
+
+impl fmt::Display for LayoutMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "layout of `{}` does not match MSL:", self.type_name)?;
+        write_nested(f, &self.differences)
+    }
+}
+
+impl Error for LayoutMismatch {}
+
+fn compare_members(
+    rust: &[MslMember],
+    msl: &StructInfo,
+    msl_size: Option<NSUInteger>,
+) -> Vec<LayoutDifference> {
+    let mut differences = Vec::new();
+    if rust.len() != msl.members.len() {
+        differences.push(LayoutDifference::MemberCount {
+            rust: rust.len(),
+            msl: msl.members.len(),
+        });
+    }
+
+    let pairs: Vec<_> = rust.iter().zip(&msl.members).collect();
+    for (index, &(field, member)) in pairs.iter().enumerate() {
+        if field.data_type != member.data_type {
+            differences.push(LayoutDifference::DataType {
+                index,
+                rust_name: field.name,
+                msl_name: member.name.clone(),
+                rust: field.data_type,
+                msl: member.data_type,
+            });
+        }
+        if field.offset != member.offset {
+            differences.push(LayoutDifference::Offset {
+                index,
+                rust_name: field.name,
+                msl_name: member.name.clone(),
+                rust: field.offset,
+                msl: member.offset,
+            });
+        }
+
+        // Point at the field that caused the next member, or the struct end, to move.
+        let next_moved = match pairs.get(index + 1) {
+            Some(&(next_field, next_member)) => next_field.offset != next_member.offset,
+            None => msl_size.is_some_and(|size| {
+                field.offset + field.size < size && member.offset == field.offset
+            }),
+        };
+        match member.data_type.size() {
+            Some(size)
+                if next_moved && field.data_type == member.data_type && size != field.size =>
+            {
+                differences.push(LayoutDifference::MemberSize {
+                    index,
+                    rust_name: field.name,
+                    data_type: member.data_type,
+                    rust: field.size,
+                    msl: size,
+                });
+            }
+            _ => {}
+        }
+
+        let mut nested = Vec::new();
+        if let (Some(fields), Some(members)) = (&field.struct_members, &member.struct_type) {
+            nested.extend(compare_members(fields, members, None));
+        }
+        if let (Some(array), Some(array_type)) = (&field.array, &member.array_type) {
+            nested.extend(compare_arrays(array, array_type));
+        }
+        if !nested.is_empty() {
+            differences.push(LayoutDifference::Nested {
+                index,
+                rust_name: field.name,
+                differences: nested,
+            });
+        }
+    }
+    differences
+}
+
+fn compare_arrays(rust: &MslArray, msl: &ArrayInfo) -> Vec<LayoutDifference> {
+    let mut differences = Vec::new();
+    if rust.length != msl.array_length {
+        differences.push(LayoutDifference::ArrayLength {
+            rust: rust.length,
+            msl: msl.array_length,
+        });
+    }
+    if rust.element_type != msl.element_type {
+        differences.push(LayoutDifference::ElementType {
+            rust: rust.element_type,
+            msl: msl.element_type,
+        });
+    }
+    if rust.stride != msl.stride {
+        differences.push(LayoutDifference::ArrayStride {
+            rust: rust.stride,
+            msl: msl.stride,
+        });
+    }
+
+    // The stride bounds each element, so a short last field shows up like a short struct.
+    let mut elements = Vec::new();
+    if let (Some(fields), Some(members)) = (&rust.element_struct_members, &msl.element_struct_type)
+    {
+        elements.extend(compare_members(fields, members, Some(msl.stride)));
+    }
+    if let (Some(array), Some(array_type)) = (&rust.element_array, &msl.element_array_type) {
+        elements.extend(compare_arrays(array, array_type));
+    }
+    if !elements.is_empty() {
+        differences.push(LayoutDifference::Elements {
+            differences: elements,
+        });
+    }
+    differences
+}
+
+/// Declares a `#[repr(C)]` struct shared with MSL and implements `MslStruct` for it.
+///
+/// Every field is annotated with the `MTLDataType` variant of the corresponding MSL member.
+/// A `Struct` field must itself implement `MslStruct`, and an array field is annotated with
+/// its element type, as in `#[msl(Array(Struct))]` or `#[msl(Array(Array(Float2)))]`, so
+/// nested members are verified too. Offsets and sizes are taken from the Rust layout, so a
+/// verification against reflection reports exactly where the two layouts diverge.
+///
+/// ```
+/// use metal::{msl_struct, MslStruct, MTLDataType, MemberInfo, StructInfo};
+///
+/// msl_struct! {
+///     pub struct Light {
+///         #[msl(Float3)]
+///         pub direction: [f32; 3],
+///         #[msl(Float)]
+///         pub intensity: f32,
+///     }
+/// }
+///
+/// // struct Light { float3 direction; float intensity; };
+/// let member = |name: &str, offset, data_type| MemberInfo {
+///     name: name.to_owned(),
+///     offset,
+///     data_type,
+///     struct_type: None,
+///     array_type: None,
+/// };
+/// let msl = StructInfo {
+///     members: vec![
+///         member("direction", 0, MTLDataType::Float3),
+///         member("intensity", 16, MTLDataType::Float),
+///     ],
+/// };
+/// let mismatch = Light::verify_layout(&msl).unwrap_err();
+/// assert_eq!(mismatch.differences.len(), 2);
+/// ```
+#[macro_export]
+macro_rules! msl_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                #[msl($($data_type:tt)+)]
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident : $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr(C)]
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl $crate::MslStruct for $name {
+            fn msl_members() -> ::std::vec::Vec<$crate::MslMember> {
+                ::std::vec![
+                    $({
+                        let (data_type, struct_members, array) =
+                            $crate::__msl_type!($ty; $($data_type)+);
+                        $crate::MslMember {
+                            name: ::std::stringify!($field),
+                            offset: $crate::__field_offset!($name, $field),
+                            size: ::std::mem::size_of::<$ty>() as $crate::NSUInteger,
+                            data_type,
+                            struct_members,
+                            array,
+                        }
+                    },)*
+                ]
+            }
+        }
+    };
+}
+
+/// The data type, nested struct members and array layout of a field declared by `msl_struct!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __msl_type {
+    ($ty:ty; Struct) => {
+        (
+            $crate::MTLDataType::Struct,
+            ::std::option::Option::Some(<$ty as $crate::MslStruct>::msl_members()),
+            ::std::option::Option::None,
+        )
+    };
+    ($ty:ty; Array($($element:tt)+)) => {{
+        let (element_type, element_struct_members, element_array) = $crate::__msl_type!(
+            <$ty as $crate::MslArrayType>::Element; $($element)+
+        );
+        let array = $crate::MslArray {
+            length: <$ty as $crate::MslArrayType>::LENGTH as $crate::NSUInteger,
+            stride: ::std::mem::size_of::<<$ty as $crate::MslArrayType>::Element>()
+                as $crate::NSUInteger,
+            element_type,
+            element_struct_members,
+            element_array: element_array.map(::std::boxed::Box::new),
+        };
+        (
+            $crate::MTLDataType::Array,
+            ::std::option::Option::None,
+            ::std::option::Option::Some(array),
+        )
+    }};
+    ($ty:ty; $data_type:ident) => {
+        (
+            $crate::MTLDataType::$data_type,
+            ::std::option::Option::None,
+            ::std::option::Option::None,
+        )
+    };
+}
+
+/// The byte offset of `$field` within `$type`, used by `msl_struct!` and `metal_vertex!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __field_offset {
+    ($type:ty, $field:ident) => {{
+        let value = ::std::mem::MaybeUninit::<$type>::uninit();
+        let base = value.as_ptr();
+        // Only the field address is computed; the uninitialized value is never read.
+        let offset = unsafe {
+            (::std::ptr::addr_of!((*base).$field) as *const u8).offset_from(base as *const u8)
+        };
+        offset as $crate::NSUInteger
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(name: &str, offset: NSUInteger, data_type: MTLDataType) -> MemberInfo {
+        MemberInfo {
+            name: name.to_owned(),
+            offset,
+            data_type,
+            struct_type: None,
+            array_type: None,
+        }
+    }
+
+    // struct Light { float3 direction; float intensity; };
+    fn light() -> StructInfo {
+        StructInfo {
+            members: vec![
+                member("direction", 0, MTLDataType::Float3),
+                member("intensity", 16, MTLDataType::Float),
+            ],
+        }
+    }
+
+    // struct Scene {
+    //     Light sun;
+    //     Light lights[4];
+    //     float2 samples[2][3];
+    // };
+    fn scene() -> StructInfo {
+        StructInfo {
+            members: vec![
+                MemberInfo {
+                    struct_type: Some(light()),
+                    ..member("sun", 0, MTLDataType::Struct)
+                },
+                MemberInfo {
+                    array_type: Some(ArrayInfo {
+                        array_length: 4,
+                        stride: 32,
+                        element_type: MTLDataType::Struct,
+                        element_struct_type: Some(light()),
+                        element_array_type: None,
+                    }),
+                    ..member("lights", 32, MTLDataType::Array)
+                },
+                MemberInfo {
+                    array_type: Some(ArrayInfo {
+                        array_length: 2,
+                        stride: 24,
+                        element_type: MTLDataType::Array,
+                        element_struct_type: None,
+                        element_array_type: Some(Box::new(ArrayInfo {
+                            array_length: 3,
+                            stride: 8,
+                            element_type: MTLDataType::Float2,
+                            element_struct_type: None,
+                            element_array_type: None,
+                        })),
+                    }),
+                    ..member("samples", 160, MTLDataType::Array)
+                },
+            ],
+        }
+    }
+
+    fn reflection() -> RenderPipelineReflectionInfo {
+        RenderPipelineReflectionInfo {
+            vertex_arguments: vec![ArgumentInfo {
+                name: "scene".to_owned(),
+                argument_type: MTLArgumentType::Buffer,
+                access: MTLArgumentAccess::ReadOnly,
+                index: 1,
+                is_active: true,
+                buffer: Some(BufferInfo {
+                    alignment: 16,
+                    data_size: 208,
+                    data_type: MTLDataType::Struct,
+                    struct_type: Some(scene()),
+                }),
+                threadgroup_memory: None,
+                texture: None,
+            }],
+            fragment_arguments: vec![ArgumentInfo {
+                name: "albedo".to_owned(),
+                argument_type: MTLArgumentType::Texture,
+                access: MTLArgumentAccess::ReadOnly,
+                index: 0,
+                is_active: false,
+                buffer: None,
+                threadgroup_memory: None,
+                texture: Some(TextureInfo {
+                    texture_type: MTLTextureType::D2,
+                    data_type: MTLDataType::Half,
+                }),
+            }],
+            tile_arguments: vec![ArgumentInfo {
+                name: "scratch".to_owned(),
+                argument_type: MTLArgumentType::ThreadgroupMemory,
+                access: MTLArgumentAccess::ReadWrite,
+                index: 0,
+                is_active: true,
+                buffer: None,
+                threadgroup_memory: Some(ThreadgroupMemoryInfo {
+                    alignment: 16,
+                    data_size: 1024,
+                }),
+                texture: None,
+            }],
+        }
+    }
+
+    msl_struct! {
+        #[allow(dead_code)]
+        #[repr(align(16))]
+        struct Light {
+            #[msl(Float3)]
+            direction: [f32; 4],
+            #[msl(Float)]
+            intensity: f32,
+        }
+    }
+
+    msl_struct! {
+        #[allow(dead_code)]
+        struct Scene {
+            #[msl(Struct)]
+            sun: Light,
+            #[msl(Array(Struct))]
+            lights: [Light; 4],
+            #[msl(Array(Array(Float2)))]
+            samples: [[[f32; 2]; 3]; 2],
+        }
+    }
+
+    // A `[f32; 3]` in place of a `float3`, the usual mistake.
+    msl_struct! {
+        #[allow(dead_code)]
+        struct PackedLight {
+            #[msl(Float3)]
+            direction: [f32; 3],
+            #[msl(Float)]
+            intensity: f32,
+        }
+    }
+
+    msl_struct! {
+        #[allow(dead_code)]
+        struct PackedScene {
+            #[msl(Struct)]
+            sun: PackedLight,
+            #[msl(Array(Struct))]
+            lights: [PackedLight; 4],
+            #[msl(Array(Array(Float3)))]
+            samples: [[[f32; 2]; 3]; 3],
+        }
+    }
+
+    fn packed_light_differences() -> Vec<LayoutDifference> {
+        vec![
+            LayoutDifference::MemberSize {
+                index: 0,
+                rust_name: "direction",
+                data_type: MTLDataType::Float3,
+                rust: 12,
+                msl: 16,
+            },
+            LayoutDifference::Offset {
+                index: 1,
+                rust_name: "intensity",
+                msl_name: "intensity".to_owned(),
+                rust: 12,
+                msl: 16,
+            },
+        ]
+    }
+
+    #[test]
+    fn nested_layouts_match() {
+        assert_eq!(Light::verify_layout(&light()), Ok(()));
+        assert_eq!(Scene::verify_layout(&scene()), Ok(()));
+        let reflection = reflection();
+        assert_eq!(
+            Scene::verify_buffer_argument(&reflection.vertex_arguments[0]),
+            Ok(())
+        );
+
+        let members = Scene::msl_members();
+        assert_eq!(members[0].struct_members, Some(Light::msl_members()));
+        let samples = members[2].array.as_ref().unwrap();
+        assert_eq!((samples.length, samples.stride), (2, 24));
+        let row = samples.element_array.as_ref().unwrap();
+        assert_eq!((row.length, row.stride), (3, 8));
+        assert_eq!(row.element_type, MTLDataType::Float2);
+    }
+
+    #[test]
+    fn nested_mismatches() {
+        let mismatch = PackedScene::verify_layout(&scene()).unwrap_err();
+        assert_eq!(
+            mismatch.differences,
+            vec![
+                LayoutDifference::Nested {
+                    index: 0,
+                    rust_name: "sun",
+                    differences: packed_light_differences(),
+                },
+                LayoutDifference::Offset {
+                    index: 1,
+                    rust_name: "lights",
+                    msl_name: "lights".to_owned(),
+                    rust: 16,
+                    msl: 32,
+                },
+                LayoutDifference::Nested {
+                    index: 1,
+                    rust_name: "lights",
+                    differences: vec![
+                        LayoutDifference::ArrayStride { rust: 16, msl: 32 },
+                        LayoutDifference::Elements {
+                            differences: packed_light_differences(),
+                        },
+                    ],
+                },
+                LayoutDifference::Offset {
+                    index: 2,
+                    rust_name: "samples",
+                    msl_name: "samples".to_owned(),
+                    rust: 80,
+                    msl: 160,
+                },
+                LayoutDifference::Nested {
+                    index: 2,
+                    rust_name: "samples",
+                    differences: vec![
+                        LayoutDifference::ArrayLength { rust: 3, msl: 2 },
+                        LayoutDifference::Elements {
+                            differences: vec![LayoutDifference::ElementType {
+                                rust: MTLDataType::Float3,
+                                msl: MTLDataType::Float2,
+                            }],
+                        },
+                    ],
+                },
+            ]
+        );
+
+        let message = mismatch.to_string();
+        assert!(message.contains(
+            "\n  member 0 `sun`:\n    member 0 `direction` is 12 bytes in Rust but Float3 takes \
+             16 bytes in MSL\n    member 1 `intensity`"
+        ));
+        assert!(message.contains(
+            "\n  member 1 `lights`:\n    array stride is 16 bytes in Rust but 32 bytes in MSL\n    \
+             in every element:\n      member 0 `direction`"
+        ));
+    }
+
+    #[test]
+    fn member_count() {
+        let direction_only = StructInfo {
+            members: vec![member("direction", 0, MTLDataType::Float3)],
+        };
+        let mismatch = Light::verify_layout(&direction_only).unwrap_err();
+        assert_eq!(
+            mismatch.differences,
+            vec![LayoutDifference::MemberCount { rust: 2, msl: 1 }]
+        );
+
+        let mut three = light();
+        three.members.push(member("range", 20, MTLDataType::Float));
+        let mismatch = Light::verify_layout(&three).unwrap_err();
+        assert_eq!(
+            mismatch.differences,
+            vec![LayoutDifference::MemberCount { rust: 2, msl: 3 }]
+        );
+    }
+
+    #[test]
+    fn verify_buffer_argument_checks_struct_size() {
+        let mut argument = reflection().vertex_arguments.remove(0);
+        argument.buffer.as_mut().unwrap().data_size = 224;
+        let mismatch = Scene::verify_buffer_argument(&argument).unwrap_err();
+        assert_eq!(
+            mismatch.differences,
+            vec![LayoutDifference::StructSize {
+                rust: 208,
+                msl: 224
+            }]
+        );
+
+        // Member differences are reported along with the size.
+        let argument = ArgumentInfo {
+            name: "light".to_owned(),
+            buffer: Some(BufferInfo {
+                alignment: 16,
+                data_size: 32,
+                data_type: MTLDataType::Struct,
+                struct_type: Some(StructInfo {
+                    members: vec![
+                        member("intensity", 0, MTLDataType::Float),
+                        member("direction", 16, MTLDataType::Float3),
+                    ],
+                }),
+            }),
+            ..argument
+        };
+        msl_struct! {
+            #[allow(dead_code)]
+            struct ShortLight {
+                #[msl(Float)]
+                intensity: f32,
+                #[msl(Float3)]
+                direction: [f32; 3],
+            }
+        }
+        let mismatch = ShortLight::verify_buffer_argument(&argument).unwrap_err();
+        assert_eq!(
+            mismatch.differences,
+            vec![
+                LayoutDifference::Offset {
+                    index: 1,
+                    rust_name: "direction",
+                    msl_name: "direction".to_owned(),
+                    rust: 4,
+                    msl: 16,
+                },
+                LayoutDifference::StructSize { rust: 16, msl: 32 },
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_buffer_argument_requires_a_struct() {
+        let reflection = reflection();
+        let mismatch = Light::verify_buffer_argument(&reflection.fragment_arguments[0]);
+        assert_eq!(
+            mismatch.unwrap_err().differences,
+            vec![LayoutDifference::NotAStruct {
+                argument: "albedo".to_owned()
+            }]
+        );
+
+        let argument = ArgumentInfo {
+            name: "colors".to_owned(),
+            buffer: Some(BufferInfo {
+                alignment: 16,
+                data_size: 16,
+                data_type: MTLDataType::Float4,
+                struct_type: None,
+            }),
+            ..reflection.vertex_arguments[0].clone()
+        };
+        let mismatch = Light::verify_buffer_argument(&argument).unwrap_err();
+        assert_eq!(
+            mismatch.differences,
+            vec![LayoutDifference::NotAStruct {
+                argument: "colors".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn struct_member_lookup() {
+        let scene = scene();
+        assert_eq!(scene.member("lights").map(|m| m.offset), Some(32));
+        assert_eq!(scene.member("light"), None);
+        assert_eq!(scene.member(""), None);
+
+        let sun = scene.member("sun").and_then(|m| m.struct_type.as_ref());
+        let intensity = sun.and_then(|sun| sun.member("intensity"));
+        assert_eq!(intensity.map(|m| m.data_type), Some(MTLDataType::Float));
+
+        // The first of several members with the same name wins.
+        let duplicated = StructInfo {
+            members: vec![
+                member("value", 0, MTLDataType::Int),
+                member("value", 4, MTLDataType::Float),
+            ],
+        };
+        assert_eq!(duplicated.member("value").map(|m| m.offset), Some(0));
+        assert_eq!(StructInfo::default().member("value"), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn reflection_serde_round_trip() {
+        let reflection = reflection();
+        let json = serde_json::to_string(&reflection).unwrap();
+        let decoded: RenderPipelineReflectionInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, reflection);
+
+        let samples = decoded.vertex_arguments[0]
+            .buffer
+            .as_ref()
+            .and_then(|buffer| buffer.struct_type.as_ref())
+            .and_then(|scene| scene.member("samples"))
+            .and_then(|samples| samples.array_type.as_ref());
+        let row = samples.and_then(|samples| samples.element_array_type.as_deref());
+        assert_eq!(row.map(|row| row.stride), Some(8));
+
+        let compute = ComputePipelineReflectionInfo {
+            arguments: reflection.vertex_arguments,
+        };
+        let json = serde_json::to_string(&compute).unwrap();
+        let decoded: ComputePipelineReflectionInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, compute);
+    }
+}
@@ -0,0 +1,750 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use super::*;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt,
+};
+
+/// The kind of encoder a pass records into.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum PassKind {
+    Render,
+    Compute,
+    Blit,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GraphResourceKind {
+    Buffer,
+    Texture,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ResourceId(pub usize);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct PassId(pub usize);
+
+/// One of the fences a `GraphExecutor` creates for a plan.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct FenceId(pub usize);
+
+/// Why a pass must run after another one.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Hazard {
+    ReadAfterWrite,
+    WriteAfterWrite,
+    WriteAfterRead,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Dependency {
+    pub from: PassId,
+    pub to: PassId,
+    pub resource: ResourceId,
+    pub hazard: Hazard,
+}
+
+/// A wait for the event of another queue to reach `value`, relative to the frame's base value.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct EventWait {
+    pub queue: usize,
+    pub value: u64,
+}
+
+/// The synchronization a pass performs around its encoder.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PlannedPass {
+    pub pass: PassId,
+    pub kind: PassKind,
+    pub queue: usize,
+    /// Encoded on the command buffer before the encoder is created.
+    pub wait_events: Vec<EventWait>,
+    pub wait_fences: Vec<FenceId>,
+    pub update_fence: Option<FenceId>,
+    /// The value this pass signals on its queue's event once its encoder ends.
+    pub signal_event: Option<u64>,
+}
+
+/// The result of `RenderGraph::compile`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GraphPlan {
+    /// The passes that contribute to an output, in execution order.
+    pub passes: Vec<PlannedPass>,
+    pub culled: Vec<PassId>,
+    /// The dependencies between the passes that are kept.
+    pub dependencies: Vec<Dependency>,
+    pub fence_count: usize,
+    /// The number of event values each queue signals per frame, indexed by queue.
+    pub signal_counts: Vec<u64>,
+}
+
+impl GraphPlan {
+    /// `None` if the pass was culled.
+    pub fn pass(&self, pass: PassId) -> Option<&PlannedPass> {
+        self.passes.iter().find(|planned| planned.pass == pass)
+    }
+
+    pub fn is_culled(&self, pass: PassId) -> bool {
+        self.culled.contains(&pass)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GraphError {
+    /// A resource that is not imported is read before any pass writes it.
+    ReadBeforeWrite { pass: String, resource: String },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::ReadBeforeWrite { pass, resource } => write!(
+                f,
+                "pass `{}` reads `{}` before any pass writes it",
+                pass, resource
+            ),
+        }
+    }
+}
+
+impl Error for GraphError {}
+
+#[derive(Clone, Debug)]
+struct ResourceNode {
+    name: String,
+    kind: GraphResourceKind,
+    hazard_tracking: MTLHazardTrackingMode,
+    imported: bool,
+    output: bool,
+}
+
+#[derive(Clone, Debug)]
+struct PassNode {
+    name: String,
+    kind: PassKind,
+    queue: usize,
+    reads: Vec<ResourceId>,
+    writes: Vec<ResourceId>,
+    side_effects: bool,
+}
+
+/// A frame's passes and the buffers and textures they read and write.
+///
+/// Passes execute in the order they are added, and a read sees the latest write declared
+/// before it. `compile` culls passes whose results reach neither an output resource nor a
+/// pass with side effects, and plans the synchronization the remaining passes need: fences
+/// between encoders on the same queue for untracked resources, and events between queues for
+/// every resource, as Metal does not track hazards across queues.
+///
+/// ```
+/// use metal::{GraphResourceKind, MTLHazardTrackingMode, PassKind, RenderGraph};
+///
+/// let mut graph = RenderGraph::new();
+/// let untracked = MTLHazardTrackingMode::Untracked;
+/// let particles = graph.add_resource("particles", GraphResourceKind::Buffer, untracked);
+/// let color = graph.add_resource("color", GraphResourceKind::Texture, untracked);
+/// let debug = graph.add_resource("debug", GraphResourceKind::Texture, untracked);
+/// graph.set_output(color);
+///
+/// let simulate = graph.add_pass("simulate", PassKind::Compute);
+/// graph.write(simulate, particles);
+/// let draw = graph.add_pass("draw", PassKind::Render);
+/// graph.read(draw, particles);
+/// graph.write(draw, color);
+/// let visualize = graph.add_pass("visualize", PassKind::Render);
+/// graph.read(visualize, particles);
+/// graph.write(visualize, debug);
+///
+/// let plan = graph.compile().unwrap();
+/// assert_eq!(plan.culled, vec![visualize]);
+/// let fence = plan.pass(simulate).unwrap().update_fence.unwrap();
+/// assert_eq!(plan.pass(draw).unwrap().wait_fences, vec![fence]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RenderGraph {
+    resources: Vec<ResourceNode>,
+    passes: Vec<PassNode>,
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_resource(
+        &mut self,
+        name: &str,
+        kind: GraphResourceKind,
+        hazard_tracking: MTLHazardTrackingMode,
+    ) -> ResourceId {
+        self.resources.push(ResourceNode {
+            name: name.to_owned(),
+            kind,
+            hazard_tracking,
+            imported: false,
+            output: false,
+        });
+        ResourceId(self.resources.len() - 1)
+    }
+
+    /// Marks a resource whose contents come from outside the graph, so it may be read before
+    /// any pass writes it.
+    pub fn set_imported(&mut self, resource: ResourceId) {
+        self.resources[resource.0].imported = true;
+    }
+
+    /// Marks a resource that is used after the graph, e.g. a drawable texture. Its writers
+    /// are never culled.
+    pub fn set_output(&mut self, resource: ResourceId) {
+        self.resources[resource.0].output = true;
+    }
+
+    /// Adds a pass on queue 0.
+    pub fn add_pass(&mut self, name: &str, kind: PassKind) -> PassId {
+        self.passes.push(PassNode {
+            name: name.to_owned(),
+            kind,
+            queue: 0,
+            reads: Vec::new(),
+            writes: Vec::new(),
+            side_effects: false,
+        });
+        PassId(self.passes.len() - 1)
+    }
+
+    /// Moves a pass to another command queue. Queues are numbered by the caller; each one
+    /// must execute its passes in graph order.
+    pub fn set_queue(&mut self, pass: PassId, queue: usize) {
+        self.passes[pass.0].queue = queue;
+    }
+
+    /// Keeps a pass even if nothing reads what it writes, e.g. for readback or timestamps.
+    pub fn set_side_effects(&mut self, pass: PassId) {
+        self.passes[pass.0].side_effects = true;
+    }
+
+    pub fn read(&mut self, pass: PassId, resource: ResourceId) {
+        let reads = &mut self.passes[pass.0].reads;
+        if !reads.contains(&resource) {
+            reads.push(resource);
+        }
+    }
+
+    pub fn write(&mut self, pass: PassId, resource: ResourceId) {
+        let writes = &mut self.passes[pass.0].writes;
+        if !writes.contains(&resource) {
+            writes.push(resource);
+        }
+    }
+
+    pub fn resource_name(&self, resource: ResourceId) -> &str {
+        &self.resources[resource.0].name
+    }
+
+    pub fn resource_kind(&self, resource: ResourceId) -> GraphResourceKind {
+        self.resources[resource.0].kind
+    }
+
+    pub fn pass_name(&self, pass: PassId) -> &str {
+        &self.passes[pass.0].name
+    }
+
+    pub fn compile(&self) -> Result<GraphPlan, GraphError> {
+        let dependencies = self.dependencies()?;
+        let needed = self.needed_passes(&dependencies);
+        let dependencies: Vec<Dependency> = dependencies
+            .into_iter()
+            .filter(|dependency| needed[dependency.from.0] && needed[dependency.to.0])
+            .collect();
+
+        // Every pass signals at most one event value and updates at most one fence, shared
+        // by all the passes that wait for it.
+        let mut signals: BTreeSet<PassId> = BTreeSet::new();
+        let mut fences: BTreeSet<PassId> = BTreeSet::new();
+        for dependency in &dependencies {
+            let (from, to) = (
+                &self.passes[dependency.from.0],
+                &self.passes[dependency.to.0],
+            );
+            if from.queue != to.queue {
+                signals.insert(dependency.from);
+            } else if self.resources[dependency.resource.0].hazard_tracking
+                == MTLHazardTrackingMode::Untracked
+            {
+                fences.insert(dependency.from);
+            }
+        }
+
+        let queue_count = self.passes.iter().map(|pass| pass.queue + 1).max();
+        let mut signal_counts = vec![0; queue_count.unwrap_or(0)];
+        let mut signal_values = BTreeMap::new();
+        for &pass in &signals {
+            let queue = self.passes[pass.0].queue;
+            signal_counts[queue] += 1;
+            signal_values.insert(pass, signal_counts[queue]);
+        }
+        let fence_ids: BTreeMap<PassId, FenceId> = fences
+            .iter()
+            .enumerate()
+            .map(|(index, &pass)| (pass, FenceId(index)))
+            .collect();
+
+        let mut passes = Vec::new();
+        let mut culled = Vec::new();
+        for (index, node) in self.passes.iter().enumerate() {
+            let pass = PassId(index);
+            if !needed[index] {
+                culled.push(pass);
+                continue;
+            }
+            // A queue signals its values in order, so only the latest one needs a wait.
+            let mut wait_events: BTreeMap<usize, u64> = BTreeMap::new();
+            let mut wait_fences = BTreeSet::new();
+            for dependency in dependencies
+                .iter()
+                .filter(|dependency| dependency.to == pass)
+            {
+                let from = dependency.from;
+                if let Some(&value) = signal_values.get(&from) {
+                    let queue = self.passes[from.0].queue;
+                    if queue != node.queue {
+                        let latest = wait_events.entry(queue).or_insert(value);
+                        *latest = (*latest).max(value);
+                        continue;
+                    }
+                }
+                if let Some(&fence) = fence_ids.get(&from) {
+                    if self.passes[from.0].queue == node.queue
+                        && self.resources[dependency.resource.0].hazard_tracking
+                            == MTLHazardTrackingMode::Untracked
+                    {
+                        wait_fences.insert(fence);
+                    }
+                }
+            }
+            passes.push(PlannedPass {
+                pass,
+                kind: node.kind,
+                queue: node.queue,
+                wait_events: wait_events
+                    .into_iter()
+                    .map(|(queue, value)| EventWait { queue, value })
+                    .collect(),
+                wait_fences: wait_fences.into_iter().collect(),
+                update_fence: fence_ids.get(&pass).copied(),
+                signal_event: signal_values.get(&pass).copied(),
+            });
+        }
+
+        Ok(GraphPlan {
+            passes,
+            culled,
+            dependencies,
+            fence_count: fence_ids.len(),
+            signal_counts,
+        })
+    }
+
+    fn dependencies(&self) -> Result<Vec<Dependency>, GraphError> {
+        let mut last_writer: Vec<Option<PassId>> = vec![None; self.resources.len()];
+        let mut readers: Vec<Vec<PassId>> = vec![Vec::new(); self.resources.len()];
+        let mut dependencies = Vec::new();
+
+        for (index, node) in self.passes.iter().enumerate() {
+            let pass = PassId(index);
+            for &resource in &node.reads {
+                match last_writer[resource.0] {
+                    Some(from) => dependencies.push(Dependency {
+                        from,
+                        to: pass,
+                        resource,
+                        hazard: Hazard::ReadAfterWrite,
+                    }),
+                    None if self.resources[resource.0].imported => {}
+                    None => {
+                        return Err(GraphError::ReadBeforeWrite {
+                            pass: node.name.clone(),
+                            resource: self.resources[resource.0].name.clone(),
+                        })
+                    }
+                }
+            }
+            for &resource in &node.writes {
+                if let Some(from) = last_writer[resource.0] {
+                    dependencies.push(Dependency {
+                        from,
+                        to: pass,
+                        resource,
+                        hazard: Hazard::WriteAfterWrite,
+                    });
+                }
+                for &from in readers[resource.0].iter().filter(|&&from| from != pass) {
+                    dependencies.push(Dependency {
+                        from,
+                        to: pass,
+                        resource,
+                        hazard: Hazard::WriteAfterRead,
+                    });
+                }
+            }
+
+            for &resource in &node.writes {
+                last_writer[resource.0] = Some(pass);
+                readers[resource.0].clear();
+            }
+            for &resource in &node.reads {
+                if !node.writes.contains(&resource) {
+                    readers[resource.0].push(pass);
+                }
+            }
+        }
+        Ok(dependencies)
+    }
+
+    /// Passes with side effects, the last writers of outputs, and every pass whose writes
+    /// they read or write over.
+    fn needed_passes(&self, dependencies: &[Dependency]) -> Vec<bool> {
+        let mut needed: Vec<bool> = self.passes.iter().map(|pass| pass.side_effects).collect();
+        for (index, resource) in self.resources.iter().enumerate() {
+            if !resource.output {
+                continue;
+            }
+            let last_writer = self
+                .passes
+                .iter()
+                .rposition(|pass| pass.writes.contains(&ResourceId(index)));
+            if let Some(writer) = last_writer {
+                needed[writer] = true;
+            }
+        }
+
+        // Dependencies always point forward, so one backward sweep reaches every producer.
+        for dependency in dependencies.iter().rev() {
+            if needed[dependency.to.0] && dependency.hazard != Hazard::WriteAfterRead {
+                needed[dependency.from.0] = true;
+            }
+        }
+        needed
+    }
+}
+
+/// Encodes the passes of a `GraphPlan` with the fences and events it calls for.
+///
+/// Each `encode_*` call creates the pass's encoder on the given command buffer, which must
+/// belong to the queue the pass was assigned to, and returns `false` without encoding if the
+/// pass was culled. Event values grow from frame to frame; call `finish_frame` after encoding
+/// all passes of a frame.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub struct GraphExecutor {
+    plan: GraphPlan,
+    fences: Vec<Fence>,
+    events: Vec<Event>,
+    event_bases: Vec<u64>,
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl GraphExecutor {
+    pub fn new(device: &DeviceRef, plan: GraphPlan) -> Self {
+        let fences = (0..plan.fence_count).map(|_| device.new_fence()).collect();
+        let events = plan
+            .signal_counts
+            .iter()
+            .map(|_| device.new_event())
+            .collect();
+        GraphExecutor {
+            event_bases: vec![0; plan.signal_counts.len()],
+            plan,
+            fences,
+            events,
+        }
+    }
+
+    pub fn plan(&self) -> &GraphPlan {
+        &self.plan
+    }
+
+    pub fn encode_render<F>(
+        &self,
+        pass: PassId,
+        command_buffer: &CommandBufferRef,
+        descriptor: &RenderPassDescriptorRef,
+        encode: F,
+    ) -> bool
+    where
+        F: FnOnce(&RenderCommandEncoderRef),
+    {
+        let planned = match self.begin(pass, PassKind::Render, command_buffer) {
+            Some(planned) => planned,
+            None => return false,
+        };
+        let encoder = command_buffer.new_render_command_encoder(descriptor);
+        for fence in &planned.wait_fences {
+            encoder.wait_for_fence(&self.fences[fence.0], MTLRenderStages::Vertex);
+        }
+        encode(encoder);
+        if let Some(fence) = planned.update_fence {
+            encoder.update_fence(
+                &self.fences[fence.0],
+                MTLRenderStages::Vertex | MTLRenderStages::Fragment,
+            );
+        }
+        encoder.end_encoding();
+        self.end(planned, command_buffer);
+        true
+    }
+
+    pub fn encode_compute<F>(
+        &self,
+        pass: PassId,
+        command_buffer: &CommandBufferRef,
+        encode: F,
+    ) -> bool
+    where
+        F: FnOnce(&ComputeCommandEncoderRef),
+    {
+        let planned = match self.begin(pass, PassKind::Compute, command_buffer) {
+            Some(planned) => planned,
+            None => return false,
+        };
+        let encoder = command_buffer.new_compute_command_encoder();
+        for fence in &planned.wait_fences {
+            encoder.wait_for_fence(&self.fences[fence.0]);
+        }
+        encode(encoder);
+        if let Some(fence) = planned.update_fence {
+            encoder.update_fence(&self.fences[fence.0]);
+        }
+        encoder.end_encoding();
+        self.end(planned, command_buffer);
+        true
+    }
+
+    pub fn encode_blit<F>(&self, pass: PassId, command_buffer: &CommandBufferRef, encode: F) -> bool
+    where
+        F: FnOnce(&BlitCommandEncoderRef),
+    {
+        let planned = match self.begin(pass, PassKind::Blit, command_buffer) {
+            Some(planned) => planned,
+            None => return false,
+        };
+        let encoder = command_buffer.new_blit_command_encoder();
+        for fence in &planned.wait_fences {
+            encoder.wait_for_fence(&self.fences[fence.0]);
+        }
+        encode(encoder);
+        if let Some(fence) = planned.update_fence {
+            encoder.update_fence(&self.fences[fence.0]);
+        }
+        encoder.end_encoding();
+        self.end(planned, command_buffer);
+        true
+    }
+
+    /// Advances the event values so the next frame's waits do not see this frame's signals.
+    pub fn finish_frame(&mut self) {
+        for (base, count) in self.event_bases.iter_mut().zip(&self.plan.signal_counts) {
+            *base += count;
+        }
+    }
+
+    fn begin(
+        &self,
+        pass: PassId,
+        kind: PassKind,
+        command_buffer: &CommandBufferRef,
+    ) -> Option<&PlannedPass> {
+        let planned = self.plan.pass(pass)?;
+        assert_eq!(
+            planned.kind, kind,
+            "pass {:?} has another encoder kind",
+            pass
+        );
+        for wait in &planned.wait_events {
+            command_buffer.encode_wait_for_event(
+                &self.events[wait.queue],
+                self.event_bases[wait.queue] + wait.value,
+            );
+        }
+        Some(planned)
+    }
+
+    fn end(&self, planned: &PlannedPass, command_buffer: &CommandBufferRef) {
+        if let Some(value) = planned.signal_event {
+            command_buffer.encode_signal_event(
+                &self.events[planned.queue],
+                self.event_bases[planned.queue] + value,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACKED: MTLHazardTrackingMode = MTLHazardTrackingMode::Tracked;
+    const UNTRACKED: MTLHazardTrackingMode = MTLHazardTrackingMode::Untracked;
+
+    fn buffer(graph: &mut RenderGraph, name: &str, tracking: MTLHazardTrackingMode) -> ResourceId {
+        graph.add_resource(name, GraphResourceKind::Buffer, tracking)
+    }
+
+    fn pass(graph: &mut RenderGraph, name: &str, queue: usize) -> PassId {
+        let pass = graph.add_pass(name, PassKind::Compute);
+        graph.set_queue(pass, queue);
+        pass
+    }
+
+    #[test]
+    fn cross_queue_dependencies_use_events() {
+        let mut graph = RenderGraph::new();
+        let a = buffer(&mut graph, "a", UNTRACKED);
+        let b = buffer(&mut graph, "b", TRACKED);
+        let c = buffer(&mut graph, "c", TRACKED);
+        let d = buffer(&mut graph, "d", TRACKED);
+        let out = buffer(&mut graph, "out", TRACKED);
+        graph.set_output(out);
+
+        let write_a = pass(&mut graph, "write_a", 0);
+        graph.write(write_a, a);
+        let write_b = pass(&mut graph, "write_b", 0);
+        graph.write(write_b, b);
+        let combine = pass(&mut graph, "combine", 1);
+        graph.read(combine, a);
+        graph.read(combine, b);
+        graph.write(combine, c);
+        let other = pass(&mut graph, "other", 2);
+        graph.write(other, d);
+        let resolve = pass(&mut graph, "resolve", 0);
+        graph.read(resolve, c);
+        graph.read(resolve, d);
+        graph.write(resolve, out);
+
+        let plan = graph.compile().unwrap();
+        assert!(plan.culled.is_empty());
+        assert_eq!(plan.signal_counts, [2, 1, 1]);
+        // Queue-crossing dependencies never need fences, even for untracked resources.
+        assert_eq!(plan.fence_count, 0);
+
+        assert_eq!(plan.pass(write_a).unwrap().signal_event, Some(1));
+        assert_eq!(plan.pass(write_b).unwrap().signal_event, Some(2));
+        let combine = plan.pass(combine).unwrap();
+        // Queue 0 signals in order, so waiting for its latest value covers both writers.
+        assert_eq!(combine.wait_events, [EventWait { queue: 0, value: 2 }]);
+        assert_eq!(combine.signal_event, Some(1));
+        assert_eq!(plan.pass(other).unwrap().signal_event, Some(1));
+
+        let resolve = plan.pass(resolve).unwrap();
+        assert_eq!(
+            resolve.wait_events,
+            [
+                EventWait { queue: 1, value: 1 },
+                EventWait { queue: 2, value: 1 },
+            ]
+        );
+        assert_eq!(resolve.signal_event, None);
+        assert!(resolve.wait_fences.is_empty());
+    }
+
+    #[test]
+    fn only_untracked_resources_need_fences() {
+        let mut graph = RenderGraph::new();
+        let tracked = buffer(&mut graph, "tracked", TRACKED);
+        let untracked = buffer(&mut graph, "untracked", UNTRACKED);
+        let out = buffer(&mut graph, "out", TRACKED);
+        graph.set_output(out);
+
+        let write_tracked = pass(&mut graph, "write_tracked", 0);
+        graph.write(write_tracked, tracked);
+        let write_untracked = pass(&mut graph, "write_untracked", 0);
+        graph.write(write_untracked, untracked);
+        let consume = pass(&mut graph, "consume", 0);
+        graph.read(consume, tracked);
+        graph.read(consume, untracked);
+        graph.write(consume, out);
+
+        let plan = graph.compile().unwrap();
+        assert_eq!(plan.fence_count, 1);
+        assert_eq!(plan.dependencies.len(), 2);
+        assert_eq!(plan.pass(write_tracked).unwrap().update_fence, None);
+        let fence = plan.pass(write_untracked).unwrap().update_fence;
+        assert_eq!(fence, Some(FenceId(0)));
+        assert_eq!(plan.pass(consume).unwrap().wait_fences, [FenceId(0)]);
+        assert!(plan
+            .passes
+            .iter()
+            .all(|planned| planned.wait_events.is_empty() && planned.signal_event.is_none()));
+        assert_eq!(plan.signal_counts, [0]);
+    }
+
+    #[test]
+    fn write_after_read_does_not_keep_reader() {
+        let mut graph = RenderGraph::new();
+        let history = buffer(&mut graph, "history", UNTRACKED);
+        let debug = buffer(&mut graph, "debug", UNTRACKED);
+        graph.set_imported(history);
+        graph.set_output(history);
+
+        let visualize = pass(&mut graph, "visualize", 0);
+        graph.read(visualize, history);
+        graph.write(visualize, debug);
+        let update = pass(&mut graph, "update", 0);
+        graph.write(update, history);
+
+        let plan = graph.compile().unwrap();
+        assert_eq!(plan.culled, [visualize]);
+        assert!(plan.dependencies.is_empty());
+        assert!(plan.pass(update).unwrap().wait_fences.is_empty());
+
+        // Once the reader is needed, the writer must wait for it.
+        graph.set_output(debug);
+        let plan = graph.compile().unwrap();
+        assert!(plan.culled.is_empty());
+        assert_eq!(
+            plan.dependencies,
+            [Dependency {
+                from: visualize,
+                to: update,
+                resource: history,
+                hazard: Hazard::WriteAfterRead,
+            }]
+        );
+        let fence = plan.pass(visualize).unwrap().update_fence.unwrap();
+        assert_eq!(plan.pass(update).unwrap().wait_fences, [fence]);
+    }
+
+    #[test]
+    fn read_before_write_is_an_error() {
+        let mut graph = RenderGraph::new();
+        let input = buffer(&mut graph, "input", TRACKED);
+        let out = buffer(&mut graph, "out", TRACKED);
+        graph.set_output(out);
+        let consume = pass(&mut graph, "consume", 0);
+        graph.read(consume, input);
+        graph.write(consume, out);
+        // A later write does not count.
+        let produce = pass(&mut graph, "produce", 0);
+        graph.write(produce, input);
+
+        assert_eq!(
+            graph.compile(),
+            Err(GraphError::ReadBeforeWrite {
+                pass: "consume".to_owned(),
+                resource: "input".to_owned(),
+            })
+        );
+
+        graph.set_imported(input);
+        let plan = graph.compile().unwrap();
+        assert_eq!(plan.culled, [produce]);
+    }
+}
@@ -5,6 +5,7 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use super::*;
 
 #[repr(u64)]
@@ -54,14 +55,17 @@ pub enum MTLMultisampleStencilResolveFilter {
     DepthResolvedSample = 1,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderPassAttachmentDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderPassAttachmentDescriptor;
     pub struct RenderPassAttachmentDescriptor;
     pub struct RenderPassAttachmentDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPassAttachmentDescriptorRef {
     pub fn texture(&self) -> Option<&TextureRef> {
         unsafe { msg_send![self, texture] }
@@ -144,8 +148,10 @@ impl RenderPassAttachmentDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderPassColorAttachmentDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderPassColorAttachmentDescriptor;
     pub struct RenderPassColorAttachmentDescriptor;
@@ -153,6 +159,7 @@ foreign_obj_type! {
     type ParentType = RenderPassAttachmentDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPassColorAttachmentDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -162,6 +169,7 @@ impl RenderPassColorAttachmentDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPassColorAttachmentDescriptorRef {
     pub fn clear_color(&self) -> MTLClearColor {
         unsafe { msg_send![self, clearColor] }
@@ -172,8 +180,10 @@ impl RenderPassColorAttachmentDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderPassDepthAttachmentDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderPassDepthAttachmentDescriptor;
     pub struct RenderPassDepthAttachmentDescriptor;
@@ -181,6 +191,7 @@ foreign_obj_type! {
     type ParentType = RenderPassAttachmentDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPassDepthAttachmentDescriptorRef {
     pub fn clear_depth(&self) -> f64 {
         unsafe { msg_send![self, clearDepth] }
@@ -191,8 +202,10 @@ impl RenderPassDepthAttachmentDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderPassStencilAttachmentDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderPassStencilAttachmentDescriptor;
     pub struct RenderPassStencilAttachmentDescriptor;
@@ -200,6 +213,7 @@ foreign_obj_type! {
     type ParentType = RenderPassAttachmentDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPassStencilAttachmentDescriptorRef {
     pub fn clear_stencil(&self) -> u32 {
         unsafe { msg_send![self, clearStencil] }
@@ -221,14 +235,17 @@ impl RenderPassStencilAttachmentDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderPassColorAttachmentDescriptorArray {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderPassColorAttachmentDescriptorArray;
     pub struct RenderPassColorAttachmentDescriptorArray;
     pub struct RenderPassColorAttachmentDescriptorArrayRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPassColorAttachmentDescriptorArrayRef {
     pub fn object_at(&self, index: NSUInteger) -> Option<&RenderPassColorAttachmentDescriptorRef> {
         unsafe { msg_send![self, objectAtIndexedSubscript: index] }
@@ -246,14 +263,17 @@ impl RenderPassColorAttachmentDescriptorArrayRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLRenderPassDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLRenderPassDescriptor;
     pub struct RenderPassDescriptor;
     pub struct RenderPassDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPassDescriptor {
     /// Creates a default render pass descriptor with no attachments.
     pub fn new<'a>() -> &'a RenderPassDescriptorRef {
@@ -261,6 +281,7 @@ impl RenderPassDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl RenderPassDescriptorRef {
     pub fn color_attachments(&self) -> &RenderPassColorAttachmentDescriptorArrayRef {
         unsafe { msg_send![self, colorAttachments] }
@@ -5,7 +5,10 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use super::{DeviceRef, HeapRef, NSUInteger};
+use super::NSUInteger;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use super::{DeviceRef, HeapRef};
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use objc::runtime::{NO, YES};
 
 #[repr(u64)]
@@ -94,14 +97,17 @@ pub struct MTLSizeAndAlign {
     pub align: NSUInteger,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLResource {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLResource;
     pub struct Resource;
     pub struct ResourceRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl ResourceRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
@@ -0,0 +1,429 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use super::*;
+
+use std::{collections::VecDeque, error::Error, fmt};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// A slice of one of the blocks managed by a `RingAllocator`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RingAllocation {
+    /// The index of the block, in the order blocks were created.
+    pub block: usize,
+    pub offset: NSUInteger,
+    pub size: NSUInteger,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum RingError {
+    /// The allocation is larger than a block.
+    TooLarge {
+        size: NSUInteger,
+        block_size: NSUInteger,
+    },
+    /// Every block is in use by a submission that has not completed, and no new block may be
+    /// created.
+    OutOfBlocks,
+}
+
+impl fmt::Display for RingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RingError::TooLarge { size, block_size } => write!(
+                f,
+                "{} bytes do not fit in a ring block of {} bytes",
+                size, block_size
+            ),
+            RingError::OutOfBlocks => write!(f, "all ring blocks are in use by the GPU"),
+        }
+    }
+}
+
+impl Error for RingError {}
+
+/// Offset bookkeeping for streaming transient data through a set of equally sized blocks.
+///
+/// Allocations are carved linearly out of the current block. When it is full it is retired
+/// with the index of the submission being recorded, and it is handed out again only after
+/// `complete` reports that submission as finished. Submissions are numbered from 0 by
+/// `submit`, which closes the current one, and are expected to complete in order, as command
+/// buffers on one queue do. A new block is created whenever none is free, up to `max_blocks`.
+///
+/// ```
+/// use metal::RingAllocator;
+///
+/// let mut ring = RingAllocator::new(1024, 256);
+/// let a = ring.allocate(100, 1).unwrap();
+/// let b = ring.allocate(100, 1).unwrap();
+/// assert_eq!((a.block, a.offset, b.block, b.offset), (0, 0, 0, 256));
+/// assert_eq!(ring.submit(), 0);
+///
+/// // Block 0 is still read by submission 0, so a second block is created.
+/// ring.allocate(1000, 1).unwrap();
+/// assert_eq!(ring.block_count(), 2);
+/// assert_eq!(ring.submit(), 1);
+///
+/// ring.complete(0);
+/// assert_eq!(ring.allocate(1000, 1).unwrap().block, 0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RingAllocator {
+    block_size: NSUInteger,
+    alignment: NSUInteger,
+    max_blocks: Option<usize>,
+    block_count: usize,
+    /// The block being filled, its head and the last submission that allocated from it.
+    current: Option<(usize, NSUInteger, u64)>,
+    free: Vec<usize>,
+    /// Full blocks and the last submission that allocated from them, oldest first.
+    retired: VecDeque<(u64, usize)>,
+    submission: u64,
+    completed: Option<u64>,
+}
+
+impl RingAllocator {
+    /// `alignment` is the minimum alignment of every allocation, e.g.
+    /// `MTLFeatureSet::min_buffer_offset_alignment`.
+    pub fn new(block_size: NSUInteger, alignment: NSUInteger) -> Self {
+        assert!(
+            alignment.is_power_of_two(),
+            "alignment must be a power of two"
+        );
+        RingAllocator {
+            block_size,
+            alignment,
+            max_blocks: None,
+            block_count: 0,
+            current: None,
+            free: Vec::new(),
+            retired: VecDeque::new(),
+            submission: 0,
+            completed: None,
+        }
+    }
+
+    /// Limits the number of blocks. `allocate` fails with `OutOfBlocks` instead of creating
+    /// more.
+    pub fn set_max_blocks(&mut self, max_blocks: Option<usize>) {
+        self.max_blocks = max_blocks;
+    }
+
+    pub fn block_size(&self) -> NSUInteger {
+        self.block_size
+    }
+
+    /// The number of blocks created so far. Blocks are never released.
+    pub fn block_count(&self) -> usize {
+        self.block_count
+    }
+
+    /// The index of the submission that allocations are currently made for.
+    pub fn submission(&self) -> u64 {
+        self.submission
+    }
+
+    /// The blocks waiting for their submission to complete.
+    pub fn in_flight_blocks(&self) -> usize {
+        self.retired.len()
+    }
+
+    /// Allocates `size` bytes aligned to `alignment` and to the ring's minimum alignment.
+    pub fn allocate(
+        &mut self,
+        size: NSUInteger,
+        alignment: NSUInteger,
+    ) -> Result<RingAllocation, RingError> {
+        assert!(
+            alignment.is_power_of_two(),
+            "alignment must be a power of two"
+        );
+        if size > self.block_size {
+            return Err(RingError::TooLarge {
+                size,
+                block_size: self.block_size,
+            });
+        }
+        let alignment = alignment.max(self.alignment);
+
+        if let Some((block, head, last_use)) = self.current {
+            let offset = head.next_multiple_of(alignment);
+            if offset + size <= self.block_size {
+                self.current = Some((block, offset + size, self.submission));
+                return Ok(RingAllocation {
+                    block,
+                    offset,
+                    size,
+                });
+            }
+            self.retired.push_back((last_use, block));
+            self.current = None;
+        }
+
+        let block = match self.free.pop() {
+            Some(block) => block,
+            None if self.max_blocks.is_none_or(|max| self.block_count < max) => {
+                self.block_count += 1;
+                self.block_count - 1
+            }
+            None => return Err(RingError::OutOfBlocks),
+        };
+        self.current = Some((block, size, self.submission));
+        Ok(RingAllocation {
+            block,
+            offset: 0,
+            size,
+        })
+    }
+
+    /// Closes the current submission and returns its index. Allocations made from now on
+    /// belong to the next one.
+    pub fn submit(&mut self) -> u64 {
+        self.submission += 1;
+        self.submission - 1
+    }
+
+    /// Recycles the blocks of every submission up to and including `submission`.
+    pub fn complete(&mut self, submission: u64) {
+        let completed = match self.completed {
+            Some(completed) => completed.max(submission),
+            None => submission,
+        };
+        self.completed = Some(completed);
+        while let Some(&(last_use, block)) = self.retired.front() {
+            if last_use > completed {
+                break;
+            }
+            self.retired.pop_front();
+            self.free.push(block);
+        }
+    }
+}
+
+/// Streams per-draw constants and vertices through a `RingAllocator` backed by shared
+/// buffers.
+///
+/// Payloads up to `MTLFeatureSet::max_inline_constant_buffer_length` are passed with
+/// `set_*_bytes`; larger ones are copied into the ring and bound by offset. Call `submit` with
+/// each command buffer before committing it, so the ring can reuse memory once it completes.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub struct UploadRing {
+    device: Device,
+    options: MTLResourceOptions,
+    allocator: RingAllocator,
+    buffers: Vec<Buffer>,
+    inline_limit: NSUInteger,
+    /// The number of completed submissions, written from command buffer completion handlers.
+    completed: Arc<AtomicU64>,
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl UploadRing {
+    pub fn new(device: &DeviceRef, feature_set: MTLFeatureSet, block_size: NSUInteger) -> Self {
+        UploadRing {
+            device: device.to_owned(),
+            options: MTLResourceOptions::StorageModeShared
+                | MTLResourceOptions::CPUCacheModeWriteCombined,
+            allocator: RingAllocator::new(
+                block_size,
+                feature_set.min_buffer_offset_alignment() as NSUInteger,
+            ),
+            buffers: Vec::new(),
+            inline_limit: feature_set.max_inline_constant_buffer_length() as NSUInteger,
+            completed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn allocator(&self) -> &RingAllocator {
+        &self.allocator
+    }
+
+    /// Copies `data` into the ring and returns the buffer and offset it was placed at.
+    pub fn upload(&mut self, data: &[u8]) -> Result<(&BufferRef, NSUInteger), RingError> {
+        let completed = self.completed.load(Ordering::Acquire);
+        if completed > 0 {
+            self.allocator.complete(completed - 1);
+        }
+
+        let allocation = self.allocator.allocate(data.len() as NSUInteger, 1)?;
+        while self.buffers.len() <= allocation.block {
+            let buffer = self
+                .device
+                .new_buffer(self.allocator.block_size(), self.options);
+            self.buffers.push(buffer);
+        }
+        let buffer = &self.buffers[allocation.block];
+        unsafe {
+            let target = (buffer.contents() as *mut u8).add(allocation.offset as usize);
+            std::ptr::copy_nonoverlapping(data.as_ptr(), target, data.len());
+        }
+        Ok((buffer, allocation.offset))
+    }
+
+    pub fn set_vertex_data(
+        &mut self,
+        encoder: &RenderCommandEncoderRef,
+        index: NSUInteger,
+        data: &[u8],
+    ) -> Result<(), RingError> {
+        if data.len() as NSUInteger <= self.inline_limit {
+            encoder.set_vertex_bytes(index, data.len() as NSUInteger, data.as_ptr() as *const _);
+        } else {
+            let (buffer, offset) = self.upload(data)?;
+            encoder.set_vertex_buffer(index, Some(buffer), offset);
+        }
+        Ok(())
+    }
+
+    pub fn set_fragment_data(
+        &mut self,
+        encoder: &RenderCommandEncoderRef,
+        index: NSUInteger,
+        data: &[u8],
+    ) -> Result<(), RingError> {
+        if data.len() as NSUInteger <= self.inline_limit {
+            encoder.set_fragment_bytes(index, data.len() as NSUInteger, data.as_ptr() as *const _);
+        } else {
+            let (buffer, offset) = self.upload(data)?;
+            encoder.set_fragment_buffer(index, Some(buffer), offset);
+        }
+        Ok(())
+    }
+
+    pub fn set_compute_data(
+        &mut self,
+        encoder: &ComputeCommandEncoderRef,
+        index: NSUInteger,
+        data: &[u8],
+    ) -> Result<(), RingError> {
+        if data.len() as NSUInteger <= self.inline_limit {
+            encoder.set_bytes(index, data.len() as NSUInteger, data.as_ptr() as *const _);
+        } else {
+            let (buffer, offset) = self.upload(data)?;
+            encoder.set_buffer(index, Some(buffer), offset);
+        }
+        Ok(())
+    }
+
+    /// Ends the allocations for `command_buffer`, which must not be committed yet.
+    pub fn submit(&mut self, command_buffer: &CommandBufferRef) -> u64 {
+        let submission = self.allocator.submit();
+        let completed = Arc::clone(&self.completed);
+        let handler = block::ConcreteBlock::new(move |_: &CommandBufferRef| {
+            completed.fetch_max(submission + 1, Ordering::Release);
+        })
+        .copy();
+        command_buffer.add_completed_handler(&handler);
+        submission
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_blocks_limits_growth() {
+        let mut ring = RingAllocator::new(256, 16);
+        ring.set_max_blocks(Some(2));
+        assert_eq!(ring.allocate(256, 1).unwrap().block, 0);
+        assert_eq!(ring.submit(), 0);
+        assert_eq!(ring.allocate(256, 1).unwrap().block, 1);
+        assert_eq!(ring.submit(), 1);
+        assert_eq!(ring.in_flight_blocks(), 1);
+
+        assert_eq!(ring.allocate(1, 1), Err(RingError::OutOfBlocks));
+        assert_eq!(ring.block_count(), 2);
+        // The full block was retired by the failed allocation.
+        assert_eq!(ring.in_flight_blocks(), 2);
+
+        ring.complete(0);
+        assert_eq!(ring.in_flight_blocks(), 1);
+        assert_eq!(ring.allocate(1, 1).unwrap().block, 0);
+
+        ring.set_max_blocks(None);
+        assert_eq!(ring.allocate(256, 1).unwrap().block, 2);
+    }
+
+    #[test]
+    fn rejects_allocations_larger_than_a_block() {
+        let mut ring = RingAllocator::new(256, 16);
+        assert_eq!(
+            ring.allocate(257, 1),
+            Err(RingError::TooLarge {
+                size: 257,
+                block_size: 256,
+            })
+        );
+        assert_eq!(ring.block_count(), 0);
+        let whole = ring.allocate(256, 1).unwrap();
+        assert_eq!((whole.block, whole.offset, whole.size), (0, 0, 256));
+    }
+
+    #[test]
+    fn complete_is_cumulative_and_ignores_older_submissions() {
+        let mut ring = RingAllocator::new(256, 16);
+        for submission in 0..3 {
+            assert_eq!(ring.allocate(256, 1).unwrap().block, submission);
+            ring.submit();
+        }
+        // Block 2 is still current; blocks 0 and 1 are retired.
+        ring.allocate(1, 1).unwrap();
+        assert_eq!(ring.in_flight_blocks(), 3);
+
+        // Completing submission 1 first also covers submission 0.
+        ring.complete(1);
+        assert_eq!(ring.in_flight_blocks(), 1);
+        // A late report for submission 0 changes nothing.
+        ring.complete(0);
+        assert_eq!(ring.in_flight_blocks(), 1);
+
+        let mut reused = vec![
+            ring.allocate(256, 1).unwrap().block,
+            ring.allocate(256, 1).unwrap().block,
+        ];
+        reused.sort_unstable();
+        assert_eq!(reused, [0, 1]);
+        assert_eq!(ring.allocate(256, 1).unwrap().block, 4);
+        assert_eq!(ring.block_count(), 5);
+    }
+
+    #[test]
+    fn block_is_held_until_its_last_submission_completes() {
+        let mut ring = RingAllocator::new(256, 16);
+        ring.allocate(100, 1).unwrap();
+        ring.submit();
+        ring.allocate(100, 1).unwrap();
+        ring.submit();
+        // Block 0 is last used by submission 1, so completing 0 must not free it.
+        assert_eq!(ring.allocate(100, 1).unwrap().block, 1);
+        ring.complete(0);
+        assert_eq!(ring.allocate(200, 1).unwrap().block, 2);
+        ring.complete(1);
+        assert_eq!(ring.allocate(200, 1).unwrap().block, 0);
+    }
+
+    #[test]
+    fn aligns_to_the_larger_of_request_and_ring_minimum() {
+        let mut ring = RingAllocator::new(1024, 64);
+        let offsets: Vec<_> = [(10, 1), (10, 4), (10, 256), (1, 64), (1, 128)]
+            .iter()
+            .map(|&(size, alignment)| ring.allocate(size, alignment).unwrap().offset)
+            .collect();
+        assert_eq!(offsets, [0, 64, 256, 320, 384]);
+
+        // An allocation ending exactly at the block end still fits.
+        let last = ring.allocate(1024 - 448, 64).unwrap();
+        assert_eq!((last.block, last.offset), (0, 448));
+        assert_eq!(ring.allocate(1, 1).unwrap().block, 1);
+    }
+}
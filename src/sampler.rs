@@ -5,6 +5,7 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use super::{depthstencil::MTLCompareFunction, DeviceRef, NSUInteger};
 
 #[repr(u64)]
@@ -41,14 +42,17 @@ pub enum MTLSamplerBorderColor {
     OpaqueWhite = 2,
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLSamplerDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLSamplerDescriptor;
     pub struct SamplerDescriptor;
     pub struct SamplerDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl SamplerDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -58,6 +62,7 @@ impl SamplerDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl SamplerDescriptorRef {
     pub fn set_min_filter(&self, filter: MTLSamplerMinMagFilter) {
         unsafe { msg_send![self, setMinFilter: filter] }
@@ -135,14 +140,17 @@ impl SamplerDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLSamplerState {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLSamplerState;
     pub struct SamplerState;
     pub struct SamplerStateRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl SamplerStateRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
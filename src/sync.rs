@@ -6,30 +6,35 @@
 // copied, modified, or distributed except according to those terms.
 
 use super::*;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use block::{Block, RcBlock};
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use std::mem;
 
-#[cfg(feature = "dispatch_queue")]
-use dispatch;
-
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 type MTLSharedEventNotificationBlock<'a> = RcBlock<(&'a SharedEventRef, u64), ()>;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLEvent {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLEvent;
     pub struct Event;
     pub struct EventRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl EventRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLSharedEvent {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLSharedEvent;
     pub struct SharedEvent;
@@ -37,6 +42,7 @@ foreign_obj_type! {
     type ParentType = EventRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl SharedEventRef {
     pub fn signaled_value(&self) -> u64 {
         unsafe { msg_send![self, signaledValue] }
@@ -81,14 +87,17 @@ impl SharedEventRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLSharedEventListener {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLSharedEventListener;
     pub struct SharedEventListener;
     pub struct SharedEventListenerRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl SharedEventListener {
     pub unsafe fn from_queue_handle(queue: dispatch_queue_t) -> Self {
         let listener: SharedEventListener = msg_send![class!(MTLSharedEventListener), alloc];
@@ -108,14 +117,17 @@ impl SharedEventListener {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLFence {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLFence;
     pub struct Fence;
     pub struct FenceRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl FenceRef {
     pub fn device(&self) -> &DeviceRef {
         unsafe { msg_send![self, device] }
@@ -153,9 +165,12 @@ bitflags! {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 const BLOCK_HAS_COPY_DISPOSE: i32 = 0x02000000;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 const BLOCK_HAS_SIGNATURE: i32 = 0x40000000;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[repr(C)]
 struct BlockBase<A, R> {
     isa: *const std::ffi::c_void,                             // 0x00
@@ -165,8 +180,10 @@ struct BlockBase<A, R> {
     extra: *const BlockExtra<A, R>,                           // 0x18
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 type BlockExtraDtor<A, R> = extern "C" fn(*mut BlockBase<A, R>);
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 #[repr(C)]
 struct BlockExtra<A, R> {
     unknown0: *mut i32,          // 0x00
@@ -7,11 +7,15 @@
 
 use super::*;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use objc::runtime::{NO, YES};
 
+use std::{error::Error, fmt};
+
 #[repr(u64)]
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MTLTextureType {
     D1 = 0,
     D1Array = 1,
@@ -40,14 +44,411 @@ bitflags! {
     }
 }
 
+/// The shape of a texture, mirroring the sizing fields of `TextureDescriptorRef`, used to
+/// compute mip extents and linear-layout byte sizes without a device.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TextureLayout {
+    pub texture_type: MTLTextureType,
+    pub pixel_format: MTLPixelFormat,
+    pub width: NSUInteger,
+    pub height: NSUInteger,
+    pub depth: NSUInteger,
+    pub array_length: NSUInteger,
+    pub mipmap_level_count: NSUInteger,
+    pub sample_count: NSUInteger,
+}
+
+/// Limits a texture description is validated against by `TextureLayout::validate`.
+///
+/// Implemented by `MTLFeatureSet` and `DeviceProfile`.
+pub trait TextureLimits {
+    fn max_1d_texture_size(&self) -> u32;
+    fn max_2d_texture_size(&self) -> u32;
+    fn max_3d_texture_size(&self) -> u32;
+    fn max_cube_map_texture_size(&self) -> u32;
+    fn max_array_layers(&self) -> u32;
+    fn pixel_format_capabilities(&self, format: MTLPixelFormat) -> PixelFormatCapabilities;
+    fn supports_sample_count(&self, count: NSUInteger) -> bool;
+}
+
+/// A texture validation failure reported by `TextureLayout::validate`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TextureValidationError {
+    InvalidPixelFormat,
+    UnsupportedPixelFormat {
+        pixel_format: MTLPixelFormat,
+    },
+    ZeroSize {
+        size: MTLSize,
+    },
+    SizeExceedsLimit {
+        size: MTLSize,
+        limit: u32,
+    },
+    CubeNotSquare {
+        width: NSUInteger,
+        height: NSUInteger,
+    },
+    InvalidArrayLength {
+        array_length: NSUInteger,
+        limit: u32,
+    },
+    InvalidMipmapLevelCount {
+        count: NSUInteger,
+        max: NSUInteger,
+    },
+    InvalidSampleCount {
+        count: NSUInteger,
+    },
+    MsaaNotSupported {
+        pixel_format: MTLPixelFormat,
+    },
+    UsageNotSupported {
+        pixel_format: MTLPixelFormat,
+        usage: MTLTextureUsage,
+    },
+}
+
+impl fmt::Display for TextureValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TextureValidationError::InvalidPixelFormat => write!(f, "pixel format is invalid"),
+            TextureValidationError::UnsupportedPixelFormat { pixel_format } => {
+                write!(f, "{:?} is not supported", pixel_format)
+            }
+            TextureValidationError::ZeroSize { size } => write!(
+                f,
+                "size {}x{}x{} has a zero dimension",
+                size.width, size.height, size.depth
+            ),
+            TextureValidationError::SizeExceedsLimit { size, limit } => write!(
+                f,
+                "size {}x{}x{} exceeds the limit of {}",
+                size.width, size.height, size.depth, limit
+            ),
+            TextureValidationError::CubeNotSquare { width, height } => {
+                write!(f, "cube faces of {}x{} are not square", width, height)
+            }
+            TextureValidationError::InvalidArrayLength {
+                array_length,
+                limit,
+            } => write!(
+                f,
+                "array length {} is outside of 1..={}",
+                array_length, limit
+            ),
+            TextureValidationError::InvalidMipmapLevelCount { count, max } => {
+                write!(f, "mipmap level count {} is outside of 1..={}", count, max)
+            }
+            TextureValidationError::InvalidSampleCount { count } => {
+                write!(f, "sample count {} is not supported", count)
+            }
+            TextureValidationError::MsaaNotSupported { pixel_format } => {
+                write!(f, "{:?} cannot be multisampled", pixel_format)
+            }
+            TextureValidationError::UsageNotSupported {
+                pixel_format,
+                usage,
+            } => write!(f, "{:?} does not support {:?}", pixel_format, usage),
+        }
+    }
+}
+
+impl Error for TextureValidationError {}
+
+/// Extent and linear-layout byte sizes of one mip level of one slice of a texture.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TextureSubresourceLayout {
+    pub level: NSUInteger,
+    /// Array slice; cube textures have six slices per array element.
+    pub slice: NSUInteger,
+    /// Size of the mip level in texels.
+    pub size: MTLSize,
+    /// Size of the mip level in blocks of the pixel format.
+    pub blocks: MTLSize,
+    pub bytes_per_row: NSUInteger,
+    pub bytes_per_image: NSUInteger,
+    /// Offset of the subresource in a tightly packed buffer holding all subresources,
+    /// ordered by slice and then by level.
+    pub offset: NSUInteger,
+    /// Size of the subresource in bytes, covering every depth image and sample.
+    pub length: NSUInteger,
+}
+
+impl TextureLayout {
+    /// Creates a layout with one array element, one mip level and one sample.
+    pub fn new(texture_type: MTLTextureType, pixel_format: MTLPixelFormat, size: MTLSize) -> Self {
+        TextureLayout {
+            texture_type,
+            pixel_format,
+            width: size.width,
+            height: size.height,
+            depth: size.depth,
+            array_length: 1,
+            mipmap_level_count: 1,
+            sample_count: 1,
+        }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn from_descriptor(descriptor: &TextureDescriptorRef) -> Self {
+        TextureLayout {
+            texture_type: descriptor.texture_type(),
+            pixel_format: descriptor.pixel_format(),
+            width: descriptor.width(),
+            height: descriptor.height(),
+            depth: descriptor.depth(),
+            array_length: descriptor.array_length(),
+            mipmap_level_count: descriptor.mipmap_level_count(),
+            sample_count: descriptor.sample_count(),
+        }
+    }
+
+    /// Size of the base level, with the dimensions the texture type does not use set to 1.
+    pub fn size(&self) -> MTLSize {
+        match self.texture_type {
+            MTLTextureType::D1 | MTLTextureType::D1Array => MTLSize::new(self.width, 1, 1),
+            MTLTextureType::D3 => MTLSize::new(self.width, self.height, self.depth),
+            _ => MTLSize::new(self.width, self.height, 1),
+        }
+    }
+
+    /// Number of mip levels of a full mip chain down to 1x1x1.
+    pub fn full_mipmap_level_count(&self) -> NSUInteger {
+        let size = self.size();
+        let largest = size.width.max(size.height).max(size.depth).max(1);
+        (NSUInteger::BITS - largest.leading_zeros()) as NSUInteger
+    }
+
+    /// Number of 2D slices: array elements times six faces for cube textures, or `None` if
+    /// that does not fit in `NSUInteger`.
+    pub fn slice_count(&self) -> Option<NSUInteger> {
+        let array_length = match self.texture_type {
+            MTLTextureType::D1Array | MTLTextureType::D2Array | MTLTextureType::CubeArray => {
+                self.array_length.max(1)
+            }
+            _ => 1,
+        };
+        match self.texture_type {
+            MTLTextureType::Cube | MTLTextureType::CubeArray => array_length.checked_mul(6),
+            _ => Some(array_length),
+        }
+    }
+
+    /// Size of `level` in texels.
+    pub fn mip_size(&self, level: NSUInteger) -> MTLSize {
+        let size = self.size();
+        let shrink = |extent: NSUInteger| (extent.checked_shr(level as u32).unwrap_or(0)).max(1);
+        MTLSize::new(shrink(size.width), shrink(size.height), shrink(size.depth))
+    }
+
+    /// Size of `level` in blocks of the pixel format.
+    ///
+    /// PVRTC levels are padded to at least 2x2 blocks, as the format requires.
+    pub fn mip_blocks(&self, level: NSUInteger) -> MTLSize {
+        let size = self.mip_size(level);
+        let (block_width, block_height) = self.pixel_format.block_size();
+        let mut width = size.width.div_ceil(block_width as NSUInteger);
+        let mut height = size.height.div_ceil(block_height as NSUInteger);
+        if self.is_pvrtc() {
+            width = width.max(2);
+            height = height.max(2);
+        }
+        MTLSize::new(width, height, size.depth)
+    }
+
+    /// Bytes of one row of blocks of `level` in a linear layout, or `None` if that does not
+    /// fit in `NSUInteger`.
+    pub fn bytes_per_row(&self, level: NSUInteger) -> Option<NSUInteger> {
+        self.mip_blocks(level)
+            .width
+            .checked_mul(self.pixel_format.bytes_per_block() as NSUInteger)
+    }
+
+    /// Bytes of one 2D image of `level` in a linear layout, or `None` if that does not fit
+    /// in `NSUInteger`.
+    pub fn bytes_per_image(&self, level: NSUInteger) -> Option<NSUInteger> {
+        self.bytes_per_row(level)?
+            .checked_mul(self.mip_blocks(level).height)
+    }
+
+    /// Bytes of every depth image and sample of `level` of one slice.
+    fn level_length(&self, level: NSUInteger) -> Option<NSUInteger> {
+        self.bytes_per_image(level)?
+            .checked_mul(self.mip_blocks(level).depth)?
+            .checked_mul(self.sample_count.max(1))
+    }
+
+    /// Returns the layout of every subresource, ordered by slice and then by level, or
+    /// `None` if an offset does not fit in `NSUInteger`.
+    pub fn subresources(&self) -> Option<Vec<TextureSubresourceLayout>> {
+        let levels = self.mipmap_level_count.max(1);
+        let slices = self.slice_count()?;
+        // Also rejects layouts whose subresource offsets would overflow.
+        self.total_bytes()?;
+
+        let mut offset = 0;
+        let mut subresources = Vec::with_capacity(slices.checked_mul(levels)? as usize);
+        for slice in 0..slices {
+            for level in 0..levels {
+                let length = self.level_length(level)?;
+                subresources.push(TextureSubresourceLayout {
+                    level,
+                    slice,
+                    size: self.mip_size(level),
+                    blocks: self.mip_blocks(level),
+                    bytes_per_row: self.bytes_per_row(level)?,
+                    bytes_per_image: self.bytes_per_image(level)?,
+                    offset,
+                    length,
+                });
+                offset += length;
+            }
+        }
+        Some(subresources)
+    }
+
+    /// Total bytes of all subresources in a tightly packed linear layout, or `None` if that
+    /// does not fit in `NSUInteger`.
+    ///
+    /// This is the size of a staging buffer holding the whole texture; the private
+    /// storage a device allocates may be larger because of tiling and alignment.
+    pub fn total_bytes(&self) -> Option<NSUInteger> {
+        let levels = self.mipmap_level_count.max(1);
+        let per_slice = (0..levels).try_fold(0 as NSUInteger, |total, level| {
+            total.checked_add(self.level_length(level)?)
+        })?;
+        per_slice.checked_mul(self.slice_count()?)
+    }
+
+    /// Checks this texture, created with `usage`, against the size limits, sample counts
+    /// and pixel format capabilities of `limits`.
+    ///
+    /// Returns the first problem found.
+    pub fn validate<L: TextureLimits + ?Sized>(
+        &self,
+        usage: MTLTextureUsage,
+        limits: &L,
+    ) -> Result<(), TextureValidationError> {
+        use MTLTextureType::*;
+
+        if self.pixel_format == MTLPixelFormat::Invalid {
+            return Err(TextureValidationError::InvalidPixelFormat);
+        }
+        let capabilities = limits.pixel_format_capabilities(self.pixel_format);
+        if capabilities.is_empty() {
+            return Err(TextureValidationError::UnsupportedPixelFormat {
+                pixel_format: self.pixel_format,
+            });
+        }
+
+        let size = self.size();
+        if size.width == 0 || size.height == 0 || size.depth == 0 {
+            return Err(TextureValidationError::ZeroSize { size });
+        }
+        let limit = match self.texture_type {
+            D1 | D1Array => limits.max_1d_texture_size(),
+            D2 | D2Array | D2Multisample => limits.max_2d_texture_size(),
+            Cube | CubeArray => limits.max_cube_map_texture_size(),
+            D3 => limits.max_3d_texture_size(),
+        } as NSUInteger;
+        if size.width > limit || size.height > limit || size.depth > limit {
+            return Err(TextureValidationError::SizeExceedsLimit {
+                size,
+                limit: limit as u32,
+            });
+        }
+        if let Cube | CubeArray = self.texture_type {
+            if size.width != size.height {
+                return Err(TextureValidationError::CubeNotSquare {
+                    width: size.width,
+                    height: size.height,
+                });
+            }
+        }
+
+        match self.texture_type {
+            D1Array | D2Array | CubeArray => {
+                let limit = limits.max_array_layers();
+                if self.array_length == 0 || self.array_length > limit as NSUInteger {
+                    return Err(TextureValidationError::InvalidArrayLength {
+                        array_length: self.array_length,
+                        limit,
+                    });
+                }
+            }
+            _ => {
+                if self.array_length != 1 {
+                    return Err(TextureValidationError::InvalidArrayLength {
+                        array_length: self.array_length,
+                        limit: 1,
+                    });
+                }
+            }
+        }
+
+        let max_levels = if self.texture_type == D2Multisample {
+            1
+        } else {
+            self.full_mipmap_level_count()
+        };
+        if self.mipmap_level_count == 0 || self.mipmap_level_count > max_levels {
+            return Err(TextureValidationError::InvalidMipmapLevelCount {
+                count: self.mipmap_level_count,
+                max: max_levels,
+            });
+        }
+
+        let multisampled = self.texture_type == D2Multisample;
+        if self.sample_count == 0
+            || (self.sample_count > 1) != multisampled
+            || !limits.supports_sample_count(self.sample_count)
+        {
+            return Err(TextureValidationError::InvalidSampleCount {
+                count: self.sample_count,
+            });
+        }
+        if multisampled && !capabilities.contains(PixelFormatCapabilities::Msaa) {
+            return Err(TextureValidationError::MsaaNotSupported {
+                pixel_format: self.pixel_format,
+            });
+        }
+
+        let writable = capabilities.contains(PixelFormatCapabilities::Write);
+        let renderable = capabilities.contains(PixelFormatCapabilities::Color)
+            || self.pixel_format.is_depth_stencil();
+        let unsupported = (usage.contains(MTLTextureUsage::ShaderWrite) && !writable)
+            || (usage.contains(MTLTextureUsage::RenderTarget) && !renderable);
+        if unsupported {
+            return Err(TextureValidationError::UsageNotSupported {
+                pixel_format: self.pixel_format,
+                usage,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn is_pvrtc(&self) -> bool {
+        use MTLPixelFormat::*;
+        matches!(
+            self.pixel_format.linear_variant(),
+            Some(PVRTC_RGB_2BPP | PVRTC_RGB_4BPP | PVRTC_RGBA_2BPP | PVRTC_RGBA_4BPP)
+        )
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLTextureDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLTextureDescriptor;
     pub struct TextureDescriptor;
     pub struct TextureDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl TextureDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -57,6 +458,7 @@ impl TextureDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl TextureDescriptorRef {
     pub fn texture_type(&self) -> MTLTextureType {
         unsafe { msg_send![self, textureType] }
@@ -173,8 +575,10 @@ impl TextureDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLTexture {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLTexture;
     pub struct Texture;
@@ -182,6 +586,7 @@ foreign_obj_type! {
     type ParentType = ResourceRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl TextureRef {
     #[deprecated(since = "0.13.0")]
     pub fn root_resource(&self) -> Option<&ResourceRef> {
@@ -346,3 +751,343 @@ impl TextureRef {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(
+        texture_type: MTLTextureType,
+        pixel_format: MTLPixelFormat,
+        width: NSUInteger,
+        height: NSUInteger,
+        depth: NSUInteger,
+    ) -> TextureLayout {
+        TextureLayout::new(
+            texture_type,
+            pixel_format,
+            MTLSize::new(width, height, depth),
+        )
+    }
+
+    #[test]
+    fn mip_extents() {
+        let mut texture = layout(MTLTextureType::D2, MTLPixelFormat::RGBA8Unorm, 37, 16, 9);
+        assert_eq!(texture.size(), MTLSize::new(37, 16, 1));
+        assert_eq!(texture.full_mipmap_level_count(), 6);
+        assert_eq!(texture.mip_size(1), MTLSize::new(18, 8, 1));
+        assert_eq!(texture.mip_size(4), MTLSize::new(2, 1, 1));
+        assert_eq!(texture.mip_size(5), MTLSize::new(1, 1, 1));
+        assert_eq!(texture.mip_size(70), MTLSize::new(1, 1, 1));
+
+        texture.texture_type = MTLTextureType::D3;
+        assert_eq!(texture.mip_size(2), MTLSize::new(9, 4, 2));
+
+        texture.texture_type = MTLTextureType::D1;
+        assert_eq!(texture.size(), MTLSize::new(37, 1, 1));
+    }
+
+    #[test]
+    fn bc_blocks_round_up() {
+        let mut texture = layout(MTLTextureType::D2, MTLPixelFormat::BC1_RGBA, 10, 6, 1);
+        texture.mipmap_level_count = 3;
+        assert_eq!(texture.mip_blocks(0), MTLSize::new(3, 2, 1));
+        assert_eq!(texture.bytes_per_row(0), Some(24));
+        assert_eq!(texture.bytes_per_image(0), Some(48));
+        // 5x3 and 2x1 texels both fit in a single row of blocks.
+        assert_eq!(texture.mip_blocks(1), MTLSize::new(2, 1, 1));
+        assert_eq!(texture.mip_blocks(2), MTLSize::new(1, 1, 1));
+        assert_eq!(texture.total_bytes(), Some(48 + 16 + 8));
+    }
+
+    #[test]
+    fn pvrtc_pads_to_two_blocks() {
+        let texture = layout(MTLTextureType::D2, MTLPixelFormat::PVRTC_RGBA_4BPP, 4, 4, 1);
+        assert_eq!(texture.mip_blocks(0), MTLSize::new(2, 2, 1));
+        assert_eq!(texture.bytes_per_image(0), Some(32));
+
+        let texture = layout(
+            MTLTextureType::D2,
+            MTLPixelFormat::PVRTC_RGB_2BPP_sRGB,
+            32,
+            4,
+            1,
+        );
+        assert_eq!(texture.mip_blocks(0), MTLSize::new(4, 2, 1));
+        assert_eq!(texture.bytes_per_row(0), Some(32));
+    }
+
+    #[test]
+    fn cube_array_subresources_are_ordered_by_slice_then_level() {
+        let mut texture = layout(
+            MTLTextureType::CubeArray,
+            MTLPixelFormat::RGBA8Unorm,
+            4,
+            4,
+            1,
+        );
+        texture.array_length = 2;
+        texture.mipmap_level_count = 2;
+        assert_eq!(texture.slice_count(), Some(12));
+
+        let subresources = texture.subresources().unwrap();
+        assert_eq!(subresources.len(), 24);
+        let order: Vec<_> = subresources[..4]
+            .iter()
+            .map(|subresource| (subresource.slice, subresource.level))
+            .collect();
+        assert_eq!(order, [(0, 0), (0, 1), (1, 0), (1, 1)]);
+
+        let offsets: Vec<_> = subresources[..4]
+            .iter()
+            .map(|subresource| (subresource.offset, subresource.length))
+            .collect();
+        assert_eq!(offsets, [(0, 64), (64, 16), (80, 64), (144, 16)]);
+
+        let last = subresources.last().unwrap();
+        assert_eq!((last.slice, last.level), (11, 1));
+        assert_eq!(last.offset + last.length, texture.total_bytes().unwrap());
+        assert_eq!(texture.total_bytes(), Some(12 * 80));
+    }
+
+    #[test]
+    fn subresource_lengths_cover_depth_and_samples() {
+        let mut texture = layout(MTLTextureType::D3, MTLPixelFormat::R16Float, 8, 4, 4);
+        texture.mipmap_level_count = 2;
+        let subresources = texture.subresources().unwrap();
+        assert_eq!(subresources[0].bytes_per_row, 16);
+        assert_eq!(subresources[0].bytes_per_image, 64);
+        assert_eq!(subresources[0].length, 256);
+        assert_eq!(subresources[1].size, MTLSize::new(4, 2, 2));
+        assert_eq!(subresources[1].offset, 256);
+        assert_eq!(subresources[1].length, 32);
+
+        let mut texture = layout(
+            MTLTextureType::D2Multisample,
+            MTLPixelFormat::RGBA8Unorm,
+            4,
+            4,
+            1,
+        );
+        texture.sample_count = 4;
+        assert_eq!(texture.total_bytes(), Some(4 * 4 * 4 * 4));
+
+        // Array lengths only count for array types.
+        let mut texture = layout(MTLTextureType::D2, MTLPixelFormat::RGBA8Unorm, 4, 4, 1);
+        texture.array_length = 3;
+        assert_eq!(texture.slice_count(), Some(1));
+        texture.texture_type = MTLTextureType::D2Array;
+        assert_eq!(texture.slice_count(), Some(3));
+    }
+
+    #[test]
+    fn oversized_layouts_do_not_overflow() {
+        let texture = layout(
+            MTLTextureType::D2,
+            MTLPixelFormat::RGBA32Float,
+            NSUInteger::MAX,
+            1,
+            1,
+        );
+        assert_eq!(texture.bytes_per_row(0), None);
+        assert_eq!(texture.total_bytes(), None);
+        assert_eq!(texture.subresources(), None);
+
+        let texture = layout(
+            MTLTextureType::D2,
+            MTLPixelFormat::RGBA8Unorm,
+            1 << 32,
+            1 << 32,
+            1,
+        );
+        assert_eq!(texture.bytes_per_row(0), Some(1 << 34));
+        assert_eq!(texture.bytes_per_image(0), None);
+
+        let mut texture = layout(
+            MTLTextureType::CubeArray,
+            MTLPixelFormat::RGBA8Unorm,
+            1,
+            1,
+            1,
+        );
+        texture.array_length = NSUInteger::MAX / 4;
+        assert_eq!(texture.slice_count(), None);
+        assert_eq!(texture.total_bytes(), None);
+
+        let mut texture = layout(MTLTextureType::D2Array, MTLPixelFormat::RGBA8Unorm, 1, 1, 1);
+        texture.array_length = NSUInteger::MAX / 2;
+        assert_eq!(texture.total_bytes(), None);
+    }
+
+    fn validate(
+        texture: TextureLayout,
+        usage: MTLTextureUsage,
+        feature_set: MTLFeatureSet,
+    ) -> Result<(), TextureValidationError> {
+        texture.validate(usage, &feature_set)
+    }
+
+    #[test]
+    fn feature_set_accepts_valid_textures() {
+        let mac = MTLFeatureSet::macOS_GPUFamily2_v1;
+        let usage = MTLTextureUsage::RenderTarget | MTLTextureUsage::ShaderWrite;
+        let mut texture = layout(MTLTextureType::D2, MTLPixelFormat::RGBA8Unorm, 256, 128, 1);
+        texture.mipmap_level_count = 9;
+        assert_eq!(validate(texture, usage, mac), Ok(()));
+
+        let mut texture = layout(
+            MTLTextureType::CubeArray,
+            MTLPixelFormat::BC7_RGBAUnorm,
+            64,
+            64,
+            1,
+        );
+        texture.array_length = 4;
+        assert_eq!(validate(texture, MTLTextureUsage::ShaderRead, mac), Ok(()));
+
+        let mut texture = layout(
+            MTLTextureType::D2Multisample,
+            MTLPixelFormat::Depth32Float,
+            64,
+            64,
+            1,
+        );
+        texture.sample_count = 8;
+        assert_eq!(
+            validate(texture, MTLTextureUsage::RenderTarget, mac),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn feature_set_rejects_formats() {
+        let ios = MTLFeatureSet::iOS_GPUFamily3_v1;
+        let texture = layout(MTLTextureType::D2, MTLPixelFormat::Invalid, 4, 4, 1);
+        assert_eq!(
+            validate(texture, MTLTextureUsage::ShaderRead, ios),
+            Err(TextureValidationError::InvalidPixelFormat)
+        );
+
+        let texture = layout(MTLTextureType::D2, MTLPixelFormat::BC1_RGBA, 4, 4, 1);
+        assert_eq!(
+            validate(texture, MTLTextureUsage::ShaderRead, ios),
+            Err(TextureValidationError::UnsupportedPixelFormat {
+                pixel_format: MTLPixelFormat::BC1_RGBA
+            })
+        );
+
+        // BC textures can only be sampled.
+        let usage = MTLTextureUsage::ShaderRead | MTLTextureUsage::ShaderWrite;
+        assert_eq!(
+            validate(texture, usage, MTLFeatureSet::macOS_GPUFamily1_v1),
+            Err(TextureValidationError::UsageNotSupported {
+                pixel_format: MTLPixelFormat::BC1_RGBA,
+                usage
+            })
+        );
+    }
+
+    #[test]
+    fn feature_set_rejects_sizes() {
+        let ios = MTLFeatureSet::iOS_GPUFamily1_v1;
+        let read = MTLTextureUsage::ShaderRead;
+        let texture = layout(MTLTextureType::D2, MTLPixelFormat::RGBA8Unorm, 4, 0, 1);
+        assert_eq!(
+            validate(texture, read, ios),
+            Err(TextureValidationError::ZeroSize {
+                size: MTLSize::new(4, 0, 1)
+            })
+        );
+
+        let texture = layout(MTLTextureType::D2, MTLPixelFormat::RGBA8Unorm, 4097, 16, 1);
+        assert_eq!(
+            validate(texture, read, ios),
+            Err(TextureValidationError::SizeExceedsLimit {
+                size: MTLSize::new(4097, 16, 1),
+                limit: 4096
+            })
+        );
+        assert_eq!(
+            validate(texture, read, MTLFeatureSet::iOS_GPUFamily1_v3),
+            Ok(())
+        );
+
+        let texture = layout(MTLTextureType::Cube, MTLPixelFormat::RGBA8Unorm, 8, 4, 1);
+        assert_eq!(
+            validate(texture, read, ios),
+            Err(TextureValidationError::CubeNotSquare {
+                width: 8,
+                height: 4
+            })
+        );
+    }
+
+    #[test]
+    fn feature_set_rejects_counts() {
+        let ios = MTLFeatureSet::iOS_GPUFamily3_v1;
+        let read = MTLTextureUsage::ShaderRead;
+        let mut texture = layout(MTLTextureType::D2, MTLPixelFormat::RGBA8Unorm, 4, 4, 1);
+        texture.array_length = 2;
+        assert_eq!(
+            validate(texture, read, ios),
+            Err(TextureValidationError::InvalidArrayLength {
+                array_length: 2,
+                limit: 1
+            })
+        );
+
+        texture.texture_type = MTLTextureType::D2Array;
+        texture.array_length = 2049;
+        assert_eq!(
+            validate(texture, read, ios),
+            Err(TextureValidationError::InvalidArrayLength {
+                array_length: 2049,
+                limit: 2048
+            })
+        );
+
+        let mut texture = layout(MTLTextureType::D2, MTLPixelFormat::RGBA8Unorm, 4, 4, 1);
+        texture.mipmap_level_count = 4;
+        assert_eq!(
+            validate(texture, read, ios),
+            Err(TextureValidationError::InvalidMipmapLevelCount { count: 4, max: 3 })
+        );
+
+        let mut texture = layout(
+            MTLTextureType::D2Multisample,
+            MTLPixelFormat::RGBA8Unorm,
+            4,
+            4,
+            1,
+        );
+        texture.sample_count = 8;
+        assert_eq!(
+            validate(texture, read, ios),
+            Err(TextureValidationError::InvalidSampleCount { count: 8 })
+        );
+        assert_eq!(
+            validate(texture, read, MTLFeatureSet::macOS_GPUFamily1_v1),
+            Ok(())
+        );
+        texture.sample_count = 1;
+        assert_eq!(
+            validate(texture, read, ios),
+            Err(TextureValidationError::InvalidSampleCount { count: 1 })
+        );
+
+        let mut texture = layout(
+            MTLTextureType::D2Multisample,
+            MTLPixelFormat::RGBA32Float,
+            4,
+            4,
+            1,
+        );
+        texture.sample_count = 4;
+        assert_eq!(
+            validate(texture, read, ios),
+            Err(TextureValidationError::MsaaNotSupported {
+                pixel_format: MTLPixelFormat::RGBA32Float
+            })
+        );
+    }
+}
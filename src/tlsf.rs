@@ -0,0 +1,761 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use super::*;
+
+/// log2 of the number of second-level bins per power of two.
+const SL_LOG2: u32 = 4;
+const SL_COUNT: usize = 1 << SL_LOG2;
+const FL_COUNT: usize = 64 - SL_LOG2 as usize + 1;
+
+/// A range handed out by `TlsfAllocator::allocate`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TlsfAllocation {
+    pub offset: NSUInteger,
+    pub size: NSUInteger,
+    block: usize,
+    generation: u64,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Block {
+    offset: NSUInteger,
+    size: NSUInteger,
+    free: bool,
+    /// The generation of the allocation occupying the block, so that a stale allocation
+    /// of the same range is not mistaken for the current one.
+    generation: u64,
+    prev_physical: Option<usize>,
+    next_physical: Option<usize>,
+    prev_free: Option<usize>,
+    next_free: Option<usize>,
+}
+
+/// A two-level segregated fit allocator for offsets within a fixed range, such as a
+/// `MTLHeapType::Placement` heap.
+///
+/// Free blocks are binned by size into power-of-two ranges, each split into 16 linear bins,
+/// and bitmaps find a fitting bin in constant time. Freed blocks merge with free neighbours
+/// immediately. Allocation rounds the request up to the next bin so that any block found fits
+/// without walking a list, and only walks the smaller bins when that finds nothing, e.g. for a
+/// block of exactly the requested size. Alignment is satisfied by splitting off the front of
+/// the block.
+///
+/// ```
+/// use metal::TlsfAllocator;
+///
+/// let mut allocator = TlsfAllocator::new(1 << 20);
+/// let a = allocator.allocate(1000, 256).unwrap();
+/// let b = allocator.allocate(5000, 4096).unwrap();
+/// assert_eq!(b.offset % 4096, 0);
+/// allocator.free(a);
+/// allocator.free(b);
+/// assert_eq!(allocator.used_size(), 0);
+/// assert_eq!(allocator.largest_free_block(), 1 << 20);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TlsfAllocator {
+    size: NSUInteger,
+    blocks: Vec<Block>,
+    spare_blocks: Vec<usize>,
+    fl_bitmap: u64,
+    sl_bitmaps: [u32; FL_COUNT],
+    heads: [[Option<usize>; SL_COUNT]; FL_COUNT],
+    used_size: NSUInteger,
+    allocation_count: usize,
+    /// Incremented by every allocation.
+    generation: u64,
+}
+
+impl TlsfAllocator {
+    pub fn new(size: NSUInteger) -> Self {
+        let mut allocator = TlsfAllocator {
+            size,
+            blocks: Vec::new(),
+            spare_blocks: Vec::new(),
+            fl_bitmap: 0,
+            sl_bitmaps: [0; FL_COUNT],
+            heads: [[None; SL_COUNT]; FL_COUNT],
+            used_size: 0,
+            allocation_count: 0,
+            generation: 0,
+        };
+        if size > 0 {
+            let block = allocator.new_block(Block {
+                offset: 0,
+                size,
+                free: true,
+                generation: 0,
+                prev_physical: None,
+                next_physical: None,
+                prev_free: None,
+                next_free: None,
+            });
+            allocator.insert_free(block);
+        }
+        allocator
+    }
+
+    /// Allocates `size` bytes at an offset that is a multiple of `alignment`. Returns `None`
+    /// if no free block is large enough or `size` is zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alignment` is not a power of two.
+    pub fn allocate(&mut self, size: NSUInteger, alignment: NSUInteger) -> Option<TlsfAllocation> {
+        assert!(
+            alignment.is_power_of_two(),
+            "alignment must be a power of two"
+        );
+        if size == 0 {
+            return None;
+        }
+        let block = self.find_free(size, alignment)?;
+        self.remove_free(block);
+
+        let offset = self.blocks[block].offset;
+        let aligned = offset.next_multiple_of(alignment);
+        let block = match aligned - offset {
+            0 => block,
+            padding => {
+                let rest = self.split(block, padding);
+                self.insert_free(block);
+                rest
+            }
+        };
+        if self.blocks[block].size > size {
+            let rest = self.split(block, size);
+            self.insert_free(rest);
+            self.merge_with_next(rest);
+        }
+
+        self.generation += 1;
+        self.blocks[block].free = false;
+        self.blocks[block].generation = self.generation;
+        self.used_size += size;
+        self.allocation_count += 1;
+        Some(TlsfAllocation {
+            offset: aligned,
+            size,
+            block,
+            generation: self.generation,
+        })
+    }
+
+    /// Returns an allocation's range to the free blocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation was already freed, even if its range was allocated again
+    /// since, or comes from another allocator.
+    pub fn free(&mut self, allocation: TlsfAllocation) {
+        let block = self.blocks.get(allocation.block).copied();
+        match block {
+            Some(block)
+                if !block.free
+                    && block.generation == allocation.generation
+                    && block.offset == allocation.offset
+                    && block.size == allocation.size => {}
+            _ => panic!("{:?} is not allocated here", allocation),
+        }
+        self.used_size -= allocation.size;
+        self.allocation_count -= 1;
+
+        let mut block = allocation.block;
+        self.blocks[block].free = true;
+        if let Some(prev) = self.blocks[block].prev_physical {
+            if self.blocks[prev].free {
+                self.remove_free(prev);
+                self.absorb_next(prev);
+                block = prev;
+            }
+        }
+        self.insert_free(block);
+        self.merge_with_next(block);
+    }
+
+    /// The size of the managed range.
+    pub fn size(&self) -> NSUInteger {
+        self.size
+    }
+
+    pub fn used_size(&self) -> NSUInteger {
+        self.used_size
+    }
+
+    pub fn free_size(&self) -> NSUInteger {
+        self.size - self.used_size
+    }
+
+    pub fn allocation_count(&self) -> usize {
+        self.allocation_count
+    }
+
+    /// The size of the largest allocation with alignment 1 that would currently succeed.
+    pub fn largest_free_block(&self) -> NSUInteger {
+        if self.fl_bitmap == 0 {
+            return 0;
+        }
+        let fl = 63 - self.fl_bitmap.leading_zeros() as usize;
+        let sl = 31 - self.sl_bitmaps[fl].leading_zeros() as usize;
+        let mut largest = 0;
+        let mut next = self.heads[fl][sl];
+        while let Some(block) = next {
+            largest = largest.max(self.blocks[block].size);
+            next = self.blocks[block].next_free;
+        }
+        largest
+    }
+
+    /// The share of free memory outside the largest free block, from 0 (one contiguous free
+    /// range) towards 1 (free memory scattered in small pieces).
+    pub fn fragmentation(&self) -> f32 {
+        match self.free_size() {
+            0 => 0.0,
+            free => 1.0 - self.largest_free_block() as f32 / free as f32,
+        }
+    }
+
+    /// Verifies the internal invariants, for fuzzing and debugging.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the blocks do not tile the range, free neighbours were not merged, or the
+    /// bins disagree with the blocks.
+    pub fn check_consistency(&self) {
+        let mut free_in_bins = 0;
+        for fl in 0..FL_COUNT {
+            assert_eq!(self.fl_bitmap >> fl & 1 == 1, self.sl_bitmaps[fl] != 0);
+            for sl in 0..SL_COUNT {
+                assert_eq!(
+                    self.sl_bitmaps[fl] >> sl & 1 == 1,
+                    self.heads[fl][sl].is_some()
+                );
+                let mut prev = None;
+                let mut next = self.heads[fl][sl];
+                while let Some(block) = next {
+                    let entry = &self.blocks[block];
+                    assert!(entry.free);
+                    assert_eq!(entry.prev_free, prev);
+                    assert_eq!(mapping(entry.size), (fl, sl));
+                    free_in_bins += 1;
+                    prev = next;
+                    next = entry.next_free;
+                }
+            }
+        }
+
+        let mut free_blocks = 0;
+        let mut offset = 0;
+        let mut prev: Option<usize> = None;
+        let mut next = self
+            .blocks
+            .iter()
+            .position(|block| block.prev_physical.is_none() && block.size > 0 && block.offset == 0);
+        let mut used = 0;
+        while let Some(block) = next {
+            let entry = &self.blocks[block];
+            assert_eq!(entry.offset, offset);
+            assert_eq!(entry.prev_physical, prev);
+            if entry.free {
+                free_blocks += 1;
+                assert!(prev.is_none_or(|prev| !self.blocks[prev].free));
+            } else {
+                used += entry.size;
+            }
+            offset += entry.size;
+            prev = next;
+            next = entry.next_physical;
+        }
+        assert_eq!(offset, self.size);
+        assert_eq!(free_blocks, free_in_bins);
+        assert_eq!(used, self.used_size);
+    }
+
+    fn new_block(&mut self, block: Block) -> usize {
+        match self.spare_blocks.pop() {
+            Some(index) => {
+                self.blocks[index] = block;
+                index
+            }
+            None => {
+                self.blocks.push(block);
+                self.blocks.len() - 1
+            }
+        }
+    }
+
+    /// Splits `block` after `size` bytes and returns the new second half, which is neither
+    /// free nor in a bin.
+    fn split(&mut self, block: usize, size: NSUInteger) -> usize {
+        let entry = self.blocks[block];
+        let rest = self.new_block(Block {
+            offset: entry.offset + size,
+            size: entry.size - size,
+            free: true,
+            generation: 0,
+            prev_physical: Some(block),
+            next_physical: entry.next_physical,
+            prev_free: None,
+            next_free: None,
+        });
+        if let Some(next) = entry.next_physical {
+            self.blocks[next].prev_physical = Some(rest);
+        }
+        self.blocks[block].size = size;
+        self.blocks[block].next_physical = Some(rest);
+        rest
+    }
+
+    /// Merges the free, binned `block` with its next neighbour if that is free too.
+    fn merge_with_next(&mut self, block: usize) {
+        if let Some(next) = self.blocks[block].next_physical {
+            if self.blocks[next].free {
+                self.remove_free(block);
+                self.remove_free(next);
+                self.absorb_next(block);
+                self.insert_free(block);
+            }
+        }
+    }
+
+    /// Appends the next physical block to `block` and recycles its slot.
+    fn absorb_next(&mut self, block: usize) {
+        let next = self.blocks[block].next_physical.unwrap();
+        let absorbed = self.blocks[next];
+        self.blocks[block].size += absorbed.size;
+        self.blocks[block].next_physical = absorbed.next_physical;
+        if let Some(after) = absorbed.next_physical {
+            self.blocks[after].prev_physical = Some(block);
+        }
+        self.blocks[next].size = 0;
+        self.blocks[next].prev_physical = None;
+        self.spare_blocks.push(next);
+    }
+
+    /// A free block that can hold `size` bytes at `alignment`.
+    fn find_free(&self, size: NSUInteger, alignment: NSUInteger) -> Option<usize> {
+        // Every block in this bin or above fits, whatever its offset.
+        let good_fit = size
+            .checked_add(alignment - 1)
+            .and_then(round_up_to_bin)
+            .map(mapping);
+        if let Some(bin) = good_fit.and_then(|bin| self.first_bin_from(bin)) {
+            return Some(self.heads[bin.0][bin.1].unwrap());
+        }
+
+        // Smaller blocks fit only if their offset needs little enough padding.
+        let mut next_bin = Some(mapping(size));
+        while let Some((fl, sl)) = next_bin.and_then(|bin| self.first_bin_from(bin)) {
+            if good_fit.is_some_and(|good_fit| (fl, sl) >= good_fit) {
+                break;
+            }
+            let mut next = self.heads[fl][sl];
+            while let Some(block) = next {
+                let Block {
+                    offset,
+                    size: available,
+                    ..
+                } = self.blocks[block];
+                let fits = offset
+                    .checked_next_multiple_of(alignment)
+                    .and_then(|aligned| aligned.checked_add(size))
+                    .is_some_and(|end| end <= offset + available);
+                if fits {
+                    return Some(block);
+                }
+                next = self.blocks[block].next_free;
+            }
+            next_bin = match sl + 1 {
+                SL_COUNT if fl + 1 < FL_COUNT => Some((fl + 1, 0)),
+                SL_COUNT => None,
+                sl => Some((fl, sl)),
+            };
+        }
+        None
+    }
+
+    /// The first non-empty bin at or after `(fl, sl)`.
+    fn first_bin_from(&self, (fl, sl): (usize, usize)) -> Option<(usize, usize)> {
+        let sl_map = self.sl_bitmaps.get(fl).map_or(0, |map| map & (!0u32 << sl));
+        if sl_map != 0 {
+            return Some((fl, sl_map.trailing_zeros() as usize));
+        }
+        let fl_map = self.fl_bitmap & (!0u64).checked_shl(fl as u32 + 1).unwrap_or(0);
+        if fl_map == 0 {
+            return None;
+        }
+        let fl = fl_map.trailing_zeros() as usize;
+        Some((fl, self.sl_bitmaps[fl].trailing_zeros() as usize))
+    }
+
+    fn insert_free(&mut self, block: usize) {
+        let (fl, sl) = mapping(self.blocks[block].size);
+        let head = self.heads[fl][sl];
+        self.blocks[block].free = true;
+        self.blocks[block].prev_free = None;
+        self.blocks[block].next_free = head;
+        if let Some(head) = head {
+            self.blocks[head].prev_free = Some(block);
+        }
+        self.heads[fl][sl] = Some(block);
+        self.fl_bitmap |= 1 << fl;
+        self.sl_bitmaps[fl] |= 1 << sl;
+    }
+
+    fn remove_free(&mut self, block: usize) {
+        let Block {
+            size,
+            prev_free,
+            next_free,
+            ..
+        } = self.blocks[block];
+        let (fl, sl) = mapping(size);
+        match prev_free {
+            Some(prev) => self.blocks[prev].next_free = next_free,
+            None => self.heads[fl][sl] = next_free,
+        }
+        if let Some(next) = next_free {
+            self.blocks[next].prev_free = prev_free;
+        }
+        self.blocks[block].prev_free = None;
+        self.blocks[block].next_free = None;
+        if self.heads[fl][sl].is_none() {
+            self.sl_bitmaps[fl] &= !(1 << sl);
+            if self.sl_bitmaps[fl] == 0 {
+                self.fl_bitmap &= !(1 << fl);
+            }
+        }
+    }
+}
+
+/// The first- and second-level bin of a block size.
+fn mapping(size: NSUInteger) -> (usize, usize) {
+    if size < SL_COUNT as NSUInteger {
+        return (0, size as usize);
+    }
+    let log2 = 63 - size.leading_zeros();
+    let fl = (log2 - SL_LOG2 + 1) as usize;
+    let sl = ((size >> (log2 - SL_LOG2)) as usize) ^ SL_COUNT;
+    (fl, sl)
+}
+
+/// Rounds `size` up so that every block in its bin is at least `size` bytes.
+fn round_up_to_bin(size: NSUInteger) -> Option<NSUInteger> {
+    if size < SL_COUNT as NSUInteger {
+        return Some(size);
+    }
+    let log2 = 63 - size.leading_zeros();
+    let granularity = 1 << (log2 - SL_LOG2);
+    Some(size.checked_add(granularity - 1)? & !(granularity - 1))
+}
+
+/// Where a resource created by `PlacementHeapAllocator` lives, needed to free its memory.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct HeapAllocation {
+    pub heap: usize,
+    pub allocation: TlsfAllocation,
+}
+
+/// Sub-allocates buffers and textures from `MTLHeapType::Placement` heaps, adding a heap
+/// whenever none has room.
+///
+/// Resource options must use the storage mode the allocator was created with. `free` only
+/// returns the memory; the resource must be released and no longer used by the GPU.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub struct PlacementHeapAllocator {
+    device: Device,
+    storage_mode: MTLStorageMode,
+    heap_size: NSUInteger,
+    heaps: Vec<(Heap, TlsfAllocator)>,
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl PlacementHeapAllocator {
+    /// New heaps are `heap_size` bytes, or larger for resources that do not fit.
+    pub fn new(device: &DeviceRef, storage_mode: MTLStorageMode, heap_size: NSUInteger) -> Self {
+        PlacementHeapAllocator {
+            device: device.to_owned(),
+            storage_mode,
+            heap_size,
+            heaps: Vec::new(),
+        }
+    }
+
+    pub fn new_buffer(
+        &mut self,
+        length: NSUInteger,
+        options: MTLResourceOptions,
+    ) -> Option<(Buffer, HeapAllocation)> {
+        let size_and_align = self.device.heap_buffer_size_and_align(length, options);
+        let allocation = self.allocate(size_and_align)?;
+        let (ref heap, _) = self.heaps[allocation.heap];
+        match heap.new_buffer_with_offset(length, options, allocation.allocation.offset) {
+            Some(buffer) => Some((buffer, allocation)),
+            None => {
+                self.free(allocation);
+                None
+            }
+        }
+    }
+
+    pub fn new_texture(
+        &mut self,
+        descriptor: &TextureDescriptorRef,
+    ) -> Option<(Texture, HeapAllocation)> {
+        let size_and_align = self.device.heap_texture_size_and_align(descriptor);
+        let allocation = self.allocate(size_and_align)?;
+        let (ref heap, _) = self.heaps[allocation.heap];
+        match heap.new_texture_with_offset(descriptor, allocation.allocation.offset) {
+            Some(texture) => Some((texture, allocation)),
+            None => {
+                self.free(allocation);
+                None
+            }
+        }
+    }
+
+    pub fn free(&mut self, allocation: HeapAllocation) {
+        self.heaps[allocation.heap].1.free(allocation.allocation);
+    }
+
+    pub fn heaps(&self) -> impl Iterator<Item = (&HeapRef, &TlsfAllocator)> {
+        self.heaps
+            .iter()
+            .map(|(heap, allocator)| (&**heap, allocator))
+    }
+
+    pub fn used_size(&self) -> NSUInteger {
+        self.heaps
+            .iter()
+            .map(|(_, allocator)| allocator.used_size())
+            .sum()
+    }
+
+    pub fn free_size(&self) -> NSUInteger {
+        self.heaps
+            .iter()
+            .map(|(_, allocator)| allocator.free_size())
+            .sum()
+    }
+
+    fn allocate(&mut self, size_and_align: MTLSizeAndAlign) -> Option<HeapAllocation> {
+        let MTLSizeAndAlign { size, align } = size_and_align;
+        for (heap, (_, allocator)) in self.heaps.iter_mut().enumerate() {
+            if let Some(allocation) = allocator.allocate(size, align) {
+                return Some(HeapAllocation { heap, allocation });
+            }
+        }
+
+        let descriptor = HeapDescriptor::new();
+        descriptor.set_heap_type(MTLHeapType::Placement);
+        descriptor.set_storage_mode(self.storage_mode);
+        // A new heap is a single free block at offset 0, which satisfies any alignment.
+        descriptor.set_size(self.heap_size.max(size));
+        let heap = self.device.new_heap(&descriptor);
+        let mut allocator = TlsfAllocator::new(heap.size());
+        let allocation = allocator.allocate(size, align)?;
+        self.heaps.push((heap, allocator));
+        Some(HeapAllocation {
+            heap: self.heaps.len() - 1,
+            allocation,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Rng;
+
+    #[test]
+    fn exact_fit_in_fresh_allocator() {
+        for &(size, alignment) in &[
+            (1, 1),
+            (15, 8),
+            (1000, 1),
+            (1000, 256),
+            (4096, 4096),
+            (3_000_000, 65536),
+            (1 << 40, 1 << 20),
+        ] {
+            let mut allocator = TlsfAllocator::new(size);
+            let allocation = allocator.allocate(size, alignment).unwrap();
+            assert_eq!((allocation.offset, allocation.size), (0, size));
+            assert_eq!(allocator.free_size(), 0);
+            assert_eq!(allocator.allocate(1, 1), None);
+            allocator.check_consistency();
+        }
+    }
+
+    #[test]
+    fn exact_fit_reuses_freed_block() {
+        let mut allocator = TlsfAllocator::new(3000);
+        let a = allocator.allocate(1000, 1).unwrap();
+        let b = allocator.allocate(1000, 1).unwrap();
+        let c = allocator.allocate(1000, 1).unwrap();
+        allocator.free(b);
+        assert_eq!(allocator.largest_free_block(), 1000);
+
+        let reused = allocator.allocate(1000, 8).unwrap();
+        assert_eq!(reused.offset, b.offset);
+        assert_eq!(allocator.allocate(1, 1), None);
+        allocator.check_consistency();
+
+        allocator.free(a);
+        // The freed block at offset 0 is aligned for anything that fits in it.
+        assert_eq!(allocator.allocate(1000, 512).unwrap().offset, 0);
+        allocator.free(c);
+        // A block at offset 2000 cannot hold 1000 bytes at 1024 alignment.
+        assert_eq!(allocator.allocate(1000, 1024), None);
+        allocator.check_consistency();
+    }
+
+    #[test]
+    fn allocations_are_aligned() {
+        let mut allocator = TlsfAllocator::new(1 << 20);
+        let mut allocations = Vec::new();
+        for (index, &alignment) in [1, 4, 256, 16, 4096, 2, 65536, 8].iter().enumerate() {
+            let allocation = allocator
+                .allocate(100 + index as NSUInteger * 37, alignment)
+                .unwrap();
+            assert_eq!(allocation.offset % alignment, 0);
+            allocations.push(allocation);
+            allocator.check_consistency();
+        }
+        for (index, a) in allocations.iter().enumerate() {
+            for b in &allocations[..index] {
+                assert!(a.offset + a.size <= b.offset || b.offset + b.size <= a.offset);
+            }
+        }
+        assert_eq!(
+            allocator.used_size(),
+            allocations
+                .iter()
+                .map(|allocation| allocation.size)
+                .sum::<u64>()
+        );
+        for allocation in allocations {
+            allocator.free(allocation);
+            allocator.check_consistency();
+        }
+        assert_eq!(allocator.largest_free_block(), 1 << 20);
+    }
+
+    #[test]
+    fn fragmentation() {
+        let mut allocator = TlsfAllocator::new(1024);
+        assert_eq!(allocator.fragmentation(), 0.0);
+        let blocks: Vec<_> = (0..4)
+            .map(|_| allocator.allocate(256, 1).unwrap())
+            .collect();
+        // Nothing is free.
+        assert_eq!(allocator.fragmentation(), 0.0);
+
+        allocator.free(blocks[0]);
+        allocator.free(blocks[2]);
+        assert_eq!(allocator.largest_free_block(), 256);
+        assert_eq!(allocator.fragmentation(), 0.5);
+
+        allocator.free(blocks[1]);
+        assert_eq!(allocator.largest_free_block(), 768);
+        assert_eq!(allocator.fragmentation(), 0.0);
+        allocator.check_consistency();
+    }
+
+    #[test]
+    #[should_panic]
+    fn double_free_panics() {
+        let mut allocator = TlsfAllocator::new(1024);
+        let allocation = allocator.allocate(100, 1).unwrap();
+        allocator.free(allocation);
+        allocator.free(allocation);
+    }
+
+    #[test]
+    #[should_panic]
+    fn stale_free_after_reuse_panics() {
+        let mut allocator = TlsfAllocator::new(1024);
+        let stale = allocator.allocate(100, 1).unwrap();
+        allocator.free(stale);
+        let live = allocator.allocate(100, 1).unwrap();
+        assert_eq!((live.offset, live.size), (stale.offset, stale.size));
+        allocator.free(stale);
+    }
+
+    #[test]
+    fn stale_free_leaves_reused_range_allocated() {
+        let mut allocator = TlsfAllocator::new(1024);
+        let a = allocator.allocate(100, 1).unwrap();
+        let b = allocator.allocate(100, 1).unwrap();
+        allocator.free(b);
+        let c = allocator.allocate(100, 1).unwrap();
+        assert_eq!((c.offset, c.size), (b.offset, b.size));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            allocator.free(b);
+        }));
+        assert!(result.is_err());
+        assert_eq!(allocator.allocation_count(), 2);
+        assert_eq!(allocator.used_size(), 200);
+        allocator.check_consistency();
+        allocator.free(a);
+        allocator.free(c);
+        assert_eq!(allocator.largest_free_block(), 1024);
+    }
+
+    #[test]
+    fn random_allocate_and_free() {
+        // Random operations, checking invariants after every step.
+        let mut rng = Rng::new(0x853c_49e6_748f_ea9b);
+        for _ in 0..20 {
+            let size = 1 + rng.below(1 << 16);
+            let mut allocator = TlsfAllocator::new(size);
+            let mut live: Vec<TlsfAllocation> = Vec::new();
+            for _ in 0..500 {
+                if live.is_empty() || rng.below(3) != 0 {
+                    let bound = if rng.below(4) == 0 { size } else { 2048 };
+                    let request = 1 + rng.below(bound);
+                    let alignment = 1 << rng.below(13);
+                    let largest = allocator.largest_free_block();
+                    match allocator.allocate(request, alignment) {
+                        Some(allocation) => {
+                            assert_eq!(allocation.offset % alignment, 0);
+                            assert_eq!(allocation.size, request);
+                            assert!(allocation.offset + request <= size);
+                            for other in &live {
+                                assert!(
+                                    allocation.offset + request <= other.offset
+                                        || other.offset + other.size <= allocation.offset
+                                );
+                            }
+                            live.push(allocation);
+                        }
+                        // Unaligned requests that fit the largest block always succeed.
+                        None => assert!(alignment > 1 || request > largest),
+                    }
+                } else {
+                    let index = rng.below(live.len() as u64) as usize;
+                    allocator.free(live.swap_remove(index));
+                }
+                allocator.check_consistency();
+                assert_eq!(allocator.allocation_count(), live.len());
+                assert_eq!(
+                    allocator.used_size(),
+                    live.iter().map(|allocation| allocation.size).sum::<u64>()
+                );
+                let fragmentation = allocator.fragmentation();
+                assert!((0.0..1.0).contains(&fragmentation));
+            }
+            for allocation in live.drain(..) {
+                allocator.free(allocation);
+            }
+            allocator.check_consistency();
+            assert_eq!(allocator.largest_free_block(), size);
+        }
+    }
+}
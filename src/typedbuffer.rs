@@ -0,0 +1,350 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use super::*;
+
+use std::{error::Error, fmt, mem, ops::Range};
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+use std::marker::PhantomData;
+
+/// Plain data that can be copied to and from buffer memory byte for byte.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` or `#[repr(transparent)]`, contain no padding and no
+/// pointers, and be valid for any bit pattern.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+unsafe impl<T: Pod> Pod for Normalized<T> {}
+
+/// An element range that does not fit in a `TypedBuffer`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct BufferBoundsError {
+    pub offset: usize,
+    pub count: usize,
+    pub len: usize,
+}
+
+impl fmt::Display for BufferBoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} elements at offset {} exceed a buffer of {} elements",
+            self.count, self.offset, self.len
+        )
+    }
+}
+
+impl Error for BufferBoundsError {}
+
+/// Sorted, disjoint byte ranges written by the CPU and not yet flushed to the GPU.
+///
+/// Overlapping and adjacent ranges are merged as they are inserted, so flushing issues one
+/// `did_modify_range` per contiguous region.
+///
+/// ```
+/// use metal::DirtyRanges;
+///
+/// let mut dirty = DirtyRanges::new();
+/// dirty.insert(64..128);
+/// dirty.insert(0..16);
+/// dirty.insert(16..32);
+/// dirty.insert(100..200);
+/// assert_eq!(dirty.ranges(), &[0..32, 64..200]);
+/// dirty.insert(32..64);
+/// assert_eq!(dirty.take(), vec![0..200]);
+/// assert!(dirty.is_empty());
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct DirtyRanges {
+    ranges: Vec<Range<NSUInteger>>,
+}
+
+impl DirtyRanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, range: Range<NSUInteger>) {
+        if range.start >= range.end {
+            return;
+        }
+        // Ranges before `first` end strictly before `range`, ranges from `last` on start
+        // strictly after it; everything in between touches `range` and is merged into it.
+        let first = self.ranges.partition_point(|dirty| dirty.end < range.start);
+        let last = self
+            .ranges
+            .partition_point(|dirty| dirty.start <= range.end);
+        let merged = match self.ranges[first..last] {
+            [] => range,
+            ref touching => {
+                touching[0].start.min(range.start)..touching[touching.len() - 1].end.max(range.end)
+            }
+        };
+        self.ranges.splice(first..last, Some(merged));
+    }
+
+    pub fn ranges(&self) -> &[Range<NSUInteger>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The number of dirty bytes.
+    pub fn byte_count(&self) -> NSUInteger {
+        self.ranges
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum()
+    }
+
+    /// Removes and returns all ranges.
+    pub fn take(&mut self) -> Vec<Range<NSUInteger>> {
+        mem::take(&mut self.ranges)
+    }
+}
+
+/// A buffer of `T`s with bounds-checked access to its contents.
+///
+/// Writes to a `MTLStorageMode::Managed` buffer are recorded in `DirtyRanges` and reported to
+/// Metal by `flush`, which must run before the GPU reads the new data. `Shared` buffers need no
+/// flush, and `Private` buffers have no CPU-visible contents and are rejected.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub struct TypedBuffer<T: Pod> {
+    buffer: Buffer,
+    len: usize,
+    managed: bool,
+    dirty: DirtyRanges,
+    marker: PhantomData<T>,
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+impl<T: Pod> TypedBuffer<T> {
+    /// Creates a zero-initialized buffer of `len` elements.
+    pub fn new(device: &DeviceRef, len: usize, options: MTLResourceOptions) -> Self {
+        let buffer = device.new_buffer((len * mem::size_of::<T>()) as NSUInteger, options);
+        Self::from_buffer(buffer)
+    }
+
+    pub fn from_slice(device: &DeviceRef, data: &[T], options: MTLResourceOptions) -> Self {
+        let buffer = device.new_buffer_with_data(
+            data.as_ptr() as *const _,
+            mem::size_of_val(data) as NSUInteger,
+            options,
+        );
+        Self::from_buffer(buffer)
+    }
+
+    /// Views an existing buffer as `T`s. Trailing bytes that do not fill an element are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer uses `MTLStorageMode::Private` or `T` is zero-sized.
+    pub fn from_buffer(buffer: Buffer) -> Self {
+        let storage_mode = buffer.storage_mode();
+        assert!(
+            storage_mode != MTLStorageMode::Private,
+            "private buffers have no CPU-visible contents"
+        );
+        assert!(
+            mem::size_of::<T>() != 0,
+            "zero-sized elements are not supported"
+        );
+        TypedBuffer {
+            len: buffer.length() as usize / mem::size_of::<T>(),
+            managed: storage_mode == MTLStorageMode::Managed,
+            buffer,
+            dirty: DirtyRanges::new(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn buffer(&self) -> &BufferRef {
+        &self.buffer
+    }
+
+    /// The byte offset of element `index`, for `set_vertex_buffer`, `set_buffer` and friends.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than `len`.
+    pub fn byte_offset(&self, index: usize) -> NSUInteger {
+        assert!(index <= self.len, "index {} out of {}", index, self.len);
+        (index * mem::size_of::<T>()) as NSUInteger
+    }
+
+    /// Copies `data` into the buffer starting at element `offset`.
+    pub fn write(&mut self, offset: usize, data: &[T]) -> Result<(), BufferBoundsError> {
+        self.check(offset, data.len())?;
+        unsafe {
+            let target = (self.buffer.contents() as *mut T).add(offset);
+            std::ptr::copy_nonoverlapping(data.as_ptr(), target, data.len());
+        }
+        if self.managed {
+            let start = self.byte_offset(offset);
+            self.dirty
+                .insert(start..start + mem::size_of_val(data) as NSUInteger);
+        }
+        Ok(())
+    }
+
+    /// Borrows `count` elements starting at element `offset`.
+    ///
+    /// The slice reflects GPU writes only once the command buffer that made them completed,
+    /// and for managed buffers only after a blit `synchronize_resource`.
+    pub fn read(&self, offset: usize, count: usize) -> Result<&[T], BufferBoundsError> {
+        self.check(offset, count)?;
+        unsafe {
+            let source = (self.buffer.contents() as *const T).add(offset);
+            Ok(std::slice::from_raw_parts(source, count))
+        }
+    }
+
+    pub fn dirty_ranges(&self) -> &DirtyRanges {
+        &self.dirty
+    }
+
+    /// Calls `did_modify_range` for every range written since the last flush.
+    pub fn flush(&mut self) {
+        for range in self.dirty.take() {
+            self.buffer
+                .did_modify_range(NSRange::new(range.start, range.end - range.start));
+        }
+    }
+
+    fn check(&self, offset: usize, count: usize) -> Result<(), BufferBoundsError> {
+        match offset.checked_add(count) {
+            Some(end) if end <= self.len => Ok(()),
+            _ => Err(BufferBoundsError {
+                offset,
+                count,
+                len: self.len,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::*;
+    use crate::test_util::Rng;
+
+    fn dirty(ranges: &[Range<NSUInteger>]) -> DirtyRanges {
+        let mut dirty = DirtyRanges::new();
+        for range in ranges {
+            dirty.insert(range.clone());
+        }
+        dirty
+    }
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn ignores_empty_ranges() {
+        let mut ranges = dirty(&[4..4, 8..2]);
+        assert!(ranges.is_empty());
+        ranges.insert(0..4);
+        ranges.insert(2..2);
+        ranges.insert(10..10);
+        assert_eq!(ranges.ranges(), &[0..4]);
+    }
+
+    #[test]
+    fn keeps_disjoint_ranges_sorted() {
+        let ranges = dirty(&[20..24, 0..4, 10..12]);
+        assert_eq!(ranges.ranges(), &[0..4, 10..12, 20..24]);
+        assert_eq!(ranges.byte_count(), 10);
+    }
+
+    #[test]
+    fn merges_exactly_adjacent_ranges() {
+        assert_eq!(dirty(&[0..4, 4..8]).ranges(), &[0..8]);
+        assert_eq!(dirty(&[4..8, 0..4]).ranges(), &[0..8]);
+        assert_eq!(dirty(&[0..4, 8..12, 4..8]).ranges(), &[0..12]);
+        assert_eq!(dirty(&[0..4, 5..8]).ranges(), &[0..4, 5..8]);
+    }
+
+    #[test]
+    fn merges_insert_spanning_several_ranges() {
+        let mut ranges = dirty(&[0..2, 4..6, 8..10, 12..14, 20..22]);
+        ranges.insert(5..13);
+        assert_eq!(ranges.ranges(), &[0..2, 4..14, 20..22]);
+        ranges.insert(1..30);
+        assert_eq!(ranges.ranges(), &[0..30]);
+        assert_eq!(ranges.byte_count(), 30);
+    }
+
+    #[test]
+    fn ignores_range_contained_in_existing_one() {
+        let mut ranges = dirty(&[0..16, 32..48]);
+        ranges.insert(4..8);
+        ranges.insert(32..48);
+        ranges.insert(40..48);
+        assert_eq!(ranges.ranges(), &[0..16, 32..48]);
+        assert_eq!(ranges.byte_count(), 32);
+    }
+
+    #[test]
+    fn take_empties_the_set() {
+        let mut ranges = dirty(&[0..4, 8..12]);
+        assert_eq!(ranges.take(), vec![0..4, 8..12]);
+        assert!(ranges.is_empty());
+        assert_eq!(ranges.byte_count(), 0);
+    }
+
+    #[test]
+    fn matches_byte_map() {
+        // Random inserts checked against a per-byte model.
+        let mut rng = Rng::new(0x2545_f491);
+        for _ in 0..100 {
+            let mut ranges = DirtyRanges::new();
+            let mut bytes = [false; 80];
+            for _ in 0..12 {
+                let start = rng.below(64);
+                let end = start + rng.below(12);
+                ranges.insert(start..end);
+                for byte in start..end {
+                    bytes[byte as usize] = true;
+                }
+
+                let mut expected: Vec<Range<NSUInteger>> = Vec::new();
+                for (byte, _) in bytes.iter().enumerate().filter(|&(_, &set)| set) {
+                    let byte = byte as NSUInteger;
+                    match expected.last_mut() {
+                        Some(last) if last.end == byte => last.end += 1,
+                        _ => expected.push(byte..byte + 1),
+                    }
+                }
+                assert_eq!(ranges.ranges(), expected.as_slice());
+                assert_eq!(
+                    ranges.byte_count(),
+                    bytes.iter().filter(|&&set| set).count() as NSUInteger
+                );
+            }
+        }
+    }
+}
@@ -18,6 +18,7 @@ pub struct MTLOrigin {
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MTLSize {
     pub width: NSUInteger,
     pub height: NSUInteger,
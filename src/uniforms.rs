@@ -0,0 +1,415 @@
+// Copyright 2020 GFX developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use super::*;
+
+use crate::vertexdescriptor::f32_to_f16;
+
+/// An `f32` stored as an MSL `half`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct Half(pub f32);
+
+/// A Rust value that can be written as an MSL scalar, vector or matrix.
+///
+/// Vectors are arrays of scalars and matrices are arrays of columns, so `[[f32; 3]; 4]` is a
+/// `float4x3`.
+pub trait MslValue {
+    const DATA_TYPE: MTLDataType;
+
+    /// Appends the scalars column by column, without any padding.
+    fn write_scalars(&self, out: &mut Vec<u8>);
+}
+
+macro_rules! msl_scalar {
+    ($($ty:ty => $data_type:ident, [$vec2:ident, $vec3:ident, $vec4:ident], |$value:ident| $bytes:expr;)*) => {
+        $(
+            impl MslValue for $ty {
+                const DATA_TYPE: MTLDataType = MTLDataType::$data_type;
+
+                fn write_scalars(&self, out: &mut Vec<u8>) {
+                    let $value = *self;
+                    out.extend_from_slice(&$bytes);
+                }
+            }
+
+            msl_scalar!(@vector $ty, 2, $vec2);
+            msl_scalar!(@vector $ty, 3, $vec3);
+            msl_scalar!(@vector $ty, 4, $vec4);
+        )*
+    };
+    (@vector $ty:ty, $n:expr, $data_type:ident) => {
+        impl MslValue for [$ty; $n] {
+            const DATA_TYPE: MTLDataType = MTLDataType::$data_type;
+
+            fn write_scalars(&self, out: &mut Vec<u8>) {
+                for component in self {
+                    component.write_scalars(out);
+                }
+            }
+        }
+    };
+}
+
+msl_scalar! {
+    f32 => Float, [Float2, Float3, Float4], |value| value.to_le_bytes();
+    Half => Half, [Half2, Half3, Half4], |value| f32_to_f16(value.0).to_le_bytes();
+    i32 => Int, [Int2, Int3, Int4], |value| value.to_le_bytes();
+    u32 => UInt, [UInt2, UInt3, UInt4], |value| value.to_le_bytes();
+    i16 => Short, [Short2, Short3, Short4], |value| value.to_le_bytes();
+    u16 => UShort, [UShort2, UShort3, UShort4], |value| value.to_le_bytes();
+    i8 => Char, [Char2, Char3, Char4], |value| value.to_le_bytes();
+    u8 => UChar, [UChar2, UChar3, UChar4], |value| [value];
+    bool => Bool, [Bool2, Bool3, Bool4], |value| [value as u8];
+}
+
+macro_rules! msl_matrix {
+    ($($ty:ty, $columns:expr, $rows:expr => $data_type:ident;)*) => {
+        $(
+            impl MslValue for [[$ty; $rows]; $columns] {
+                const DATA_TYPE: MTLDataType = MTLDataType::$data_type;
+
+                fn write_scalars(&self, out: &mut Vec<u8>) {
+                    for column in self {
+                        column.write_scalars(out);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+msl_matrix! {
+    f32, 2, 2 => Float2x2;
+    f32, 2, 3 => Float2x3;
+    f32, 2, 4 => Float2x4;
+    f32, 3, 2 => Float3x2;
+    f32, 3, 3 => Float3x3;
+    f32, 3, 4 => Float3x4;
+    f32, 4, 2 => Float4x2;
+    f32, 4, 3 => Float4x3;
+    f32, 4, 4 => Float4x4;
+    Half, 2, 2 => Half2x2;
+    Half, 2, 3 => Half2x3;
+    Half, 2, 4 => Half2x4;
+    Half, 3, 2 => Half3x2;
+    Half, 3, 3 => Half3x3;
+    Half, 3, 4 => Half3x4;
+    Half, 4, 2 => Half4x2;
+    Half, 4, 3 => Half4x3;
+    Half, 4, 4 => Half4x4;
+}
+
+/// Lays out values the way MSL places struct members in a buffer.
+///
+/// Every member is aligned to the alignment of its type and three-component vectors, including
+/// matrix columns, take the space of four. The finished struct is padded to a multiple of its
+/// largest member alignment, so it can be passed to `set_bytes` or stored in arrays as is.
+/// Each `write_*` method returns the offset the value was placed at.
+///
+/// ```
+/// use metal::UniformWriter;
+///
+/// // struct Light { float3 direction; float intensity; packed_float3 color; float4x4 view; };
+/// let mut writer = UniformWriter::new();
+/// assert_eq!(writer.write(&[0.0f32, -1.0, 0.0]), 0);
+/// assert_eq!(writer.write(&2.5f32), 16);
+/// assert_eq!(writer.write_packed(&[1.0f32, 1.0, 1.0]), 20);
+/// assert_eq!(writer.write(&[[0.0f32; 4]; 4]), 32);
+/// assert_eq!((writer.size(), writer.alignment(), writer.padding()), (96, 16, 4));
+/// let bytes = writer.finish();
+/// assert_eq!(bytes.len(), 96);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UniformWriter {
+    bytes: Vec<u8>,
+    alignment: usize,
+    padding: usize,
+}
+
+impl Default for UniformWriter {
+    fn default() -> Self {
+        UniformWriter {
+            bytes: Vec::new(),
+            alignment: 1,
+            padding: 0,
+        }
+    }
+}
+
+impl UniformWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes a scalar, vector or matrix with its MSL alignment and padding.
+    pub fn write<T: MslValue>(&mut self, value: &T) -> usize {
+        let data_type = T::DATA_TYPE;
+        let alignment = data_type.alignment().unwrap() as usize;
+        let rows = data_type.component_count().unwrap() as usize;
+        let scalar_size = data_type.scalar_type().unwrap().size() as usize;
+
+        let mut scalars = Vec::new();
+        value.write_scalars(&mut scalars);
+        let offset = self.align_to(alignment);
+        for column in scalars.chunks(rows * scalar_size) {
+            self.bytes.extend_from_slice(column);
+            self.pad(alignment - column.len());
+        }
+        offset
+    }
+
+    /// Writes a vector as `packed_floatN` and friends: aligned to its scalar, with no padding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` is a matrix, which MSL has no packed form of.
+    pub fn write_packed<T: MslValue>(&mut self, value: &T) -> usize {
+        let data_type = T::DATA_TYPE;
+        assert!(!data_type.is_matrix(), "{:?} cannot be packed", data_type);
+        let scalar_size = data_type.scalar_type().unwrap().size() as usize;
+
+        let offset = self.align_to(scalar_size);
+        value.write_scalars(&mut self.bytes);
+        offset
+    }
+
+    /// Writes an array, with an element stride equal to the element's MSL size.
+    pub fn write_array<T: MslValue>(&mut self, values: &[T]) -> usize {
+        let offset = self.align_to(T::DATA_TYPE.alignment().unwrap() as usize);
+        for value in values {
+            self.write(value);
+        }
+        offset
+    }
+
+    /// Writes a nested struct whose members are written by `members`.
+    ///
+    /// Offsets inside the closure are relative to the start of the nested struct. Calling this
+    /// repeatedly writes an array of structs.
+    pub fn write_struct<F: FnOnce(&mut UniformWriter)>(&mut self, members: F) -> usize {
+        let mut nested = UniformWriter::new();
+        members(&mut nested);
+        let alignment = nested.alignment;
+        let padding = nested.padding + nested.size() - nested.bytes.len();
+
+        let offset = self.align_to(alignment);
+        self.bytes.extend(nested.finish());
+        self.padding += padding;
+        offset
+    }
+
+    /// Writes bytes that are already laid out, e.g. a `#[repr(C)]` value matching MSL.
+    pub fn write_bytes(&mut self, bytes: &[u8], alignment: usize) -> usize {
+        let offset = self.align_to(alignment);
+        self.bytes.extend_from_slice(bytes);
+        offset
+    }
+
+    /// Inserts padding until the end is a multiple of `alignment`, which also becomes a lower
+    /// bound for the struct alignment. Returns the new end.
+    pub fn align_to(&mut self, alignment: usize) -> usize {
+        assert!(
+            alignment.is_power_of_two(),
+            "alignment must be a power of two"
+        );
+        self.alignment = self.alignment.max(alignment);
+        let offset = align_up(self.bytes.len(), alignment);
+        self.pad(offset - self.bytes.len());
+        offset
+    }
+
+    /// The end of the last member written.
+    pub fn offset(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// The largest alignment of any member, at least 1.
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    /// The size of the struct, including tail padding.
+    pub fn size(&self) -> usize {
+        align_up(self.bytes.len(), self.alignment)
+    }
+
+    /// The bytes of `size` that belong to no member.
+    pub fn padding(&self) -> usize {
+        self.padding + self.size() - self.bytes.len()
+    }
+
+    /// Returns the struct bytes, padded to `size`.
+    pub fn finish(mut self) -> Vec<u8> {
+        let size = self.size();
+        self.bytes.resize(size, 0);
+        self.bytes
+    }
+
+    /// Copies the padded struct into `buffer` at `offset`, and flushes the range if the buffer
+    /// is managed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the struct does not fit in the buffer.
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn write_to_buffer(&self, buffer: &BufferRef, offset: NSUInteger) {
+        let size = self.size();
+        assert!(
+            offset + size as NSUInteger <= buffer.length(),
+            "{} bytes at offset {} exceed a buffer of {} bytes",
+            size,
+            offset,
+            buffer.length()
+        );
+        unsafe {
+            let target = (buffer.contents() as *mut u8).add(offset as usize);
+            std::ptr::copy_nonoverlapping(self.bytes.as_ptr(), target, self.bytes.len());
+            std::ptr::write_bytes(target.add(self.bytes.len()), 0, size - self.bytes.len());
+        }
+        if buffer.storage_mode() == MTLStorageMode::Managed {
+            buffer.did_modify_range(NSRange::new(offset, size as NSUInteger));
+        }
+    }
+
+    fn pad(&mut self, count: usize) {
+        self.bytes.resize(self.bytes.len() + count, 0);
+        self.padding += count;
+    }
+}
+
+fn align_up(offset: usize, alignment: usize) -> usize {
+    (offset + alignment - 1) & !(alignment - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floats(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn half3_is_padded_to_four_halves() {
+        // struct { half3 a; half b; };
+        let mut writer = UniformWriter::new();
+        assert_eq!(writer.write(&[Half(1.0), Half(2.0), Half(3.0)]), 0);
+        assert_eq!(writer.offset(), 8);
+        assert_eq!(writer.write(&Half(-2.0)), 8);
+        assert_eq!(
+            (writer.size(), writer.alignment(), writer.padding()),
+            (16, 8, 8)
+        );
+        assert_eq!(
+            writer.finish(),
+            [0x00, 0x3c, 0x00, 0x40, 0x00, 0x42, 0, 0, 0x00, 0xc0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn float3x3_columns_are_padded() {
+        // struct { float a; float3x3 b; };
+        let mut writer = UniformWriter::new();
+        assert_eq!(writer.write(&1.0f32), 0);
+        let matrix = [[2.0f32, 3.0, 4.0], [5.0, 6.0, 7.0], [8.0, 9.0, 10.0]];
+        assert_eq!(writer.write(&matrix), 16);
+        assert_eq!(
+            (writer.size(), writer.alignment(), writer.padding()),
+            (64, 16, 24)
+        );
+        assert_eq!(
+            floats(&writer.finish()),
+            [1.0, 0.0, 0.0, 0.0, 2.0, 3.0, 4.0, 0.0, 5.0, 6.0, 7.0, 0.0, 8.0, 9.0, 10.0, 0.0]
+        );
+
+        // struct { half3x3 a; };
+        let mut writer = UniformWriter::new();
+        writer.write(&[[Half(0.0); 3]; 3]);
+        assert_eq!(
+            (writer.size(), writer.alignment(), writer.padding()),
+            (24, 8, 6)
+        );
+    }
+
+    #[test]
+    fn float3_arrays_have_a_stride_of_16() {
+        // struct { half a; float3 b[3]; };
+        let mut writer = UniformWriter::new();
+        writer.write(&Half(0.0));
+        let values = [[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]];
+        assert_eq!(writer.write_array(&values), 16);
+        assert_eq!(
+            (writer.size(), writer.alignment(), writer.padding()),
+            (64, 16, 26)
+        );
+        let bytes = writer.finish();
+        for (index, value) in values.iter().enumerate() {
+            let start = 16 + 16 * index;
+            assert_eq!(floats(&bytes[start..start + 12]), value);
+            assert_eq!(bytes[start + 12..start + 16], [0; 4]);
+        }
+
+        // An empty array still aligns the struct.
+        let mut writer = UniformWriter::new();
+        writer.write(&1u8);
+        assert_eq!(writer.write_array::<[f32; 3]>(&[]), 16);
+        assert_eq!((writer.size(), writer.alignment()), (16, 16));
+    }
+
+    #[test]
+    fn nested_structs_are_aligned_and_tail_padded() {
+        // struct Inner { float3 a; float b; half c; };
+        // struct { char a; Inner b; ushort c; Inner d[2]; };
+        let inner = |writer: &mut UniformWriter| {
+            assert_eq!(writer.write(&[1.0f32, 2.0, 3.0]), 0);
+            assert_eq!(writer.write(&4.0f32), 16);
+            assert_eq!(writer.write(&Half(5.0)), 20);
+        };
+        let mut writer = UniformWriter::new();
+        assert_eq!(writer.write(&7u8), 0);
+        assert_eq!(writer.write_struct(inner), 16);
+        assert_eq!(writer.offset(), 48);
+        // The tail padding of `Inner` is not reused by the next member.
+        assert_eq!(writer.write(&8u16), 48);
+        assert_eq!(writer.write_struct(inner), 64);
+        assert_eq!(writer.write_struct(inner), 96);
+        assert_eq!(
+            (writer.size(), writer.alignment(), writer.padding()),
+            (128, 16, 15 + 14 + 14 + 14 + 14)
+        );
+
+        let bytes = writer.finish();
+        assert_eq!(bytes[0], 7);
+        assert_eq!(floats(&bytes[16..36]), [1.0, 2.0, 3.0, 0.0, 4.0]);
+        assert_eq!(bytes[36..38], f32_to_f16(5.0).to_le_bytes());
+        assert_eq!(bytes[38..48], [0; 10]);
+        assert_eq!(bytes[48..50], 8u16.to_le_bytes());
+        assert_eq!(bytes[64..128], bytes[16..48].repeat(2)[..]);
+    }
+
+    #[test]
+    fn packed_vectors_are_scalar_aligned() {
+        // struct { half a; packed_float3 b; packed_half3 c; };
+        let mut writer = UniformWriter::new();
+        writer.write(&Half(0.0));
+        assert_eq!(writer.write_packed(&[1.0f32, 2.0, 3.0]), 4);
+        assert_eq!(writer.write_packed(&[Half(1.0); 3]), 16);
+        assert_eq!(
+            (writer.size(), writer.alignment(), writer.padding()),
+            (24, 4, 4)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Float4x4 cannot be packed")]
+    fn matrices_cannot_be_packed() {
+        UniformWriter::new().write_packed(&[[0.0f32; 4]; 4]);
+    }
+}
@@ -7,6 +7,8 @@
 
 use super::NSUInteger;
 
+use std::{collections::BTreeMap, error::Error, fmt};
+
 #[repr(u64)]
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -65,6 +67,381 @@ pub enum MTLVertexFormat {
     Half = 53,
 }
 
+/// The scalar type a vertex format stores its components as.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum VertexScalarType {
+    UChar,
+    Char,
+    UShort,
+    Short,
+    Half,
+    Float,
+    Int,
+    UInt,
+    /// Three 10-bit and one 2-bit unsigned components packed into 32 bits.
+    UInt1010102,
+    /// Three 10-bit and one 2-bit signed components packed into 32 bits.
+    Int1010102,
+}
+
+impl VertexScalarType {
+    /// Size in bytes of one scalar; the packed types count as a single 32-bit scalar.
+    pub const fn size(&self) -> u32 {
+        match self {
+            VertexScalarType::UChar | VertexScalarType::Char => 1,
+            VertexScalarType::UShort | VertexScalarType::Short | VertexScalarType::Half => 2,
+            VertexScalarType::Float
+            | VertexScalarType::Int
+            | VertexScalarType::UInt
+            | VertexScalarType::UInt1010102
+            | VertexScalarType::Int1010102 => 4,
+        }
+    }
+
+    pub const fn is_packed(&self) -> bool {
+        matches!(
+            self,
+            VertexScalarType::UInt1010102 | VertexScalarType::Int1010102
+        )
+    }
+}
+
+/// Static description of a `MTLVertexFormat`, as returned by `MTLVertexFormat::info`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct VertexFormatInfo {
+    /// Size in bytes of one attribute value.
+    pub size: u32,
+    pub components: u32,
+    pub scalar_type: VertexScalarType,
+    /// Whether integer components are mapped to `[0, 1]` (unsigned) or `[-1, 1]` (signed).
+    pub normalized: bool,
+    /// Whether the red and blue components are stored swapped (`UChar4Normalized_BGRA`).
+    pub bgra: bool,
+}
+
+impl VertexFormatInfo {
+    const fn new(scalar_type: VertexScalarType, components: u32, normalized: bool) -> Self {
+        VertexFormatInfo {
+            size: if scalar_type.is_packed() {
+                scalar_type.size()
+            } else {
+                scalar_type.size() * components
+            },
+            components,
+            scalar_type,
+            normalized,
+            bgra: false,
+        }
+    }
+
+    const fn bgra(mut self) -> Self {
+        self.bgra = true;
+        self
+    }
+}
+
+impl MTLVertexFormat {
+    /// Returns the size, component layout and scalar type of this format, or `None` for
+    /// `MTLVertexFormat::Invalid`.
+    pub fn info(&self) -> Option<VertexFormatInfo> {
+        use MTLVertexFormat::*;
+        let info = match self {
+            Invalid => return None,
+            UChar2 => VertexFormatInfo::new(VertexScalarType::UChar, 2, false),
+            UChar3 => VertexFormatInfo::new(VertexScalarType::UChar, 3, false),
+            UChar4 => VertexFormatInfo::new(VertexScalarType::UChar, 4, false),
+            Char2 => VertexFormatInfo::new(VertexScalarType::Char, 2, false),
+            Char3 => VertexFormatInfo::new(VertexScalarType::Char, 3, false),
+            Char4 => VertexFormatInfo::new(VertexScalarType::Char, 4, false),
+            UChar2Normalized => VertexFormatInfo::new(VertexScalarType::UChar, 2, true),
+            UChar3Normalized => VertexFormatInfo::new(VertexScalarType::UChar, 3, true),
+            UChar4Normalized => VertexFormatInfo::new(VertexScalarType::UChar, 4, true),
+            Char2Normalized => VertexFormatInfo::new(VertexScalarType::Char, 2, true),
+            Char3Normalized => VertexFormatInfo::new(VertexScalarType::Char, 3, true),
+            Char4Normalized => VertexFormatInfo::new(VertexScalarType::Char, 4, true),
+            UShort2 => VertexFormatInfo::new(VertexScalarType::UShort, 2, false),
+            UShort3 => VertexFormatInfo::new(VertexScalarType::UShort, 3, false),
+            UShort4 => VertexFormatInfo::new(VertexScalarType::UShort, 4, false),
+            Short2 => VertexFormatInfo::new(VertexScalarType::Short, 2, false),
+            Short3 => VertexFormatInfo::new(VertexScalarType::Short, 3, false),
+            Short4 => VertexFormatInfo::new(VertexScalarType::Short, 4, false),
+            UShort2Normalized => VertexFormatInfo::new(VertexScalarType::UShort, 2, true),
+            UShort3Normalized => VertexFormatInfo::new(VertexScalarType::UShort, 3, true),
+            UShort4Normalized => VertexFormatInfo::new(VertexScalarType::UShort, 4, true),
+            Short2Normalized => VertexFormatInfo::new(VertexScalarType::Short, 2, true),
+            Short3Normalized => VertexFormatInfo::new(VertexScalarType::Short, 3, true),
+            Short4Normalized => VertexFormatInfo::new(VertexScalarType::Short, 4, true),
+            Half2 => VertexFormatInfo::new(VertexScalarType::Half, 2, false),
+            Half3 => VertexFormatInfo::new(VertexScalarType::Half, 3, false),
+            Half4 => VertexFormatInfo::new(VertexScalarType::Half, 4, false),
+            Float => VertexFormatInfo::new(VertexScalarType::Float, 1, false),
+            Float2 => VertexFormatInfo::new(VertexScalarType::Float, 2, false),
+            Float3 => VertexFormatInfo::new(VertexScalarType::Float, 3, false),
+            Float4 => VertexFormatInfo::new(VertexScalarType::Float, 4, false),
+            Int => VertexFormatInfo::new(VertexScalarType::Int, 1, false),
+            Int2 => VertexFormatInfo::new(VertexScalarType::Int, 2, false),
+            Int3 => VertexFormatInfo::new(VertexScalarType::Int, 3, false),
+            Int4 => VertexFormatInfo::new(VertexScalarType::Int, 4, false),
+            UInt => VertexFormatInfo::new(VertexScalarType::UInt, 1, false),
+            UInt2 => VertexFormatInfo::new(VertexScalarType::UInt, 2, false),
+            UInt3 => VertexFormatInfo::new(VertexScalarType::UInt, 3, false),
+            UInt4 => VertexFormatInfo::new(VertexScalarType::UInt, 4, false),
+            Int1010102Normalized => VertexFormatInfo::new(VertexScalarType::Int1010102, 4, true),
+            UInt1010102Normalized => VertexFormatInfo::new(VertexScalarType::UInt1010102, 4, true),
+            UChar4Normalized_BGRA => VertexFormatInfo::new(VertexScalarType::UChar, 4, true).bgra(),
+            UChar => VertexFormatInfo::new(VertexScalarType::UChar, 1, false),
+            Char => VertexFormatInfo::new(VertexScalarType::Char, 1, false),
+            UCharNormalized => VertexFormatInfo::new(VertexScalarType::UChar, 1, true),
+            CharNormalized => VertexFormatInfo::new(VertexScalarType::Char, 1, true),
+            UShort => VertexFormatInfo::new(VertexScalarType::UShort, 1, false),
+            Short => VertexFormatInfo::new(VertexScalarType::Short, 1, false),
+            UShortNormalized => VertexFormatInfo::new(VertexScalarType::UShort, 1, true),
+            ShortNormalized => VertexFormatInfo::new(VertexScalarType::Short, 1, true),
+            Half => VertexFormatInfo::new(VertexScalarType::Half, 1, false),
+        };
+        Some(info)
+    }
+
+    /// Size in bytes of one attribute value, or 0 for `MTLVertexFormat::Invalid`.
+    pub fn size(&self) -> u32 {
+        self.info().map_or(0, |info| info.size)
+    }
+
+    /// Alignment in bytes the attribute offset has to satisfy, or 0 for
+    /// `MTLVertexFormat::Invalid`.
+    pub fn alignment(&self) -> u32 {
+        self.info().map_or(0, |info| info.scalar_type.size())
+    }
+
+    /// Number of components, or 0 for `MTLVertexFormat::Invalid`.
+    pub fn component_count(&self) -> u32 {
+        self.info().map_or(0, |info| info.components)
+    }
+
+    pub fn scalar_type(&self) -> Option<VertexScalarType> {
+        self.info().map(|info| info.scalar_type)
+    }
+
+    pub fn is_normalized(&self) -> bool {
+        self.info().is_some_and(|info| info.normalized)
+    }
+
+    /// Writes `value` into `bytes` the way the GPU expects to read this format.
+    ///
+    /// Components beyond `component_count` are ignored. Normalized formats clamp to their
+    /// range; all integer formats round to nearest and saturate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the format is `Invalid` or `bytes` is shorter than `size`.
+    pub fn encode(&self, value: [f32; 4], bytes: &mut [u8]) {
+        let info = self.info().expect("cannot encode MTLVertexFormat::Invalid");
+        let bytes = &mut bytes[..info.size as usize];
+        let value = if info.bgra {
+            [value[2], value[1], value[0], value[3]]
+        } else {
+            value
+        };
+
+        if info.scalar_type.is_packed() {
+            let signed = info.scalar_type == VertexScalarType::Int1010102;
+            let mut packed = 0u32;
+            for (i, &component) in value.iter().enumerate() {
+                let bits = if i == 3 { 2 } else { 10 };
+                let mask = (1u32 << bits) - 1;
+                let raw = if signed {
+                    snorm(component, ((1 << (bits - 1)) - 1) as f32) as i32 as u32
+                } else {
+                    unorm(component, mask as f32) as u32
+                };
+                packed |= (raw & mask) << (i * 10);
+            }
+            bytes.copy_from_slice(&packed.to_le_bytes());
+            return;
+        }
+
+        let stride = info.scalar_type.size() as usize;
+        for (&component, out) in value.iter().zip(bytes.chunks_exact_mut(stride)) {
+            let normalized = info.normalized;
+            match info.scalar_type {
+                VertexScalarType::UChar => {
+                    out[0] = if normalized {
+                        unorm(component, u8::MAX as f32)
+                    } else {
+                        component.round()
+                    } as u8;
+                }
+                VertexScalarType::Char => {
+                    let value = if normalized {
+                        snorm(component, i8::MAX as f32)
+                    } else {
+                        component.round()
+                    };
+                    out[0] = value as i8 as u8;
+                }
+                VertexScalarType::UShort => {
+                    let value = if normalized {
+                        unorm(component, u16::MAX as f32)
+                    } else {
+                        component.round()
+                    };
+                    out.copy_from_slice(&(value as u16).to_le_bytes());
+                }
+                VertexScalarType::Short => {
+                    let value = if normalized {
+                        snorm(component, i16::MAX as f32)
+                    } else {
+                        component.round()
+                    };
+                    out.copy_from_slice(&(value as i16).to_le_bytes());
+                }
+                VertexScalarType::Half => out.copy_from_slice(&f32_to_f16(component).to_le_bytes()),
+                VertexScalarType::Float => out.copy_from_slice(&component.to_le_bytes()),
+                VertexScalarType::Int => {
+                    out.copy_from_slice(&(component.round() as i32).to_le_bytes())
+                }
+                VertexScalarType::UInt => {
+                    out.copy_from_slice(&(component.round() as u32).to_le_bytes())
+                }
+                VertexScalarType::UInt1010102 | VertexScalarType::Int1010102 => unreachable!(),
+            }
+        }
+    }
+
+    /// Reads a value stored in this format, filling missing components the way vertex
+    /// fetch does: `0` for green and blue, `1` for alpha.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the format is `Invalid` or `bytes` is shorter than `size`.
+    pub fn decode(&self, bytes: &[u8]) -> [f32; 4] {
+        let info = self.info().expect("cannot decode MTLVertexFormat::Invalid");
+        let bytes = &bytes[..info.size as usize];
+        let mut value = [0.0, 0.0, 0.0, 1.0];
+
+        if info.scalar_type.is_packed() {
+            let signed = info.scalar_type == VertexScalarType::Int1010102;
+            let packed = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            for (i, component) in value.iter_mut().enumerate() {
+                let bits = if i == 3 { 2 } else { 10 };
+                let raw = (packed >> (i * 10)) & ((1 << bits) - 1);
+                *component = if signed {
+                    let extended = ((raw << (32 - bits)) as i32) >> (32 - bits);
+                    (extended as f32 / ((1 << (bits - 1)) - 1) as f32).max(-1.0)
+                } else {
+                    raw as f32 / ((1 << bits) - 1) as f32
+                };
+            }
+            return value;
+        }
+
+        let stride = info.scalar_type.size() as usize;
+        for (component, data) in value.iter_mut().zip(bytes.chunks_exact(stride)) {
+            let normalized = info.normalized;
+            *component = match info.scalar_type {
+                VertexScalarType::UChar if normalized => data[0] as f32 / u8::MAX as f32,
+                VertexScalarType::UChar => data[0] as f32,
+                VertexScalarType::Char if normalized => {
+                    (data[0] as i8 as f32 / i8::MAX as f32).max(-1.0)
+                }
+                VertexScalarType::Char => data[0] as i8 as f32,
+                VertexScalarType::UShort => {
+                    let raw = u16::from_le_bytes([data[0], data[1]]) as f32;
+                    if normalized {
+                        raw / u16::MAX as f32
+                    } else {
+                        raw
+                    }
+                }
+                VertexScalarType::Short => {
+                    let raw = i16::from_le_bytes([data[0], data[1]]) as f32;
+                    if normalized {
+                        (raw / i16::MAX as f32).max(-1.0)
+                    } else {
+                        raw
+                    }
+                }
+                VertexScalarType::Half => f16_to_f32(u16::from_le_bytes([data[0], data[1]])),
+                VertexScalarType::Float => f32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+                VertexScalarType::Int => {
+                    i32::from_le_bytes([data[0], data[1], data[2], data[3]]) as f32
+                }
+                VertexScalarType::UInt => {
+                    u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as f32
+                }
+                VertexScalarType::UInt1010102 | VertexScalarType::Int1010102 => unreachable!(),
+            };
+        }
+
+        if info.bgra {
+            value.swap(0, 2);
+        }
+        value
+    }
+}
+
+fn unorm(value: f32, max: f32) -> f32 {
+    (value.clamp(0.0, 1.0) * max).round()
+}
+
+fn snorm(value: f32, max: f32) -> f32 {
+    (value.clamp(-1.0, 1.0) * max).round()
+}
+
+/// Converts to IEEE 754 half precision, rounding to nearest even.
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let rounded = mantissa + (1 << (shift - 1)) - 1 + ((mantissa >> shift) & 1);
+        return sign | (rounded >> shift) as u16;
+    }
+
+    let rounded = mantissa + 0xfff + ((mantissa >> 13) & 1);
+    let half = ((exponent as u32) << 10) + (rounded >> 13);
+    if half >= 0x7c00 {
+        sign | 0x7c00
+    } else {
+        sign | half as u16
+    }
+}
+
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+
+    match exponent {
+        0 => {
+            let magnitude = mantissa as f32 / (1 << 24) as f32;
+            if sign != 0 {
+                -magnitude
+            } else {
+                magnitude
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+    }
+}
+
 #[repr(u64)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum MTLVertexStepFunction {
@@ -75,14 +452,477 @@ pub enum MTLVertexStepFunction {
     PerPatchControlPoint = 4,
 }
 
+/// A vertex attribute of a `VertexLayout`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct VertexLayoutAttribute {
+    pub format: MTLVertexFormat,
+    pub offset: NSUInteger,
+    pub buffer_index: NSUInteger,
+}
+
+/// A vertex buffer layout of a `VertexLayout`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct VertexBufferLayout {
+    pub stride: NSUInteger,
+    pub step_function: MTLVertexStepFunction,
+    pub step_rate: NSUInteger,
+}
+
+impl VertexBufferLayout {
+    pub fn per_vertex(stride: NSUInteger) -> Self {
+        VertexBufferLayout {
+            stride,
+            step_function: MTLVertexStepFunction::PerVertex,
+            step_rate: 1,
+        }
+    }
+
+    pub fn per_instance(stride: NSUInteger, step_rate: NSUInteger) -> Self {
+        VertexBufferLayout {
+            stride,
+            step_function: MTLVertexStepFunction::PerInstance,
+            step_rate,
+        }
+    }
+}
+
+/// A vertex layout validation failure reported by `VertexLayout::validate`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum VertexLayoutError {
+    AttributeIndexOutOfRange {
+        attribute: NSUInteger,
+        limit: NSUInteger,
+    },
+    BufferIndexOutOfRange {
+        buffer_index: NSUInteger,
+        limit: NSUInteger,
+    },
+    InvalidFormat {
+        attribute: NSUInteger,
+    },
+    MissingBufferLayout {
+        attribute: NSUInteger,
+        buffer_index: NSUInteger,
+    },
+    MisalignedOffset {
+        attribute: NSUInteger,
+        offset: NSUInteger,
+        alignment: NSUInteger,
+    },
+    AttributeExceedsStride {
+        attribute: NSUInteger,
+        end: NSUInteger,
+        stride: NSUInteger,
+    },
+    OffsetOverflow {
+        attribute: NSUInteger,
+        offset: NSUInteger,
+    },
+    UnalignedStride {
+        buffer_index: NSUInteger,
+        stride: NSUInteger,
+    },
+    InvalidStepRate {
+        buffer_index: NSUInteger,
+        step_function: MTLVertexStepFunction,
+        step_rate: NSUInteger,
+    },
+}
+
+impl fmt::Display for VertexLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            VertexLayoutError::AttributeIndexOutOfRange { attribute, limit } => write!(
+                f,
+                "attribute {} is out of range (limit {})",
+                attribute, limit
+            ),
+            VertexLayoutError::BufferIndexOutOfRange {
+                buffer_index,
+                limit,
+            } => write!(
+                f,
+                "buffer index {} is out of range (limit {})",
+                buffer_index, limit
+            ),
+            VertexLayoutError::InvalidFormat { attribute } => {
+                write!(f, "attribute {} has an invalid format", attribute)
+            }
+            VertexLayoutError::MissingBufferLayout {
+                attribute,
+                buffer_index,
+            } => write!(
+                f,
+                "attribute {} reads buffer {} which has no layout",
+                attribute, buffer_index
+            ),
+            VertexLayoutError::MisalignedOffset {
+                attribute,
+                offset,
+                alignment,
+            } => write!(
+                f,
+                "attribute {} offset {} is not a multiple of {}",
+                attribute, offset, alignment
+            ),
+            VertexLayoutError::AttributeExceedsStride {
+                attribute,
+                end,
+                stride,
+            } => write!(
+                f,
+                "attribute {} ends at byte {}, past the stride of {}",
+                attribute, end, stride
+            ),
+            VertexLayoutError::OffsetOverflow { attribute, offset } => write!(
+                f,
+                "attribute {} offset {} overflows the buffer address space",
+                attribute, offset
+            ),
+            VertexLayoutError::UnalignedStride {
+                buffer_index,
+                stride,
+            } => write!(
+                f,
+                "buffer {} stride {} is not a multiple of 4",
+                buffer_index, stride
+            ),
+            VertexLayoutError::InvalidStepRate {
+                buffer_index,
+                step_function,
+                step_rate,
+            } => write!(
+                f,
+                "buffer {} step rate {} is not valid for {:?}",
+                buffer_index, step_rate, step_function
+            ),
+        }
+    }
+}
+
+impl Error for VertexLayoutError {}
+
+/// A pure Rust vertex descriptor that can be validated before it is turned into a
+/// `VertexDescriptor`.
+///
+/// Attributes are keyed by attribute index and buffer layouts by buffer index.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VertexLayout {
+    pub attributes: BTreeMap<NSUInteger, VertexLayoutAttribute>,
+    pub layouts: BTreeMap<NSUInteger, VertexBufferLayout>,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_attribute(&mut self, index: NSUInteger, attribute: VertexLayoutAttribute) {
+        self.attributes.insert(index, attribute);
+    }
+
+    pub fn set_layout(&mut self, buffer_index: NSUInteger, layout: VertexBufferLayout) {
+        self.layouts.insert(buffer_index, layout);
+    }
+
+    /// Checks the rules Metal's validation layer enforces on vertex descriptors:
+    ///
+    /// - attribute and buffer indices are below `max_vertex_attributes`, which should be the
+    ///   `max_vertex_attributes` of the target `MTLFeatureSet` or `MTLGPUFamily`;
+    /// - every attribute has a valid format and reads a buffer that has a layout;
+    /// - attribute offsets are aligned to their format and fit within the stride;
+    /// - strides are multiples of 4;
+    /// - the step rate is 0 for `Constant`, at least 1 for `PerInstance` and 1 otherwise.
+    ///
+    /// Attributes of `Constant` layouts are not checked against the stride, since every
+    /// vertex reads the same element.
+    pub fn validate(&self, max_vertex_attributes: NSUInteger) -> Result<(), VertexLayoutError> {
+        for (&buffer_index, layout) in &self.layouts {
+            if buffer_index >= max_vertex_attributes {
+                return Err(VertexLayoutError::BufferIndexOutOfRange {
+                    buffer_index,
+                    limit: max_vertex_attributes,
+                });
+            }
+            if layout.stride % 4 != 0 {
+                return Err(VertexLayoutError::UnalignedStride {
+                    buffer_index,
+                    stride: layout.stride,
+                });
+            }
+            let step_rate_valid = match layout.step_function {
+                MTLVertexStepFunction::Constant => layout.step_rate == 0,
+                MTLVertexStepFunction::PerInstance => layout.step_rate >= 1,
+                MTLVertexStepFunction::PerVertex
+                | MTLVertexStepFunction::PerPatch
+                | MTLVertexStepFunction::PerPatchControlPoint => layout.step_rate == 1,
+            };
+            if !step_rate_valid {
+                return Err(VertexLayoutError::InvalidStepRate {
+                    buffer_index,
+                    step_function: layout.step_function,
+                    step_rate: layout.step_rate,
+                });
+            }
+        }
+
+        for (&attribute, desc) in &self.attributes {
+            if attribute >= max_vertex_attributes {
+                return Err(VertexLayoutError::AttributeIndexOutOfRange {
+                    attribute,
+                    limit: max_vertex_attributes,
+                });
+            }
+            if desc.buffer_index >= max_vertex_attributes {
+                return Err(VertexLayoutError::BufferIndexOutOfRange {
+                    buffer_index: desc.buffer_index,
+                    limit: max_vertex_attributes,
+                });
+            }
+            if desc.format == MTLVertexFormat::Invalid {
+                return Err(VertexLayoutError::InvalidFormat { attribute });
+            }
+            let layout = match self.layouts.get(&desc.buffer_index) {
+                Some(layout) => layout,
+                None => {
+                    return Err(VertexLayoutError::MissingBufferLayout {
+                        attribute,
+                        buffer_index: desc.buffer_index,
+                    })
+                }
+            };
+            let alignment = desc.format.alignment() as NSUInteger;
+            if desc.offset % alignment != 0 {
+                return Err(VertexLayoutError::MisalignedOffset {
+                    attribute,
+                    offset: desc.offset,
+                    alignment,
+                });
+            }
+            let end = desc
+                .offset
+                .checked_add(desc.format.size() as NSUInteger)
+                .ok_or(VertexLayoutError::OffsetOverflow {
+                    attribute,
+                    offset: desc.offset,
+                })?;
+            if layout.step_function != MTLVertexStepFunction::Constant && end > layout.stride {
+                return Err(VertexLayoutError::AttributeExceedsStride {
+                    attribute,
+                    end,
+                    stride: layout.stride,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the layout and copies it into a new `VertexDescriptor`.
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn to_descriptor<'a>(
+        &self,
+        max_vertex_attributes: NSUInteger,
+    ) -> Result<&'a VertexDescriptorRef, VertexLayoutError> {
+        let descriptor = VertexDescriptor::new();
+        self.apply_to(descriptor, max_vertex_attributes)?;
+        Ok(descriptor)
+    }
+
+    /// Validates the layout and copies its attributes and buffer layouts into `descriptor`.
+    ///
+    /// Entries of `descriptor` that this layout does not mention are left untouched, so
+    /// layouts of several vertex buffers can be combined into one descriptor.
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn apply_to(
+        &self,
+        descriptor: &VertexDescriptorRef,
+        max_vertex_attributes: NSUInteger,
+    ) -> Result<(), VertexLayoutError> {
+        self.validate(max_vertex_attributes)?;
+
+        for (&index, attribute) in &self.attributes {
+            let desc = descriptor.attributes().object_at(index).unwrap();
+            desc.set_format(attribute.format);
+            desc.set_offset(attribute.offset);
+            desc.set_buffer_index(attribute.buffer_index);
+        }
+        for (&buffer_index, layout) in &self.layouts {
+            let desc = descriptor.layouts().object_at(buffer_index).unwrap();
+            desc.set_stride(layout.stride);
+            desc.set_step_function(layout.step_function);
+            desc.set_step_rate(layout.step_rate);
+        }
+        Ok(())
+    }
+}
+
+/// Normalized integer vertex attribute, read by the shader as a float in `[0, 1]` for
+/// unsigned and `[-1, 1]` for signed types.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Normalized<T>(pub T);
+
+/// Rust types that can be used as fields of a `metal_vertex!` struct.
+pub trait VertexAttributeType {
+    const FORMAT: MTLVertexFormat;
+}
+
+macro_rules! vertex_attribute_types {
+    ($($ty:ty => $format:ident,)*) => {
+        $(
+            impl VertexAttributeType for $ty {
+                const FORMAT: MTLVertexFormat = MTLVertexFormat::$format;
+            }
+        )*
+    };
+}
+
+vertex_attribute_types! {
+    f32 => Float,
+    [f32; 2] => Float2,
+    [f32; 3] => Float3,
+    [f32; 4] => Float4,
+    i32 => Int,
+    [i32; 2] => Int2,
+    [i32; 3] => Int3,
+    [i32; 4] => Int4,
+    u32 => UInt,
+    [u32; 2] => UInt2,
+    [u32; 3] => UInt3,
+    [u32; 4] => UInt4,
+    u8 => UChar,
+    [u8; 2] => UChar2,
+    [u8; 3] => UChar3,
+    [u8; 4] => UChar4,
+    i8 => Char,
+    [i8; 2] => Char2,
+    [i8; 3] => Char3,
+    [i8; 4] => Char4,
+    u16 => UShort,
+    [u16; 2] => UShort2,
+    [u16; 3] => UShort3,
+    [u16; 4] => UShort4,
+    i16 => Short,
+    [i16; 2] => Short2,
+    [i16; 3] => Short3,
+    [i16; 4] => Short4,
+    Normalized<u8> => UCharNormalized,
+    Normalized<[u8; 2]> => UChar2Normalized,
+    Normalized<[u8; 3]> => UChar3Normalized,
+    Normalized<[u8; 4]> => UChar4Normalized,
+    Normalized<i8> => CharNormalized,
+    Normalized<[i8; 2]> => Char2Normalized,
+    Normalized<[i8; 3]> => Char3Normalized,
+    Normalized<[i8; 4]> => Char4Normalized,
+    Normalized<u16> => UShortNormalized,
+    Normalized<[u16; 2]> => UShort2Normalized,
+    Normalized<[u16; 3]> => UShort3Normalized,
+    Normalized<[u16; 4]> => UShort4Normalized,
+    Normalized<i16> => ShortNormalized,
+    Normalized<[i16; 2]> => Short2Normalized,
+    Normalized<[i16; 3]> => Short3Normalized,
+    Normalized<[i16; 4]> => Short4Normalized,
+}
+
+/// A `#[repr(C)]` vertex struct whose layout is known to Metal, usually implemented with
+/// `metal_vertex!`.
+pub trait MetalVertex: Sized {
+    /// Returns the layout of `Self` read from the vertex buffer at `buffer_index`, with
+    /// one attribute per field and a per-vertex stride of `size_of::<Self>()`.
+    fn vertex_layout(buffer_index: NSUInteger) -> VertexLayout;
+
+    /// Validates the layout of `Self` and copies it into `descriptor`.
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    fn fill_vertex_descriptor(
+        descriptor: &VertexDescriptorRef,
+        buffer_index: NSUInteger,
+        max_vertex_attributes: NSUInteger,
+    ) -> Result<(), VertexLayoutError> {
+        Self::vertex_layout(buffer_index).apply_to(descriptor, max_vertex_attributes)
+    }
+}
+
+/// Declares a `#[repr(C)]` vertex struct and implements `MetalVertex` for it.
+///
+/// Every field is annotated with its attribute index and must have a type implementing
+/// `VertexAttributeType`. Offsets and the stride are taken from the Rust layout, so they
+/// always match the struct.
+///
+/// ```
+/// use metal::{metal_vertex, MetalVertex, Normalized};
+///
+/// metal_vertex! {
+///     #[derive(Copy, Clone)]
+///     pub struct Vertex {
+///         #[attribute(0)]
+///         pub position: [f32; 3],
+///         #[attribute(1)]
+///         pub color: Normalized<[u8; 4]>,
+///     }
+/// }
+///
+/// let layout = Vertex::vertex_layout(0);
+/// assert_eq!(layout.attributes[&1].offset, 12);
+/// assert_eq!(layout.layouts[&0].stride, 16);
+/// ```
+#[macro_export]
+macro_rules! metal_vertex {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                #[attribute($index:expr)]
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident : $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr(C)]
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl $crate::MetalVertex for $name {
+            fn vertex_layout(buffer_index: $crate::NSUInteger) -> $crate::VertexLayout {
+                let mut layout = $crate::VertexLayout::new();
+                layout.set_layout(
+                    buffer_index,
+                    $crate::VertexBufferLayout::per_vertex(
+                        ::std::mem::size_of::<$name>() as $crate::NSUInteger,
+                    ),
+                );
+                $(
+                    layout.set_attribute(
+                        $index,
+                        $crate::VertexLayoutAttribute {
+                            format: <$ty as $crate::VertexAttributeType>::FORMAT,
+                            offset: $crate::__field_offset!($name, $field),
+                            buffer_index,
+                        },
+                    );
+                )*
+                layout
+            }
+        }
+    };
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLVertexBufferLayoutDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLVertexBufferLayoutDescriptor;
     pub struct VertexBufferLayoutDescriptor;
     pub struct VertexBufferLayoutDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl VertexBufferLayoutDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -92,6 +932,7 @@ impl VertexBufferLayoutDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl VertexBufferLayoutDescriptorRef {
     pub fn stride(&self) -> NSUInteger {
         unsafe { msg_send![self, stride] }
@@ -118,14 +959,17 @@ impl VertexBufferLayoutDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLVertexBufferLayoutDescriptorArray {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLVertexBufferLayoutDescriptorArray;
     pub struct VertexBufferLayoutDescriptorArray;
     pub struct VertexBufferLayoutDescriptorArrayRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl VertexBufferLayoutDescriptorArrayRef {
     pub fn object_at(&self, index: NSUInteger) -> Option<&VertexBufferLayoutDescriptorRef> {
         unsafe { msg_send![self, objectAtIndexedSubscript: index] }
@@ -143,14 +987,17 @@ impl VertexBufferLayoutDescriptorArrayRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLVertexAttributeDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLVertexAttributeDescriptor;
     pub struct VertexAttributeDescriptor;
     pub struct VertexAttributeDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl VertexAttributeDescriptor {
     pub fn new() -> Self {
         unsafe {
@@ -160,6 +1007,7 @@ impl VertexAttributeDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl VertexAttributeDescriptorRef {
     pub fn format(&self) -> MTLVertexFormat {
         unsafe { msg_send![self, format] }
@@ -186,14 +1034,17 @@ impl VertexAttributeDescriptorRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLVertexAttributeDescriptorArray {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLVertexAttributeDescriptorArray;
     pub struct VertexAttributeDescriptorArray;
     pub struct VertexAttributeDescriptorArrayRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl VertexAttributeDescriptorArrayRef {
     pub fn object_at(&self, index: NSUInteger) -> Option<&VertexAttributeDescriptorRef> {
         unsafe { msg_send![self, objectAtIndexedSubscript: index] }
@@ -211,14 +1062,17 @@ impl VertexAttributeDescriptorArrayRef {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 pub enum MTLVertexDescriptor {}
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 foreign_obj_type! {
     type CType = MTLVertexDescriptor;
     pub struct VertexDescriptor;
     pub struct VertexDescriptorRef;
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl VertexDescriptor {
     pub fn new<'a>() -> &'a VertexDescriptorRef {
         unsafe {
@@ -228,6 +1082,7 @@ impl VertexDescriptor {
     }
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 impl VertexDescriptorRef {
     pub fn layouts(&self) -> &VertexBufferLayoutDescriptorArrayRef {
         unsafe { msg_send![self, layouts] }
@@ -246,3 +1101,407 @@ impl VertexDescriptorRef {
         unsafe { msg_send![self, reset] }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [MTLVertexFormat; 51] = {
+        use MTLVertexFormat::*;
+        [
+            UChar2,
+            UChar3,
+            UChar4,
+            Char2,
+            Char3,
+            Char4,
+            UChar2Normalized,
+            UChar3Normalized,
+            UChar4Normalized,
+            Char2Normalized,
+            Char3Normalized,
+            Char4Normalized,
+            UShort2,
+            UShort3,
+            UShort4,
+            Short2,
+            Short3,
+            Short4,
+            UShort2Normalized,
+            UShort3Normalized,
+            UShort4Normalized,
+            Short2Normalized,
+            Short3Normalized,
+            Short4Normalized,
+            Half2,
+            Half3,
+            Half4,
+            Float,
+            Float2,
+            Float3,
+            Float4,
+            Int,
+            Int2,
+            Int3,
+            Int4,
+            UInt,
+            UInt2,
+            UInt3,
+            UInt4,
+            Int1010102Normalized,
+            UInt1010102Normalized,
+            UChar4Normalized_BGRA,
+            UChar,
+            Char,
+            UCharNormalized,
+            CharNormalized,
+            UShort,
+            Short,
+            UShortNormalized,
+            ShortNormalized,
+            Half,
+        ]
+    };
+
+    /// A value every component of the format can store exactly.
+    fn representable_value(info: VertexFormatInfo) -> [f32; 4] {
+        let signed = matches!(
+            info.scalar_type,
+            VertexScalarType::Char
+                | VertexScalarType::Short
+                | VertexScalarType::Int
+                | VertexScalarType::Int1010102
+        );
+        match (info.scalar_type, info.normalized, signed) {
+            (VertexScalarType::Half, ..) | (VertexScalarType::Float, ..) => {
+                [0.5, -2.0, 3.25, -0.125]
+            }
+            (_, true, true) => [-1.0, 1.0, 0.0, -1.0],
+            (_, true, false) => [1.0, 0.0, 1.0, 0.0],
+            (_, false, true) => [-1.0, 2.0, -3.0, 4.0],
+            (_, false, false) => [1.0, 2.0, 3.0, 4.0],
+        }
+    }
+
+    fn encode(format: MTLVertexFormat, value: [f32; 4]) -> Vec<u8> {
+        let mut bytes = vec![0; format.size() as usize];
+        format.encode(value, &mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        for &format in FORMATS.iter() {
+            let info = format.info().unwrap();
+            let value = representable_value(info);
+            let mut expected = [0.0, 0.0, 0.0, 1.0];
+            expected[..info.components as usize]
+                .copy_from_slice(&value[..info.components as usize]);
+
+            let bytes = encode(format, value);
+            assert_eq!(format.decode(&bytes), expected, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn normalized_formats_saturate() {
+        use MTLVertexFormat::*;
+
+        assert_eq!(
+            encode(UChar4Normalized, [0.5, 2.0, -1.0, 1.0]),
+            [128, 255, 0, 255]
+        );
+        assert_eq!(encode(Char2Normalized, [-2.0, 2.0, 0.0, 0.0]), [0x81, 0x7f]);
+        assert_eq!(
+            encode(UShortNormalized, [7.0, 0.0, 0.0, 0.0]),
+            u16::MAX.to_le_bytes()
+        );
+        assert_eq!(
+            encode(ShortNormalized, [-7.0, 0.0, 0.0, 0.0]),
+            (-i16::MAX).to_le_bytes()
+        );
+        // The most negative integer also decodes to -1.
+        assert_eq!(CharNormalized.decode(&[0x80])[0], -1.0);
+        assert_eq!(ShortNormalized.decode(&i16::MIN.to_le_bytes())[0], -1.0);
+        // Integer formats saturate instead of wrapping.
+        assert_eq!(encode(UChar2, [300.0, -5.0, 0.0, 0.0]), [255, 0]);
+        assert_eq!(encode(Char2, [-200.0, 1.6, 0.0, 0.0]), [0x80, 2]);
+    }
+
+    #[test]
+    fn packed_1010102() {
+        use MTLVertexFormat::*;
+
+        let unsigned = encode(UInt1010102Normalized, [1.0, 0.0, 1.0, 1.0]);
+        assert_eq!(
+            u32::from_le_bytes([unsigned[0], unsigned[1], unsigned[2], unsigned[3]]),
+            0xfff0_03ff
+        );
+
+        let signed = encode(Int1010102Normalized, [-1.0, 1.0, 0.0, -1.0]);
+        assert_eq!(
+            u32::from_le_bytes([signed[0], signed[1], signed[2], signed[3]]),
+            0xc007_fe01
+        );
+
+        // -512 in the red channel clamps to -1, like the 8 and 16-bit signed formats.
+        assert_eq!(
+            Int1010102Normalized.decode(&0x200u32.to_le_bytes()),
+            [-1.0, 0.0, 0.0, 0.0]
+        );
+        assert_eq!(
+            UInt1010102Normalized.decode(&(1u32 << 30).to_le_bytes())[3],
+            1.0 / 3.0
+        );
+    }
+
+    #[test]
+    fn bgra_swizzle() {
+        let format = MTLVertexFormat::UChar4Normalized_BGRA;
+        let red = [1.0, 0.0, 0.0, 1.0];
+        assert_eq!(encode(format, red), [0, 0, 255, 255]);
+        assert_eq!(format.decode(&[0, 0, 255, 255]), red);
+    }
+
+    #[test]
+    fn f32_to_f16_rounds_to_nearest_even() {
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(-2.0), 0xc000);
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+        // Halfway cases pick the even mantissa.
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11)), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 * 2f32.powi(-11)), 0x3c02);
+        assert_eq!(f32_to_f16(65519.0), 0x7bff);
+        assert_eq!(f32_to_f16(65520.0), 0x7c00);
+    }
+
+    #[test]
+    fn f32_to_f16_subnormals() {
+        assert_eq!(f32_to_f16(2f32.powi(-14)), 0x0400);
+        assert_eq!(f32_to_f16(1023.0 * 2f32.powi(-24)), 0x03ff);
+        assert_eq!(f32_to_f16(2f32.powi(-24)), 0x0001);
+        assert_eq!(f32_to_f16(-2f32.powi(-24)), 0x8001);
+        assert_eq!(f32_to_f16(2f32.powi(-25)), 0x0000);
+        assert_eq!(f32_to_f16(1.5 * 2f32.powi(-25)), 0x0001);
+        assert_eq!(f32_to_f16(2f32.powi(-26)), 0x0000);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x8001), -2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x03ff), 1023.0 * 2f32.powi(-24));
+        assert!(f16_to_f32(0x8000).is_sign_negative());
+    }
+
+    #[test]
+    fn f16_infinity_and_nan() {
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7c00);
+        assert_eq!(f32_to_f16(f32::NEG_INFINITY), 0xfc00);
+        assert_eq!(f32_to_f16(1e10), 0x7c00);
+        assert_eq!(f32_to_f16(f32::NAN) & 0x7c00, 0x7c00);
+        assert_ne!(f32_to_f16(f32::NAN) & 0x03ff, 0);
+
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+        assert!(f16_to_f32(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn every_f16_round_trips() {
+        for half in 0..=u16::MAX {
+            let value = f16_to_f32(half);
+            if value.is_nan() {
+                assert!(f16_to_f32(f32_to_f16(value)).is_nan(), "{:#06x}", half);
+            } else {
+                assert_eq!(f32_to_f16(value), half, "{:#06x}", half);
+            }
+        }
+    }
+
+    const LIMIT: NSUInteger = 31;
+
+    fn float4_layout() -> VertexLayout {
+        let mut layout = VertexLayout::new();
+        layout.set_layout(0, VertexBufferLayout::per_vertex(16));
+        layout.set_attribute(
+            0,
+            VertexLayoutAttribute {
+                format: MTLVertexFormat::Float4,
+                offset: 0,
+                buffer_index: 0,
+            },
+        );
+        layout
+    }
+
+    #[test]
+    fn valid_layout() {
+        let mut layout = float4_layout();
+        layout.set_layout(1, VertexBufferLayout::per_instance(4, 3));
+        layout.set_attribute(
+            1,
+            VertexLayoutAttribute {
+                format: MTLVertexFormat::UChar4Normalized,
+                offset: 0,
+                buffer_index: 1,
+            },
+        );
+        assert_eq!(layout.validate(LIMIT), Ok(()));
+    }
+
+    #[test]
+    fn attribute_index_out_of_range() {
+        let mut layout = float4_layout();
+        let attribute = layout.attributes[&0];
+        layout.set_attribute(8, attribute);
+        assert_eq!(layout.validate(LIMIT), Ok(()));
+        assert_eq!(
+            layout.validate(8),
+            Err(VertexLayoutError::AttributeIndexOutOfRange {
+                attribute: 8,
+                limit: 8
+            })
+        );
+    }
+
+    #[test]
+    fn buffer_index_out_of_range() {
+        let mut layout = float4_layout();
+        layout.set_layout(LIMIT, VertexBufferLayout::per_vertex(16));
+        assert_eq!(
+            layout.validate(LIMIT),
+            Err(VertexLayoutError::BufferIndexOutOfRange {
+                buffer_index: LIMIT,
+                limit: LIMIT
+            })
+        );
+
+        let mut layout = float4_layout();
+        layout.attributes.get_mut(&0).unwrap().buffer_index = 40;
+        assert_eq!(
+            layout.validate(LIMIT),
+            Err(VertexLayoutError::BufferIndexOutOfRange {
+                buffer_index: 40,
+                limit: LIMIT
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_format() {
+        let mut layout = float4_layout();
+        layout.attributes.get_mut(&0).unwrap().format = MTLVertexFormat::Invalid;
+        assert_eq!(
+            layout.validate(LIMIT),
+            Err(VertexLayoutError::InvalidFormat { attribute: 0 })
+        );
+    }
+
+    #[test]
+    fn missing_buffer_layout() {
+        let mut layout = float4_layout();
+        layout.attributes.get_mut(&0).unwrap().buffer_index = 2;
+        assert_eq!(
+            layout.validate(LIMIT),
+            Err(VertexLayoutError::MissingBufferLayout {
+                attribute: 0,
+                buffer_index: 2
+            })
+        );
+    }
+
+    #[test]
+    fn misaligned_offset() {
+        let mut layout = float4_layout();
+        layout.layouts.get_mut(&0).unwrap().stride = 32;
+        layout.attributes.get_mut(&0).unwrap().offset = 6;
+        assert_eq!(
+            layout.validate(LIMIT),
+            Err(VertexLayoutError::MisalignedOffset {
+                attribute: 0,
+                offset: 6,
+                alignment: 4
+            })
+        );
+    }
+
+    #[test]
+    fn attribute_exceeds_stride() {
+        let mut layout = float4_layout();
+        layout.attributes.get_mut(&0).unwrap().offset = 4;
+        assert_eq!(
+            layout.validate(LIMIT),
+            Err(VertexLayoutError::AttributeExceedsStride {
+                attribute: 0,
+                end: 20,
+                stride: 16
+            })
+        );
+
+        // Constant layouts read one element for every vertex, so the stride does not apply.
+        let mut layout = float4_layout();
+        layout.set_layout(
+            0,
+            VertexBufferLayout {
+                stride: 0,
+                step_function: MTLVertexStepFunction::Constant,
+                step_rate: 0,
+            },
+        );
+        assert_eq!(layout.validate(LIMIT), Ok(()));
+    }
+
+    #[test]
+    fn offset_overflow() {
+        let mut layout = float4_layout();
+        let offset = NSUInteger::MAX - 3;
+        layout.attributes.get_mut(&0).unwrap().offset = offset;
+        assert_eq!(
+            layout.validate(LIMIT),
+            Err(VertexLayoutError::OffsetOverflow {
+                attribute: 0,
+                offset
+            })
+        );
+    }
+
+    #[test]
+    fn unaligned_stride() {
+        let mut layout = float4_layout();
+        layout.layouts.get_mut(&0).unwrap().stride = 18;
+        assert_eq!(
+            layout.validate(LIMIT),
+            Err(VertexLayoutError::UnalignedStride {
+                buffer_index: 0,
+                stride: 18
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_step_rate() {
+        for &(step_function, step_rate) in &[
+            (MTLVertexStepFunction::Constant, 1),
+            (MTLVertexStepFunction::PerInstance, 0),
+            (MTLVertexStepFunction::PerVertex, 2),
+            (MTLVertexStepFunction::PerPatch, 0),
+        ] {
+            let mut layout = float4_layout();
+            layout.set_layout(
+                0,
+                VertexBufferLayout {
+                    stride: 16,
+                    step_function,
+                    step_rate,
+                },
+            );
+            assert_eq!(
+                layout.validate(LIMIT),
+                Err(VertexLayoutError::InvalidStepRate {
+                    buffer_index: 0,
+                    step_function,
+                    step_rate
+                })
+            );
+        }
+    }
+}